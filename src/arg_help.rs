@@ -23,16 +23,39 @@ macro_rules! file_arg_doc {
     () => {
         "Check for procrastinations in the given file.
 
+A leading `~` as well as `$VAR` and `${VAR}` are expanded
+using the environment.
+
 This is ignored if `local` is set."
     };
 }
 
+#[macro_export]
+macro_rules! group_arg_doc {
+    () => {
+        "Only use procrastinations in this group.
+
+Keys in a group are prefixed with the group name, e.g. `work/standup`."
+    };
+}
+
+#[macro_export]
+macro_rules! max_age_arg_doc {
+    () => {
+        "Remove one-time procrastinations without notifying
+if they are overdue by more than this delay."
+    };
+}
+
 const DELAY_TIMING_ARG_DOC: &str = "DELAY: a combination of the following intervals.
     Any interval must be in the format \"<n><tag>\" without a space between
-    the number and tag. There must a a space between intervals.
+    the number and tag. There must be a space between intervals.
     
     The tags are (year, y), (months, M), (weeks, w), (days, d), (hours, h),
     (min, m), (sec, s).
+    Months and years are calendar months, e.g. \"1M\" on the 15th notifies
+    on the 15th of the next month. With --delay-unit-precision approximate
+    months are 30 days and years 365 days.
 
     e.g: 5m 3s
          1M 2d 7m";
@@ -50,6 +73,9 @@ INSTANT: can be one of the following
     Any Date: \"y-M-d[ h:m[:s]]\"
               \"d-M[ h:m[:s]]\"
     Any Month: january, february, etc
+    Sunrise or Sunset: sunrise, sunset
+        - can be followed by an offset, e.g \"sunset -30m\" or \"sunrise +1h\"
+        - needs a location in the config file
 
 ",
     DELAY_TIMING_ARG_DOC
@@ -60,11 +86,17 @@ pub const REPEAT_TIMING_ARG_DOC: &str = constcat::concat!(
 
 INSTANT: Can be one of the following
     daily 
+        - without a time it notifies at the time of day it was created
         - can be optionally be followed by a time [h:m[:s]], e.g \"daily 10:11\"
+        - or multiple comma separated times, e.g \"daily 9:00,17:00\"
+        - or a time range to notify at a different time within it each day,
+          e.g \"daily 9:00-11:00\"
     day of week: monday, tuesday, etc
         - can be optionally be followed by a time [h:m[:s]], e.g \"friday 16:20\"
     monthly <day>
         - can be optionally be followed by a time [h:m[:s]], e.g \"monthly 5 10:11\"
+    cron <expression>
+        - a cron expression, e.g \"cron 0 9 * * 1-5\" or cron \"0 9 * * 1-5\"
 
 ",
     DELAY_TIMING_ARG_DOC
@@ -1,13 +1,27 @@
 use core::panic;
-use std::path::PathBuf;
+use std::{
+    io::{IsTerminal, Read},
+    path::{Path, PathBuf},
+    str::FromStr,
+};
 
-use clap::{Args, Parser};
+use chrono::{Local, NaiveDate, NaiveDateTime, NaiveTime};
+use clap::{Args, Parser, ValueEnum};
 use procrastinate::{
     arg_help::{ONCE_TIMING_ARG_DOC, REPEAT_TIMING_ARG_DOC},
-    file_arg_doc, local_arg_doc,
-    time::{OnceTiming, Repeat, RepeatTiming},
-    Procrastination,
+    config::{Config, TemplateDef},
+    file_arg_doc, group_arg_doc, group_key, jitter_offset,
+    list_format::ListFormat,
+    local_arg_doc,
+    locale::Locale,
+    resolve_once_timing,
+    time::{
+        parse_local_datetime, parse_time_of_day, AlignUnit, Calendar, Delay, DelayPrecision,
+        OnceTiming, Repeat, RepeatTiming, RoughInstant, TimeError, WeekStart, MISSING_TIMING,
+    },
+    OnConflict, Priority, Procrastination, SummaryOverflow,
 };
+use regex::Regex;
 
 #[derive(Parser, Debug)]
 #[command(version, about)]
@@ -27,6 +41,42 @@ pub struct Arguments {
     #[arg(short, long, help = file_arg_doc!())]
     pub file: Option<PathBuf>,
 
+    #[arg(long, global = true, help = group_arg_doc!())]
+    pub group: Option<String>,
+
+    /// The language of weekday and month names in the output, e.g. `de_DE`.
+    ///
+    /// Defaults to `LC_ALL`, `LC_TIME` or `LANG`. Supported are en, de, fr and es.
+    #[arg(long, global = true, visible_alias = "output-locale")]
+    pub locale: Option<Locale>,
+
+    /// The first day of the week, overriding the config file.
+    ///
+    /// Decides e.g. if "sunday" refers to the past or the end of the current week.
+    #[arg(long, global = true, value_enum)]
+    pub week_start: Option<WeekStart>,
+
+    /// How months and years in delays are added, e.g. in `1M` or `1M 36h`.
+    ///
+    /// `calendar`, the default, adds calendar months, so `1M` after the 15th is the 15th
+    /// of the next month. `approximate` adds 30 days per month and 365 days per year.
+    /// This applies to the timings of `once`, `repeat` and `sleep`.
+    #[arg(long, global = true, value_enum)]
+    pub delay_unit_precision: Option<DelayPrecision>,
+
+    /// Load `PROCRASTINATE_*` variables from a file of `KEY=VALUE` lines.
+    ///
+    /// Supported are `PROCRASTINATE_ICON`, `PROCRASTINATE_DATA_DIR` and `PROCRASTINATE_NO_STICKY`.
+    /// Variables that are already set take precedence, command line flags over both.
+    #[arg(long, global = true, value_name = "PATH")]
+    pub env_file: Option<PathBuf>,
+
+    /// Copy the procrastination file to `<file>.bak` before saving it.
+    ///
+    /// Keeps as many backups as set in the config file, but at least one.
+    #[arg(long, global = true)]
+    pub backup: bool,
+
     #[arg(short, long)]
     pub verbose: bool,
 }
@@ -35,13 +85,79 @@ pub struct Arguments {
 pub struct NotificationArgs {
     /// the title that will be displayed when the procrastination is over.
     ///
-    /// Defaults to `key`
+    /// Defaults to `key`. Use `-` to read the title from stdin.
     #[arg(short, long)]
     pub title: Option<String>,
 
-    /// A short message that will be displayed when the procrastination is over
+    /// A short message that will be displayed when the procrastination is over.
+    ///
+    /// Use `-` to read the message from stdin.
     #[arg(short, long)]
     pub message: Option<String>,
+
+    /// Only display the title of the notification, even if a message is set
+    #[arg(long)]
+    pub no_body: bool,
+
+    /// Use the key as message if no message is given, instead of an empty body
+    #[arg(long, conflicts_with = "no_body")]
+    pub message_append_key: bool,
+
+    /// Render simple markdown (bold, italic, links) in the message,
+    /// if the notification server supports it
+    #[arg(long)]
+    pub markdown: bool,
+
+    /// Use the title, message, timing and sticky flag of a template from the config file
+    /// unless they are given explicitly
+    #[arg(long)]
+    pub template: Option<String>,
+
+    /// Delay notifications by a fixed offset of up to the given delay.
+    ///
+    /// The offset is derived from the key, spreading out procrastinations with the same timing.
+    #[arg(long, value_name = "DELAY")]
+    pub jitter: Option<Delay>,
+
+    /// The urgency of the notification. Critical notifications are shown
+    /// even if do not disturb is enabled
+    #[arg(long, value_enum, default_value_t = Priority::Normal)]
+    pub priority: Priority,
+
+    /// The icon of the notification, either a name from the icon theme or a path.
+    ///
+    /// Defaults to the icon for the priority in the config file.
+    #[arg(long, env = "PROCRASTINATE_ICON")]
+    pub icon: Option<String>,
+
+    /// Never show the notification until it is dismissed, even if sticky or the template is
+    #[arg(long, env = "PROCRASTINATE_NO_STICKY")]
+    pub no_sticky: bool,
+
+    /// Shorten titles longer than the given number of characters in the notification.
+    ///
+    /// The full title is shown at the start of the body.
+    #[arg(long, value_name = "CHARS")]
+    pub max_summary: Option<usize>,
+
+    /// Move the words of the title that don't fit --max-summary into the body
+    /// instead of repeating the full title
+    #[arg(long, requires = "max_summary")]
+    pub move_overflow: bool,
+
+    /// Never notify before the given time, even if the timing would
+    #[arg(long, value_name = "TIMING")]
+    pub start: Option<OnceTiming>,
+
+    /// Don't notify on the given date, e.g. a holiday. Can be given multiple times.
+    ///
+    /// Only affects repeating procrastinations.
+    #[arg(long = "skip-date", value_name = "YYYY-MM-DD")]
+    pub skip_dates: Vec<NaiveDate>,
+
+    /// Print when the procrastination would notify instead of saving it
+    #[arg(long, visible_alias = "dry-run")]
+    pub explain: bool,
 }
 
 impl Arguments {
@@ -49,47 +165,273 @@ impl Arguments {
         if self.local && self.file.is_some() {
             return Err("'local' and 'file' are mutually exclusive".to_string());
         }
+        if self.group.as_ref().is_some_and(|group| group.is_empty()) {
+            return Err("'group' can't be empty".to_string());
+        }
+        Ok(())
+    }
+
+    /// How months and years of delays in timings are added, see [Arguments::delay_unit_precision]
+    pub fn delay_precision(&self) -> DelayPrecision {
+        self.delay_unit_precision.unwrap_or_default()
+    }
+
+    /// `key` within the `group`, if one is set.
+    pub fn key(&self, key: &str) -> String {
+        group_key(self.group.as_deref(), key)
+    }
+
+    /// Replaces a title or message of `-` with the content of `stdin`.
+    ///
+    /// Only one of them can be read from `stdin`.
+    pub fn read_stdin_args(&mut self, mut stdin: impl Read) -> Result<(), String> {
+        let args = match &mut self.cmd {
+            Cmd::Once { args, .. } | Cmd::Repeat { args, .. } => args,
+            _ => return Ok(()),
+        };
+        let is_stdin = |arg: &Option<String>| arg.as_deref() == Some("-");
+
+        let arg = match (is_stdin(&args.title), is_stdin(&args.message)) {
+            (true, true) => {
+                return Err("title and message can't both be read from stdin".to_string())
+            }
+            (true, false) => &mut args.title,
+            (false, true) => &mut args.message,
+            (false, false) => return Ok(()),
+        };
+
+        let mut content = String::new();
+        stdin
+            .read_to_string(&mut content)
+            .map_err(|e| format!("failed to read from stdin: {e}"))?;
+        let content = content.strip_suffix('\n').unwrap_or(&content);
+        let content = content.strip_suffix('\r').unwrap_or(content);
+        *arg = Some(content.to_string());
         Ok(())
     }
 
-    pub fn procrastination(&self) -> Procrastination {
-        let (key, args, timing, sticky) = match &self.cmd {
+    /// Uses the variables of `env_file` for all arguments that are neither given
+    /// on the command line nor set in the environment.
+    ///
+    /// Returns the lines that were ignored, see [EnvFile::ignored].
+    pub fn apply_env_file(&mut self, env_file: EnvFile) -> Vec<String> {
+        let mut ignored = env_file.ignored;
+        for (key, value) in env_file.vars {
+            if std::env::var_os(&key).is_some() {
+                continue;
+            }
+            match key.as_str() {
+                "PROCRASTINATE_ICON" => {
+                    if let Some(args) = self.notification_args_mut() {
+                        args.icon.get_or_insert(value);
+                    }
+                }
+                "PROCRASTINATE_NO_STICKY" => {
+                    if let Some(args) = self.notification_args_mut() {
+                        args.no_sticky |= !matches!(
+                            value.to_lowercase().as_str(),
+                            "" | "0" | "n" | "no" | "f" | "false" | "off"
+                        );
+                    }
+                }
+                "PROCRASTINATE_DATA_DIR" => {
+                    if !self.local && self.file.is_none() {
+                        self.file = Some(PathBuf::from(value).join(procrastinate::FILE_NAME));
+                    }
+                }
+                _ => ignored.push(format!("env file: ignoring unsupported \"{key}\"")),
+            }
+        }
+        ignored
+    }
+
+    /// The [NotificationArgs] of the once and repeat cmd
+    pub fn notification_args(&self) -> Option<&NotificationArgs> {
+        match &self.cmd {
+            Cmd::Once { args, .. } | Cmd::Repeat { args, .. } => Some(args),
+            _ => None,
+        }
+    }
+
+    fn notification_args_mut(&mut self) -> Option<&mut NotificationArgs> {
+        match &mut self.cmd {
+            Cmd::Once { args, .. } | Cmd::Repeat { args, .. } => Some(args),
+            _ => None,
+        }
+    }
+
+    /// The [Calendar] of `config`, overridden by `--week-start` and `--locale`.
+    pub fn calendar(&self, config: &Config) -> Calendar {
+        let mut calendar = config.calendar();
+        if let Some(week_start) = self.week_start {
+            calendar.week_start = week_start;
+        }
+        if let Some(locale) = self.locale {
+            calendar.locale = locale;
+        }
+        calendar
+    }
+
+    pub fn procrastination(&self, config: &Config) -> Result<Procrastination, String> {
+        let (key, args, sticky) = match &self.cmd {
+            Cmd::Once {
+                key, args, sticky, ..
+            }
+            | Cmd::Repeat {
+                key, args, sticky, ..
+            } => (key, args, sticky),
+            _ => panic!("new procrastinations can only be created from once or repeat cmd"),
+        };
+
+        let template = match args.template.as_ref() {
+            Some(name) => config
+                .template(name)
+                .ok_or_else(|| format!("No template with name \"{name}\" exists"))?
+                .clone(),
+            None => TemplateDef::default(),
+        };
+
+        let precision = self.delay_precision();
+        let timing = match &self.cmd {
             Cmd::Once {
-                key,
-                timing,
-                args,
-                sticky,
-            } => (
-                key,
-                args,
-                Repeat::Once {
-                    timing: timing.clone(),
+                timing, at, strict, ..
+            } => Repeat::Once {
+                timing: match (timing, at) {
+                    (Some(timing), _) if *strict => {
+                        OnceTiming::from_str_strict(&timing.input, precision)
+                            .map_err(|e| format!("invalid timing \"{}\": {e}", timing.input))?
+                    }
+                    (Some(timing), _) => timing.timing(precision)?,
+                    (None, Some(at)) => OnceTiming::Instant(RoughInstant::Date { date: *at }),
+                    (None, None) => {
+                        parse_template_timing(&template, args.template.as_deref(), |timing| {
+                            OnceTiming::from_str_with(timing, precision)
+                        })?
+                    }
                 },
-                sticky,
-            ),
+            },
             Cmd::Repeat {
-                key,
-                timing,
-                args,
-                sticky,
-            } => (
-                key,
-                args,
-                Repeat::Repeat {
-                    timing: timing.clone(),
-                },
-                sticky,
-            ),
-            Cmd::Done { .. } | Cmd::List { .. } | Cmd::Sleep { .. } => {
-                panic!("can't create new procrastination from done, list or sleep cmd")
+                timing, at, align, ..
+            } => {
+                let timing = match timing.is_empty() {
+                    false => {
+                        let input = timing.join(" ");
+                        RepeatTiming::from_str_with(&input, precision)
+                            .map_err(|e| format!("invalid timing \"{input}\": {e}"))?
+                    }
+                    true => parse_template_timing(&template, args.template.as_deref(), |timing| {
+                        RepeatTiming::from_str_with(timing, precision)
+                    })?,
+                };
+                let timing = match (timing, at) {
+                    (RepeatTiming::Delay(delay), Some(at)) => {
+                        RepeatTiming::AnchoredDelay { delay, at: *at }
+                    }
+                    (_, Some(_)) => return Err("'at' can only be used with a delay".to_string()),
+                    (timing, None) => timing,
+                };
+                let timing = match (timing, align) {
+                    (RepeatTiming::Delay(delay), Some(align)) => RepeatTiming::AlignedDelay {
+                        delay,
+                        align: *align,
+                    },
+                    (_, Some(_)) => return Err("'align' can only be used with a delay".to_string()),
+                    (timing, None) => timing,
+                };
+                Repeat::Repeat { timing }
             }
+            _ => unreachable!(),
         };
-        Procrastination::new(
-            args.title.clone().unwrap_or(key.clone()),
-            args.message.clone().unwrap_or(String::new()),
+        if let Repeat::Once {
+            timing: OnceTiming::Instant(RoughInstant::Sunrise { .. } | RoughInstant::Sunset { .. }),
+        } = &timing
+        {
+            if config.location.is_none() {
+                return Err(TimeError::NoLocation.to_string());
+            }
+        }
+
+        let mut procrastination = Procrastination::new(
+            args.title.clone().or(template.title).unwrap_or(key.clone()),
+            args.message
+                .clone()
+                .or(template.message)
+                .or_else(|| args.message_append_key.then(|| key.clone()))
+                .unwrap_or(String::new()),
             timing,
-            *sticky,
-        )
+            sticky.unwrap_or(template.sticky) && !args.no_sticky,
+        );
+        procrastination.no_body = args.no_body;
+        procrastination.markdown = args.markdown;
+        procrastination.priority = args.priority;
+        procrastination.icon = args.icon.clone();
+        procrastination.skip_dates = args.skip_dates.clone();
+        procrastination.summary_overflow = args.max_summary.map(|max| match args.move_overflow {
+            true => SummaryOverflow::MoveToBody { max },
+            false => SummaryOverflow::Truncate { max },
+        });
+        if let Cmd::Once {
+            keep, require_ack, ..
+        } = &self.cmd
+        {
+            procrastination.keep = *keep;
+            procrastination.require_ack = *require_ack;
+        }
+        if let Some(start) = args.start.as_ref() {
+            let now = Local::now().naive_local();
+            let start = resolve_once_timing(start, now, &self.calendar(config))
+                .map_err(|e| format!("invalid start \"{start:?}\": {e}"))?;
+            procrastination.start_after = Some(start);
+        }
+        if let Some(jitter) = args.jitter {
+            procrastination.offset = jitter_offset(key, jitter);
+        }
+        Ok(procrastination)
+    }
+}
+
+/// A valid [OnceTiming] as it was given, so it can be parsed again with the
+/// [DelayPrecision] of the arguments or with [OnceTiming::from_str_strict].
+#[derive(Debug, Clone)]
+pub struct OnceTimingArg {
+    pub input: String,
+}
+
+impl OnceTimingArg {
+    /// The timing, adding months and years of delays with `precision`
+    pub fn timing(&self, precision: DelayPrecision) -> Result<OnceTiming, String> {
+        OnceTiming::from_str_with(&self.input, precision)
+            .map_err(|e| format!("invalid timing \"{}\": {e}", self.input))
+    }
+}
+
+impl FromStr for OnceTimingArg {
+    type Err = nom::Err<String>;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        OnceTiming::from_str(s)?;
+        Ok(Self {
+            input: s.to_string(),
+        })
+    }
+}
+
+/// The timing of `template` parsed with `parse`, used if no timing is given.
+///
+/// `name` is the name of the template, if one was given.
+fn parse_template_timing<T>(
+    template: &TemplateDef,
+    name: Option<&str>,
+    parse: impl Fn(&str) -> Result<T, nom::Err<String>>,
+) -> Result<T, String> {
+    match (template.timing.as_ref(), name) {
+        (Some(timing), _) => {
+            parse(timing).map_err(|e| format!("invalid timing \"{timing}\" in template: {e}"))
+        }
+        (None, Some(name)) => Err(format!(
+            "a timing is required, the template \"{name}\" does not specify one"
+        )),
+        (None, None) => Err(MISSING_TIMING.to_string()),
     }
 }
 
@@ -101,30 +443,69 @@ pub enum Cmd {
         key: String,
 
         #[arg(help = ONCE_TIMING_ARG_DOC)]
-        timing: OnceTiming,
+        timing: Option<OnceTimingArg>,
+        /// Reject instants that are not fully specified, e.g. "14-12" without a year.
+        ///
+        /// Instants need a date and time like "2025-12-14 09:00", delays are always accepted.
+        #[arg(long)]
+        strict: bool,
+        /// Notify at the given local time, e.g. 2025-03-01T09:00 or 2025-03-01T09:00:00+01:00
+        #[arg(long, value_parser = parse_local_datetime, conflicts_with = "timing")]
+        at: Option<NaiveDateTime>,
         #[command(flatten)]
         args: NotificationArgs,
-        /// If set any any notification must be explicitly dismissed
-        #[arg(short, long)]
-        sticky: bool,
+        /// If set any any notification must be explicitly dismissed.
+        ///
+        /// Overrides the sticky flag of a template, e.g. `--sticky=false`.
+        #[arg(short, long, num_args = 0..=1, default_missing_value = "true", require_equals = true)]
+        sticky: Option<bool>,
+        /// Keep the procrastination after the notification, marked as done
+        #[arg(long)]
+        keep: bool,
+        /// Notify on every check until the procrastination is removed with `done`
+        #[arg(long)]
+        require_ack: bool,
     },
     /// procrastination is only great when doing it again and again
     Repeat {
         /// A key to identify this procrastination
         key: String,
 
-        #[arg(help = REPEAT_TIMING_ARG_DOC)]
-        timing: RepeatTiming,
+        #[arg(help = REPEAT_TIMING_ARG_DOC, value_name = "TIMING", num_args = 1..)]
+        timing: Vec<String>,
         #[command(flatten)]
         args: NotificationArgs,
-        /// If set any any notification must be explicitly dismissed
-        #[arg(short, long)]
-        sticky: bool,
+        /// If set any any notification must be explicitly dismissed.
+        ///
+        /// Overrides the sticky flag of a template, e.g. `--sticky=false`.
+        #[arg(short, long, num_args = 0..=1, default_missing_value = "true", require_equals = true)]
+        sticky: Option<bool>,
+        /// Align a repeating delay to this time of day [h:m[:s]]
+        #[arg(long, value_parser = parse_time_of_day)]
+        at: Option<NaiveTime>,
+        /// Notify a repeating delay on the hour or at midnight instead of drifting from
+        /// the time it was created
+        #[arg(long, value_enum, conflicts_with = "at")]
+        align: Option<AlignUnit>,
+        /// Restart the cadence from now when replacing an existing repeating procrastination.
+        ///
+        /// By default the time of the last notification is kept.
+        #[arg(long)]
+        reset_timestamp: bool,
     },
-    /// stop procrastinating on a given taks
+    /// stop procrastinating on the given tasks
     Done {
-        /// A key to identify this procrastination
-        key: String,
+        /// Keys to identify the procrastinations
+        #[arg(required_unless_present = "regex", conflicts_with = "regex")]
+        keys: Vec<String>,
+
+        /// Remove all procrastinations whose key matches the regex, e.g. "^temp-"
+        #[arg(long, value_name = "PATTERN")]
+        regex: Option<Regex>,
+
+        /// Confirm removing the procrastinations matching `regex`
+        #[arg(long, requires = "regex")]
+        yes: bool,
     },
     /// List all tasks you are procrastinating
     List {
@@ -140,10 +521,600 @@ pub enum Cmd {
         /// instead of the sensible day.month format
         #[arg(long, short)]
         us_date: bool,
+
+        /// only list procrastinations that are due within the given delay
+        #[arg(long, value_name = "DELAY")]
+        due_within: Option<Delay>,
+
+        /// also list paused procrastinations, which are hidden by default
+        #[arg(long)]
+        include_paused: bool,
+
+        /// only list paused procrastinations
+        #[arg(long, conflicts_with = "include_paused")]
+        paused_only: bool,
+
+        /// highlight overdue and sleeping procrastinations
+        #[arg(long, value_enum, default_value_t = ColorMode::Auto)]
+        color: ColorMode,
+
+        /// list the procrastination with the latest next notification first
+        #[arg(long)]
+        reverse: bool,
+
+        /// print each procrastination on one line using the given format,
+        /// e.g. "{key}: {title} ({next})"
+        ///
+        /// Available placeholders are {key}, {title}, {message}, {next}, {created}
+        /// and {flags}. Use {{ and }} for literal braces.
+        #[arg(long)]
+        format: Option<ListFormat>,
+
+        /// print each procrastination on one line as `key  next  title`
+        #[arg(long, conflicts_with = "format")]
+        compact: bool,
+
+        /// print the procrastinations as json: `{"schema": 1, "entries": [...]}`
+        ///
+        /// Each entry contains key, title, message, next, created and flags.
+        /// The schema is increased on incompatible changes.
+        #[arg(long, conflicts_with_all = ["format", "compact"])]
+        json: bool,
+
+        /// add the structured timing of each procrastination to the --json output
+        #[arg(long, requires = "json")]
+        flatten_timing: bool,
+
+        /// print only the keys, one per line
+        #[arg(long, conflicts_with_all = ["format", "compact", "json"])]
+        key_only: bool,
+
+        /// show the next notification relative to now, e.g. "in 2h" or "in 5 weeks"
+        #[arg(long, conflicts_with_all = ["format", "json"])]
+        relative: bool,
+
+        /// truncate titles of the compact output to fit within the given width
+        #[arg(long, requires = "compact")]
+        width: Option<usize>,
+
+        /// print only the next procrastination as "title (in 2h)", e.g. for status bars
+        ///
+        /// The line is empty if nothing is upcoming.
+        #[arg(long, conflicts_with_all = ["format", "compact", "json", "key_only"])]
+        bar: bool,
+
+        /// add a second line listing all procrastinations to the --bar output
+        #[arg(long, requires = "bar")]
+        tooltip: bool,
+
+        /// print every notification of the next --days as an iCalendar event
+        #[arg(long, conflicts_with_all = ["format", "compact", "json", "key_only", "bar"])]
+        agenda_ics: bool,
+
+        /// the number of days of the --agenda-ics output, including today
+        #[arg(long, requires = "agenda_ics", default_value_t = 30)]
+        days: u32,
+    },
+    /// List upcoming notifications for the next days, grouped by day
+    Agenda {
+        /// the number of days to show, including today
+        #[arg(default_value_t = 7)]
+        days: u32,
+
+        /// print dates with the wrong month.day format
+        /// instead of the sensible day.month format
+        #[arg(long, short)]
+        us_date: bool,
+    },
+    /// Insert all procrastinations of another file into this one
+    Merge {
+        /// the file to merge into this one
+        from: PathBuf,
+
+        /// what to do with procrastinations whose key already exists
+        #[arg(long, value_enum, default_value_t = OnConflict::Skip)]
+        on_conflict: OnConflict,
+
+        /// replace all procrastinations of this file instead of merging, e.g. to restore a backup
+        ///
+        /// Nothing is changed if any imported procrastination is invalid.
+        #[arg(long, conflicts_with = "on_conflict")]
+        replace_file: bool,
+    },
+    /// Import cron jobs as repeating procrastinations
+    ///
+    /// Each line `min hour day-of-month month day-of-week command # title` creates
+    /// a procrastination with the command as message and the comment as title.
+    Import {
+        /// the crontab file to import
+        #[arg(long)]
+        crontab: PathBuf,
+
+        /// what to do with procrastinations whose key already exists
+        #[arg(long, value_enum, default_value_t = OnConflict::Skip)]
+        on_conflict: OnConflict,
+
+        /// replace all procrastinations of this file instead of merging, e.g. to restore a backup
+        ///
+        /// Nothing is changed if any imported procrastination is invalid.
+        #[arg(long, conflicts_with = "on_conflict")]
+        replace_file: bool,
+    },
+    /// Send a test notification to check that notifications work
+    TestNotify,
+    /// Print the path of the procrastination file and exit.
+    ///
+    /// The path is resolved from `--local`, `--file` or the default data directory.
+    #[command(long_flag = "print-path")]
+    PrintPath,
+    /// Print the version of procrastinate
+    Version {
+        /// also print the schema version of the procrastination file
+        #[arg(long)]
+        file: bool,
     },
+    /// Check that the timing of all procrastinations is valid
+    ///
+    /// Exits with an error if any procrastination is invalid.
+    Check,
     Sleep {
         /// A key to identify this procrastination
         key: String,
-        timing: OnceTiming,
+
+        #[arg(help = ONCE_TIMING_ARG_DOC, required_unless_present = "clear")]
+        timing: Option<OnceTimingArg>,
+
+        /// wake the procrastination early, restoring its normal schedule
+        #[arg(long, conflicts_with = "timing")]
+        clear: bool,
+    },
+    /// Stop notifying a procrastination until it is resumed
+    ///
+    /// Paused procrastinations are hidden from `list` unless --include-paused is given.
+    Pause {
+        /// A key to identify this procrastination
+        key: String,
+    },
+    /// Notify a paused procrastination again
+    Resume {
+        /// A key to identify this procrastination
+        key: String,
     },
 }
+
+/// Which procrastinations `list` shows, depending on whether they are paused
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PausedFilter {
+    Hide,
+    Include,
+    Only,
+}
+
+impl PausedFilter {
+    pub fn new(include_paused: bool, paused_only: bool) -> Self {
+        match (include_paused, paused_only) {
+            (_, true) => PausedFilter::Only,
+            (true, false) => PausedFilter::Include,
+            (false, false) => PausedFilter::Hide,
+        }
+    }
+
+    /// returns `true` if a procrastination that is `paused` is listed
+    pub fn shows(self, paused: bool) -> bool {
+        match self {
+            PausedFilter::Hide => !paused,
+            PausedFilter::Include => true,
+            PausedFilter::Only => paused,
+        }
+    }
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorMode {
+    /// use colors if stdout is a terminal
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorMode {
+    pub fn enabled(self) -> bool {
+        match self {
+            ColorMode::Auto => std::io::stdout().is_terminal(),
+            ColorMode::Always => true,
+            ColorMode::Never => false,
+        }
+    }
+}
+
+/// The variables of an env file, see [parse_env_file]
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct EnvFile {
+    pub vars: Vec<(String, String)>,
+    /// Why lines with other variables were skipped, e.g. `env file line 3: ignoring "HOME"`
+    pub ignored: Vec<String>,
+}
+
+/// Reads the env file at `path`, see [parse_env_file]
+pub fn read_env_file(path: &Path) -> Result<EnvFile, String> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format!("failed to read env file {path:?}: {e}"))?;
+    parse_env_file(&content)
+}
+
+/// The `PROCRASTINATE_*` variables of a file of `KEY=VALUE` lines.
+///
+/// Empty lines and lines starting with `#` are ignored, values may be quoted.
+/// Other variables are skipped and reported in [EnvFile::ignored].
+pub fn parse_env_file(content: &str) -> Result<EnvFile, String> {
+    let mut env_file = EnvFile::default();
+    for (i, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| format!("env file line {}: expected KEY=VALUE", i + 1))?;
+        let key = key.trim();
+        if !key.starts_with("PROCRASTINATE_") {
+            env_file
+                .ignored
+                .push(format!("env file line {}: ignoring \"{key}\"", i + 1));
+            continue;
+        }
+        let value = value.trim();
+        let value = ['"', '\'']
+            .into_iter()
+            .find_map(|quote| value.strip_prefix(quote)?.strip_suffix(quote))
+            .unwrap_or(value);
+        env_file.vars.push((key.to_string(), value.to_string()));
+    }
+    Ok(env_file)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn config() -> Config {
+        let mut config = Config::default();
+        config.templates.insert(
+            "meeting".to_string(),
+            TemplateDef {
+                title: Some("Meeting".to_string()),
+                message: Some("grab a coffee".to_string()),
+                timing: Some("daily 9:30".to_string()),
+                sticky: true,
+            },
+        );
+        config
+    }
+
+    fn parse(args: &[&str]) -> Arguments {
+        Arguments::try_parse_from(std::iter::once("procrastinate").chain(args.iter().copied()))
+            .unwrap()
+    }
+
+    fn procrastination(args: &[&str]) -> Result<Procrastination, String> {
+        parse(args).procrastination(&config())
+    }
+
+    #[test]
+    fn test_env_file() {
+        let content = "# scripted setup\nPROCRASTINATE_ICON=\"alarm clock\"\n\nexport PROCRASTINATE_DATA_DIR=/tmp/data\nHOME=/ignored\n";
+        let env_file = parse_env_file(content).unwrap();
+        assert_eq!(
+            env_file.vars,
+            [
+                ("PROCRASTINATE_ICON".to_string(), "alarm clock".to_string()),
+                (
+                    "PROCRASTINATE_DATA_DIR".to_string(),
+                    "/tmp/data".to_string()
+                ),
+            ]
+        );
+        assert_eq!(env_file.ignored, ["env file line 5: ignoring \"HOME\""]);
+        assert!(parse_env_file("PROCRASTINATE_ICON").is_err());
+    }
+
+    #[test]
+    fn test_print_path() {
+        let try_parse = |args: &[&str]| {
+            Arguments::try_parse_from(std::iter::once("procrastinate").chain(args.iter().copied()))
+        };
+        assert!(matches!(parse(&["--print-path"]).cmd, Cmd::PrintPath));
+        assert!(matches!(parse(&["-l", "print-path"]).cmd, Cmd::PrintPath));
+        assert!(try_parse(&["--print-path", "list"]).is_err());
+        assert!(try_parse(&[]).is_err());
+    }
+
+    #[test]
+    fn test_apply_env_file() {
+        let env_file = || {
+            parse_env_file(
+                "PROCRASTINATE_ICON=alarm\nPROCRASTINATE_NO_STICKY=1\nPROCRASTINATE_DATA_DIR=/tmp/data\nPROCRASTINATE_OTHER=1\n",
+            )
+            .unwrap()
+        };
+
+        let mut args = parse(&["once", "k", "tomorrow"]);
+        let ignored = args.apply_env_file(env_file());
+        assert_eq!(
+            ignored,
+            ["env file: ignoring unsupported \"PROCRASTINATE_OTHER\""]
+        );
+        let notification = args.notification_args().unwrap();
+        assert_eq!(notification.icon.as_deref(), Some("alarm"));
+        assert!(notification.no_sticky);
+        assert_eq!(
+            args.file,
+            Some(PathBuf::from("/tmp/data/procrastination.ron"))
+        );
+
+        // flags take precedence over the env file
+        let mut args = parse(&["--local", "once", "k", "tomorrow", "--icon", "bell"]);
+        args.apply_env_file(env_file());
+        assert_eq!(
+            args.notification_args().unwrap().icon.as_deref(),
+            Some("bell")
+        );
+        assert_eq!(args.file, None);
+    }
+
+    #[test]
+    fn test_read_stdin_args() {
+        let mut args = parse(&["once", "k", "tomorrow", "-m", "-"]);
+        args.read_stdin_args("body\n".as_bytes()).unwrap();
+        let proc = args.procrastination(&config()).unwrap();
+        assert_eq!(proc.message, "body");
+        assert_eq!(proc.title, "k");
+
+        let mut args = parse(&["once", "k", "tomorrow", "-t", "-", "-m", "message"]);
+        args.read_stdin_args("multi\nline title\n".as_bytes())
+            .unwrap();
+        let proc = args.procrastination(&config()).unwrap();
+        assert_eq!(proc.title, "multi\nline title");
+        assert_eq!(proc.message, "message");
+
+        let mut args = parse(&["once", "k", "tomorrow", "-t", "-", "-m", "-"]);
+        assert!(args.read_stdin_args("body".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_message_append_key() {
+        let proc = procrastination(&["once", "buy milk", "5m", "--message-append-key"]).unwrap();
+        assert_eq!(proc.message, "buy milk");
+
+        let proc = procrastination(&["once", "buy milk", "5m", "--message-append-key", "-m", "2l"])
+            .unwrap();
+        assert_eq!(proc.message, "2l");
+
+        let proc = procrastination(&["once", "buy milk", "5m"]).unwrap();
+        assert_eq!(proc.message, "");
+    }
+
+    #[test]
+    fn test_once_at() {
+        let proc = procrastination(&["once", "k", "--at", "2025-03-01T09:00"]).unwrap();
+        let date = chrono::NaiveDate::from_ymd_opt(2025, 3, 1)
+            .unwrap()
+            .and_hms_opt(9, 0, 0)
+            .unwrap();
+        assert_eq!(
+            proc.timing,
+            Repeat::Once {
+                timing: OnceTiming::Instant(RoughInstant::Date { date })
+            }
+        );
+
+        let args = [
+            "procrastinate",
+            "once",
+            "k",
+            "tomorrow",
+            "--at",
+            "2025-03-01T09:00",
+        ];
+        assert!(Arguments::try_parse_from(args).is_err());
+        let args = ["procrastinate", "once", "k", "--at", "tomorrow"];
+        assert!(Arguments::try_parse_from(args).is_err());
+    }
+
+    #[test]
+    fn test_missing_timing() {
+        assert_eq!(procrastination(&["once", "k"]).unwrap_err(), MISSING_TIMING);
+        assert_eq!(
+            procrastination(&["repeat", "k"]).unwrap_err(),
+            MISSING_TIMING
+        );
+
+        let err = Arguments::try_parse_from(["procrastinate", "once", "k", " "]).unwrap_err();
+        assert!(err.to_string().contains(MISSING_TIMING), "{err}");
+        let err = procrastination(&["repeat", "k", " "]).unwrap_err();
+        assert!(err.contains(MISSING_TIMING), "{err}");
+    }
+
+    #[test]
+    fn test_delay_unit_precision() {
+        let once = |delay| Repeat::Once {
+            timing: OnceTiming::Delay(delay),
+        };
+        let calendar = Delay::Calendar {
+            months: 1,
+            days: 1,
+            seconds: 12 * 3600,
+        };
+        let proc = procrastination(&["once", "k", "1M 36h"]).unwrap();
+        assert_eq!(proc.timing, once(calendar));
+
+        let args = [
+            "--delay-unit-precision",
+            "approximate",
+            "once",
+            "k",
+            "1M 36h",
+        ];
+        assert_eq!(
+            procrastination(&args).unwrap().timing,
+            once(Delay::Seconds(30 * 86400 + 36 * 3600))
+        );
+        let args = ["--delay-unit-precision", "approximate", "once", "k", "1M"];
+        assert_eq!(
+            procrastination(&args).unwrap().timing,
+            once(Delay::Days(30))
+        );
+        let args = [
+            "repeat",
+            "k",
+            "1M",
+            "36h",
+            "--delay-unit-precision",
+            "calendar",
+        ];
+        assert_eq!(
+            procrastination(&args).unwrap().timing,
+            Repeat::Repeat {
+                timing: RepeatTiming::Delay(calendar)
+            }
+        );
+    }
+
+    #[test]
+    fn test_repeat_cron() {
+        let expected = Repeat::Repeat {
+            timing: RepeatTiming::Cron("0 9 * * 1-5".to_string()),
+        };
+        let proc = procrastination(&["repeat", "k", "cron", "0 9 * * 1-5"]).unwrap();
+        assert_eq!(proc.timing, expected);
+        let proc = procrastination(&["repeat", "k", "cron 0 9 * * 1-5", "--sticky"]).unwrap();
+        assert_eq!(proc.timing, expected);
+    }
+
+    #[test]
+    fn test_repeat_align() {
+        let proc = procrastination(&["repeat", "k", "1h", "--align", "hour"]).unwrap();
+        assert_eq!(
+            proc.timing,
+            Repeat::Repeat {
+                timing: RepeatTiming::AlignedDelay {
+                    delay: Delay::Seconds(60 * 60),
+                    align: AlignUnit::Hour,
+                }
+            }
+        );
+
+        let err = procrastination(&["repeat", "k", "daily 9:00", "--align", "day"]).unwrap_err();
+        assert!(err.contains("align"), "{err}");
+        let args = [
+            "procrastinate",
+            "repeat",
+            "k",
+            "1h",
+            "--align",
+            "hour",
+            "--at",
+            "9:00",
+        ];
+        assert!(Arguments::try_parse_from(args).is_err());
+    }
+
+    #[test]
+    fn test_sun_needs_location() {
+        let err = procrastination(&["once", "k", "sunset -30m"]).unwrap_err();
+        assert!(err.contains("location"), "{err}");
+
+        let mut config = config();
+        config.location = Some(procrastinate::time::sun::Location {
+            latitude: 52.52,
+            longitude: 13.405,
+        });
+        let proc = parse(&["once", "k", "sunset -30m"])
+            .procrastination(&config)
+            .unwrap();
+        assert_eq!(
+            proc.timing,
+            Repeat::Once {
+                timing: OnceTiming::Instant(RoughInstant::Sunset { offset: -30 * 60 })
+            }
+        );
+    }
+
+    #[test]
+    fn test_once_strict() {
+        assert!(procrastination(&["once", "k", "14-12"]).is_ok());
+        let err = procrastination(&["once", "k", "14-12", "--strict"]).unwrap_err();
+        assert!(err.contains("ambiguous"), "{err}");
+
+        let proc = procrastination(&["once", "k", "2025-12-14 09:00", "--strict"]).unwrap();
+        let date = chrono::NaiveDate::from_ymd_opt(2025, 12, 14)
+            .unwrap()
+            .and_hms_opt(9, 0, 0)
+            .unwrap();
+        assert_eq!(
+            proc.timing,
+            Repeat::Once {
+                timing: OnceTiming::Instant(RoughInstant::Date { date })
+            }
+        );
+    }
+
+    #[test]
+    fn test_sleep_invalid_timing() {
+        let args = ["procrastinate", "sleep", "k", "someday"];
+        assert!(Arguments::try_parse_from(args).is_err());
+    }
+
+    #[test]
+    fn test_group() {
+        let args = parse(&["--group", "work", "once", "standup", "5m"]);
+        assert_eq!(args.key("standup"), "work/standup");
+        assert_eq!(args.procrastination(&config()).unwrap().title, "standup");
+
+        let args = parse(&["list", "--group", "work"]);
+        assert_eq!(args.group.as_deref(), Some("work"));
+
+        assert_eq!(parse(&["once", "standup", "5m"]).key("standup"), "standup");
+        assert!(parse(&["--group", "", "list"]).verify().is_err());
+    }
+
+    #[test]
+    fn test_template() {
+        let proc = procrastination(&["repeat", "standup", "--template", "meeting"]).unwrap();
+        assert_eq!(proc.title, "Meeting");
+        assert_eq!(proc.message, "grab a coffee");
+        assert_eq!(
+            proc.timing,
+            Repeat::Repeat {
+                timing: "daily 9:30".parse().unwrap()
+            }
+        );
+        assert!(proc.sticky);
+
+        let proc = procrastination(&[
+            "repeat",
+            "standup",
+            "monday 10:00",
+            "--template",
+            "meeting",
+            "-t",
+            "Standup",
+            "--sticky=false",
+        ])
+        .unwrap();
+        assert!(!proc.sticky);
+        assert_eq!(proc.title, "Standup");
+        assert_eq!(proc.message, "grab a coffee");
+        assert_eq!(
+            proc.timing,
+            Repeat::Repeat {
+                timing: "monday 10:00".parse().unwrap()
+            }
+        );
+
+        assert!(procrastination(&["once", "standup", "--template", "meeting"]).is_err());
+        assert!(procrastination(&["once", "standup", "--template", "unknown"]).is_err());
+        assert!(procrastination(&["once", "standup"]).is_err());
+    }
+}
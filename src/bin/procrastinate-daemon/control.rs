@@ -0,0 +1,315 @@
+//! Line based control interface of the daemon.
+//!
+//! Every line sent to the socket is one of the following commands:
+//! - `reload`: check for notifications now
+//! - `list`: list all procrastinations as `<key>\t<next notification>`
+//! - `snooze <key> <delay>`: notify again once `delay` has passed
+//! - `done <key>`: stop procrastinating on the given task
+//!
+//! Each command is answered by zero or more lines followed by either
+//! `ok` or `error: <message>`.
+//!
+//! The http interface runs the same [Command]s.
+
+use std::{
+    env,
+    path::{Path, PathBuf},
+};
+
+use chrono::Local;
+use procrastinate::{
+    time::{Calendar, Delay, TimeError},
+    ProcrastinationFile, ProcrastinationFileData,
+};
+use thiserror::Error;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::{UnixListener, UnixStream},
+    sync::mpsc::UnboundedSender,
+};
+
+pub const SOCKET_NAME: &str = "procrastinate.sock";
+
+/// `$XDG_RUNTIME_DIR/procrastinate.sock`
+pub fn default_socket_path() -> PathBuf {
+    let dir = env::var_os("XDG_RUNTIME_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(env::temp_dir);
+    dir.join(SOCKET_NAME)
+}
+
+/// The procrastination file commands are run against
+#[derive(Debug, Clone)]
+pub struct CommandFile {
+    pub path: PathBuf,
+    /// backups kept when a command saves the file, see [ProcrastinationFile::set_backups]
+    pub backups: usize,
+}
+
+impl CommandFile {
+    fn open(&self) -> Result<ProcrastinationFile, procrastinate::Error> {
+        let mut file = ProcrastinationFile::open(&self.path)?;
+        file.set_backups(self.backups);
+        Ok(file)
+    }
+}
+
+/// The control socket. The socket file is removed on drop.
+pub struct ControlSocket {
+    pub listener: UnixListener,
+    path: PathBuf,
+}
+
+impl ControlSocket {
+    /// Binds a new socket at `path`, replacing any stale socket file.
+    pub fn bind(path: &Path) -> std::io::Result<Self> {
+        if path.exists() {
+            std::fs::remove_file(path)?;
+        }
+        let listener = UnixListener::bind(path)?;
+        Ok(Self {
+            listener,
+            path: path.to_path_buf(),
+        })
+    }
+}
+
+impl Drop for ControlSocket {
+    fn drop(&mut self) {
+        if let Err(e) = std::fs::remove_file(&self.path) {
+            log::error!("Failed to remove control socket: {e}");
+        }
+    }
+}
+
+/// Handles all commands of a single connection.
+///
+/// `reload` is notified if the daemon should check for notifications.
+pub async fn handle_connection(
+    stream: UnixStream,
+    file: CommandFile,
+    reload: UnboundedSender<()>,
+    calendar: Calendar,
+) {
+    let (read, mut write) = stream.into_split();
+    let mut lines = BufReader::new(read).lines();
+
+    loop {
+        let line = match lines.next_line().await {
+            Ok(Some(line)) => line,
+            Ok(None) => return,
+            Err(e) => {
+                log::error!("Failed to read from control socket: {e}");
+                return;
+            }
+        };
+
+        log::info!("control command: {line}");
+        let response = match Command::parse(&line) {
+            Ok(command) => command.execute(&file, &reload).await,
+            Err(e) => Err(e),
+        };
+        let response = match response.and_then(|response| format_response(&response, &calendar)) {
+            Ok(mut response) => {
+                response.push_str("ok\n");
+                response
+            }
+            Err(e) => format!("error: {e}\n"),
+        };
+
+        if let Err(e) = write.write_all(response.as_bytes()).await {
+            log::error!("Failed to write to control socket: {e}");
+            return;
+        }
+    }
+}
+
+/// The lines answering a command, without the final `ok`
+fn format_response(response: &Response, calendar: &Calendar) -> Result<String, CommandError> {
+    let mut lines = String::new();
+    if let Response::Entries(data) = response {
+        for (key, procrastination) in data.iter_sorted_by_next(calendar)? {
+            let (_, next) = procrastination.next_notification(calendar)?;
+            lines.push_str(&format!("{key}\t{}\n", next.format("%Y-%m-%d %H:%M:%S")));
+        }
+    }
+    Ok(lines)
+}
+
+/// A command of the control socket or the http interface
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    Reload,
+    List,
+    Snooze { key: String, delay: Delay },
+    Done { key: String },
+}
+
+/// The result of a successful [Command]
+#[derive(Debug)]
+pub enum Response {
+    Ok,
+    /// all procrastinations, the answer to [Command::List]
+    Entries(ProcrastinationFileData),
+}
+
+#[derive(Debug, Error)]
+pub enum CommandError {
+    #[error("{0}")]
+    Invalid(String),
+    #[error("No procrastination entry with key \"{0}\" exists")]
+    NotFound(String),
+    #[error("{0}")]
+    Failed(String),
+}
+
+impl From<TimeError> for CommandError {
+    fn from(e: TimeError) -> Self {
+        CommandError::Failed(e.to_string())
+    }
+}
+
+impl From<procrastinate::Error> for CommandError {
+    fn from(e: procrastinate::Error) -> Self {
+        CommandError::Failed(e.to_string())
+    }
+}
+
+impl Command {
+    /// Parses a line of the control socket, e.g. `snooze <key> <delay>`
+    pub fn parse(line: &str) -> Result<Self, CommandError> {
+        let mut args = line.split_whitespace();
+        let cmd = args.next().unwrap_or_default();
+        let mut key = || {
+            args.next()
+                .map(str::to_string)
+                .ok_or_else(|| CommandError::Invalid(format!("missing key for \"{cmd}\"")))
+        };
+
+        match cmd {
+            "reload" => Ok(Command::Reload),
+            "list" => Ok(Command::List),
+            "snooze" => {
+                let key = key()?;
+                let delay = Self::delay(&args.collect::<Vec<_>>().join(" "))?;
+                Ok(Command::Snooze { key, delay })
+            }
+            "done" => Ok(Command::Done { key: key()? }),
+            _ => Err(CommandError::Invalid(format!("unknown command \"{cmd}\""))),
+        }
+    }
+
+    /// Parses the delay of [Command::Snooze]
+    pub fn delay(delay: &str) -> Result<Delay, CommandError> {
+        delay
+            .trim()
+            .parse()
+            .map_err(|e| CommandError::Invalid(format!("invalid delay: {e}")))
+    }
+
+    /// Runs the command against the procrastination file.
+    ///
+    /// `reload` is notified if the daemon should check for notifications,
+    /// e.g. because the command changed the file.
+    pub async fn execute(
+        self,
+        file: &CommandFile,
+        reload: &UnboundedSender<()>,
+    ) -> Result<Response, CommandError> {
+        let response = match self {
+            Command::Reload => Response::Ok,
+            Command::List => {
+                return with_file(file, |file| {
+                    Ok(Response::Entries(ProcrastinationFile::read(&file.path)?))
+                })
+                .await;
+            }
+            Command::Snooze { key, delay } => {
+                with_file(file, move |file| {
+                    let mut proc_file = file.open()?;
+                    proc_file
+                        .data_mut()
+                        .get_mut(&key)
+                        .ok_or(CommandError::NotFound(key))?
+                        .snooze(Local::now().naive_local(), delay);
+                    proc_file.save()?;
+                    Ok(Response::Ok)
+                })
+                .await?
+            }
+            Command::Done { key } => {
+                with_file(file, move |file| {
+                    let mut proc_file = file.open()?;
+                    proc_file
+                        .data_mut()
+                        .remove(&key)
+                        .ok_or(CommandError::NotFound(key))?;
+                    proc_file.save()?;
+                    Ok(Response::Ok)
+                })
+                .await?
+            }
+        };
+        reload
+            .send(())
+            .map_err(|e| CommandError::Failed(e.to_string()))?;
+        Ok(response)
+    }
+}
+
+/// Runs `f` on the blocking thread pool, as the procrastination file is locked and
+/// written synchronously.
+async fn with_file<T, F>(file: &CommandFile, f: F) -> Result<T, CommandError>
+where
+    T: Send + 'static,
+    F: FnOnce(&CommandFile) -> Result<T, CommandError> + Send + 'static,
+{
+    let file = file.clone();
+    tokio::task::spawn_blocking(move || f(&file))
+        .await
+        .map_err(|e| CommandError::Failed(e.to_string()))?
+}
+
+#[cfg(test)]
+mod test {
+    use tokio::sync::mpsc;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_reload() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("control.sock");
+        let socket = ControlSocket::bind(&path).unwrap();
+        let (reload_tx, mut reload_rx) = mpsc::unbounded_channel();
+
+        let server = tokio::spawn(async move {
+            let (stream, _) = socket.listener.accept().await.unwrap();
+            let file = CommandFile {
+                path: PathBuf::new(),
+                backups: 0,
+            };
+            handle_connection(stream, file, reload_tx, Calendar::default()).await;
+            socket
+        });
+
+        let stream = UnixStream::connect(&path).await.unwrap();
+        let (read, mut write) = stream.into_split();
+        let mut lines = BufReader::new(read).lines();
+
+        write.write_all(b"reload\n").await.unwrap();
+        assert_eq!(lines.next_line().await.unwrap().unwrap(), "ok");
+        assert_eq!(reload_rx.recv().await, Some(()));
+
+        write.write_all(b"unknown\n").await.unwrap();
+        assert_eq!(
+            lines.next_line().await.unwrap().unwrap(),
+            "error: unknown command \"unknown\""
+        );
+
+        drop(write);
+        drop(lines);
+        drop(server.await.unwrap());
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,417 @@
+//! Minimal HTTP interface of the daemon, enabled with the `http` feature.
+//!
+//! - `GET /entries`: all procrastinations in the format of `procrastinate list --json`
+//! - `POST /entries/<key>/snooze`: notify again once the delay in the body has passed, e.g. `10m`.
+//!   The key is percent-decoded and may contain `/`, e.g. `/entries/work/stand%20up/snooze`
+//!
+//! If a token is configured every request must send it as `Authorization: Bearer <token>`.
+//! Errors are answered with `{"error": <message>}`.
+
+use std::net::SocketAddr;
+
+use procrastinate::{json, time::Calendar};
+use serde::Serialize;
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    sync::mpsc::UnboundedSender,
+};
+
+use crate::control::{Command, CommandError, CommandFile, Response};
+
+/// The largest request body that is accepted
+const MAX_BODY_LEN: usize = 1024;
+/// The largest request line and headers that are accepted
+const MAX_HEAD_LEN: usize = 8 * 1024;
+const MAX_HEADERS: usize = 32;
+
+/// Only allows to serve on loopback addresses without a `token`
+pub fn check_address(addr: SocketAddr, token: Option<&str>) -> Result<(), String> {
+    if addr.ip().is_loopback() || token.is_some() {
+        Ok(())
+    } else {
+        Err(format!(
+            "{addr} is reachable from other hosts, set --http-token to serve http on it"
+        ))
+    }
+}
+
+/// Answers requests on `listener` until the daemon stops.
+///
+/// `reload` is notified if the daemon should check for notifications.
+/// Requests without `token` are rejected, if it is set.
+pub async fn serve(
+    listener: TcpListener,
+    file: CommandFile,
+    reload: UnboundedSender<()>,
+    token: Option<String>,
+    calendar: Calendar,
+) {
+    loop {
+        match listener.accept().await {
+            Ok((stream, _)) => {
+                tokio::spawn(handle_connection(
+                    stream,
+                    file.clone(),
+                    reload.clone(),
+                    token.clone(),
+                    calendar,
+                ));
+            }
+            Err(e) => log::error!("Failed to accept http connection: {e}"),
+        }
+    }
+}
+
+/// Answers a single request, the connection is closed afterwards.
+async fn handle_connection(
+    mut stream: TcpStream,
+    file: CommandFile,
+    reload: UnboundedSender<()>,
+    token: Option<String>,
+    calendar: Calendar,
+) {
+    let (status, body) = match read_request(&mut stream).await {
+        Ok(request) if !request.authorized(token.as_deref()) => {
+            (401, error_body("missing or invalid token"))
+        }
+        Ok(request) => route(&request, &file, &reload, &calendar).await,
+        Err(e) => (400, error_body(&e)),
+    };
+
+    let reason = match status {
+        200 => "OK",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        _ => "Internal Server Error",
+    };
+    let response = format!(
+        "HTTP/1.1 {status} {reason}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+    if let Err(e) = stream.write_all(response.as_bytes()).await {
+        log::error!("Failed to write http response: {e}");
+    }
+}
+
+/// The parts of a request the interface uses
+struct Request {
+    method: String,
+    path: String,
+    authorization: Option<String>,
+    body: String,
+}
+
+impl Request {
+    /// If the request sends `token`, or no token is required
+    fn authorized(&self, token: Option<&str>) -> bool {
+        let Some(token) = token else {
+            return true;
+        };
+        self.authorization
+            .as_deref()
+            .and_then(|header| header.strip_prefix("Bearer "))
+            .is_some_and(|sent| constant_time_eq(sent.as_bytes(), token.as_bytes()))
+    }
+}
+
+/// Compares without returning early, so the time taken does not reveal the token
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+/// Reads and parses a single request
+async fn read_request<R>(read: &mut R) -> Result<Request, String>
+where
+    R: AsyncRead + Unpin,
+{
+    let mut buf = Vec::new();
+    let mut chunk = [0; 1024];
+    loop {
+        let len = read.read(&mut chunk).await.map_err(|e| e.to_string())?;
+        if len == 0 {
+            return Err("incomplete request".to_string());
+        }
+        buf.extend_from_slice(&chunk[..len]);
+
+        let mut headers = [httparse::EMPTY_HEADER; MAX_HEADERS];
+        let mut parsed = httparse::Request::new(&mut headers);
+        let head_len = match parsed.parse(&buf).map_err(|e| e.to_string())? {
+            httparse::Status::Complete(head_len) => head_len,
+            httparse::Status::Partial if buf.len() > MAX_HEAD_LEN => {
+                return Err("request header too large".to_string())
+            }
+            httparse::Status::Partial => continue,
+        };
+
+        let header = |name: &str| {
+            parsed
+                .headers
+                .iter()
+                .find(|header| header.name.eq_ignore_ascii_case(name))
+                .map(|header| String::from_utf8_lossy(header.value).into_owned())
+        };
+        let content_length = match header("content-length") {
+            Some(len) => len
+                .trim()
+                .parse()
+                .map_err(|e: std::num::ParseIntError| e.to_string())?,
+            None => 0,
+        };
+        if content_length > MAX_BODY_LEN {
+            return Err("request body too large".to_string());
+        }
+        let request = Request {
+            method: parsed.method.unwrap_or_default().to_string(),
+            path: parsed.path.unwrap_or_default().to_string(),
+            authorization: header("authorization"),
+            body: String::new(),
+        };
+
+        let mut body = buf.split_off(head_len);
+        if body.len() < content_length {
+            let mut rest = vec![0; content_length - body.len()];
+            read.read_exact(&mut rest)
+                .await
+                .map_err(|e| e.to_string())?;
+            body.extend(rest);
+        }
+        body.truncate(content_length);
+        let body = String::from_utf8(body).map_err(|e| e.to_string())?;
+        return Ok(Request { body, ..request });
+    }
+}
+
+/// The status and json body of the response
+async fn route(
+    request: &Request,
+    file: &CommandFile,
+    reload: &UnboundedSender<()>,
+    calendar: &Calendar,
+) -> (u16, String) {
+    log::info!("http request: {} {}", request.method, request.path);
+    let segments: Vec<_> = request.path.trim_matches('/').split('/').collect();
+
+    let command = match (request.method.as_str(), segments.as_slice()) {
+        ("GET", ["entries"]) => Ok(Command::List),
+        ("POST", ["entries", key @ .., "snooze"]) if !key.is_empty() => {
+            match percent_decode(&key.join("/")) {
+                Some(key) => {
+                    Command::delay(&request.body).map(|delay| Command::Snooze { key, delay })
+                }
+                None => Err(CommandError::Invalid(
+                    "invalid percent-encoding in key".to_string(),
+                )),
+            }
+        }
+        (_, ["entries"]) | (_, ["entries", _, .., "snooze"]) => {
+            return (405, error_body("method not allowed"))
+        }
+        _ => return (404, error_body("not found")),
+    };
+
+    let response = match command {
+        Ok(command) => command.execute(file, reload).await,
+        Err(e) => Err(e),
+    };
+    let body = response.and_then(|response| match response {
+        Response::Ok => Ok("{}".to_string()),
+        Response::Entries(data) => Ok(json::list(
+            &data.iter_sorted_by_next(calendar)?,
+            false,
+            calendar,
+        )),
+    });
+    match body {
+        Ok(body) => (200, body),
+        Err(e @ CommandError::Invalid(_)) => (400, error_body(&e)),
+        Err(e @ CommandError::NotFound(_)) => (404, error_body(&e)),
+        Err(e @ CommandError::Failed(_)) => (500, error_body(&e)),
+    }
+}
+
+/// Decodes `%XX` escapes of a path, `None` if an escape or the result is invalid
+fn percent_decode(path: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(path.len());
+    let mut rest = path.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        rest = tail;
+        if byte != b'%' {
+            bytes.push(byte);
+            continue;
+        }
+        let hex = std::str::from_utf8(rest.get(..2)?).ok()?;
+        bytes.push(u8::from_str_radix(hex, 16).ok()?);
+        rest = &rest[2..];
+    }
+    String::from_utf8(bytes).ok()
+}
+
+#[derive(Serialize)]
+struct ErrorBody {
+    error: String,
+}
+
+fn error_body(e: &(impl std::fmt::Display + ?Sized)) -> String {
+    let body = ErrorBody {
+        error: e.to_string(),
+    };
+    serde_json::to_string(&body).expect("errors can be serialized")
+}
+
+#[cfg(test)]
+mod test {
+    use procrastinate::{time::Repeat, Procrastination, ProcrastinationFileData};
+    use tokio::sync::mpsc;
+
+    use super::*;
+
+    async fn request(addr: SocketAddr, request: &str) -> String {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response
+    }
+
+    #[tokio::test]
+    async fn test_list_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("procrastination.ron");
+        let mut data = ProcrastinationFileData::empty();
+        let standup = Procrastination::new(
+            "Standup".to_string(),
+            String::new(),
+            Repeat::Repeat {
+                timing: "1h".parse().unwrap(),
+            },
+            false,
+        );
+        data.insert("standup".to_string(), standup);
+        let grouped = Procrastination::new(
+            "Review".to_string(),
+            String::new(),
+            Repeat::Repeat {
+                timing: "1h".parse().unwrap(),
+            },
+            false,
+        );
+        data.insert("work/code review".to_string(), grouped);
+        std::fs::write(&path, ron::to_string(&data).unwrap()).unwrap();
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (reload_tx, mut reload_rx) = mpsc::unbounded_channel();
+        let server = tokio::spawn(serve(
+            listener,
+            CommandFile {
+                path: path.clone(),
+                backups: 0,
+            },
+            reload_tx,
+            None,
+            Calendar::default(),
+        ));
+
+        let response = request(addr, "GET /entries HTTP/1.1\r\nHost: localhost\r\n\r\n").await;
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{response}");
+        assert!(
+            response.contains(r#""entries":[{"key":"standup","title":"Standup""#),
+            "{response}"
+        );
+
+        let response = request(
+            addr,
+            "POST /entries/missing/snooze HTTP/1.1\r\nContent-Length: 3\r\n\r\n10m",
+        )
+        .await;
+        assert!(
+            response.starts_with("HTTP/1.1 404 Not Found\r\n"),
+            "{response}"
+        );
+
+        let response = request(
+            addr,
+            "POST /entries/standup/snooze HTTP/1.1\r\nContent-Length: 3\r\n\r\n10m",
+        )
+        .await;
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{response}");
+        assert_eq!(reload_rx.recv().await, Some(()));
+
+        let response = request(
+            addr,
+            "POST /entries/work/code%20review/snooze HTTP/1.1\r\nContent-Length: 3\r\n\r\n10m",
+        )
+        .await;
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{response}");
+        assert_eq!(reload_rx.recv().await, Some(()));
+
+        let response = request(
+            addr,
+            "POST /entries/work%2/snooze HTTP/1.1\r\nContent-Length: 3\r\n\r\n10m",
+        )
+        .await;
+        assert!(
+            response.starts_with("HTTP/1.1 400 Bad Request\r\n"),
+            "{response}"
+        );
+
+        server.abort();
+    }
+
+    #[tokio::test]
+    async fn test_token() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (reload_tx, _reload_rx) = mpsc::unbounded_channel();
+        let file = CommandFile {
+            path: "/nonexistent/procrastinate.ron".into(),
+            backups: 0,
+        };
+        let server = tokio::spawn(serve(
+            listener,
+            file,
+            reload_tx,
+            Some("secret".to_string()),
+            Calendar::default(),
+        ));
+
+        let response = request(addr, "GET /entries HTTP/1.1\r\n\r\n").await;
+        assert!(
+            response.starts_with("HTTP/1.1 401 Unauthorized\r\n"),
+            "{response}"
+        );
+        let response = request(
+            addr,
+            "GET /entries HTTP/1.1\r\nAuthorization: Bearer wrong\r\n\r\n",
+        )
+        .await;
+        assert!(
+            response.starts_with("HTTP/1.1 401 Unauthorized\r\n"),
+            "{response}"
+        );
+        let response = request(
+            addr,
+            "GET /unknown HTTP/1.1\r\nAuthorization: Bearer secret\r\n\r\n",
+        )
+        .await;
+        assert!(
+            response.starts_with("HTTP/1.1 404 Not Found\r\n"),
+            "{response}"
+        );
+
+        server.abort();
+    }
+
+    #[test]
+    fn test_check_address() {
+        let local: SocketAddr = "127.0.0.1:8080".parse().unwrap();
+        let public: SocketAddr = "0.0.0.0:8080".parse().unwrap();
+        assert!(check_address(local, None).is_ok());
+        assert!(check_address("[::1]:8080".parse().unwrap(), None).is_ok());
+        assert!(check_address(public, None).is_err());
+        assert!(check_address(public, Some("secret")).is_ok());
+    }
+}
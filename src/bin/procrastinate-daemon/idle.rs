@@ -0,0 +1,115 @@
+//! Holds back notifications while the user is away from the computer.
+
+use std::{process::Command, time::Duration};
+
+use procrastinate::Priority;
+
+/// Queries how long the user has been idle
+pub trait IdleQuery {
+    /// `None` if the idle time is unknown
+    fn idle_time(&self) -> Option<Duration>;
+}
+
+/// Queries the idle time of the X server using `xprintidle`
+pub struct XPrintIdle;
+
+impl IdleQuery for XPrintIdle {
+    fn idle_time(&self) -> Option<Duration> {
+        let output = match Command::new("xprintidle").output() {
+            Ok(output) if output.status.success() => output,
+            Ok(output) => {
+                log::warn!(
+                    "xprintidle failed: {}",
+                    String::from_utf8_lossy(&output.stderr)
+                );
+                return None;
+            }
+            Err(e) => {
+                log::warn!("failed to run xprintidle: {e}");
+                return None;
+            }
+        };
+        let millis = String::from_utf8_lossy(&output.stdout)
+            .trim()
+            .parse()
+            .ok()?;
+        Some(Duration::from_millis(millis))
+    }
+}
+
+/// Used if the idle time can't be queried. The user is never idle.
+pub struct NoIdleQuery;
+
+impl IdleQuery for NoIdleQuery {
+    fn idle_time(&self) -> Option<Duration> {
+        None
+    }
+}
+
+/// Returns the best available [IdleQuery]
+pub fn idle_query() -> Box<dyn IdleQuery> {
+    if XPrintIdle.idle_time().is_some() {
+        Box::new(XPrintIdle)
+    } else {
+        log::warn!("can't query the idle time, notifications are never deferred");
+        Box::new(NoIdleQuery)
+    }
+}
+
+/// Defers notifications while the user is idle for at least `threshold`
+pub struct IdleDefer {
+    pub query: Box<dyn IdleQuery>,
+    pub threshold: Duration,
+}
+
+impl IdleDefer {
+    /// Queries the idle time once, returning a function that decides
+    /// if a notification with the given priority is deferred.
+    pub fn check(&self) -> impl Fn(Priority) -> bool {
+        let idle = self.query.idle_time();
+        let threshold = self.threshold;
+        move |priority| should_defer(priority, idle, threshold)
+    }
+}
+
+/// Critical notifications are never deferred
+fn should_defer(priority: Priority, idle: Option<Duration>, threshold: Duration) -> bool {
+    priority != Priority::Critical && idle.is_some_and(|idle| idle >= threshold)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    struct FixedIdle(Option<Duration>);
+
+    impl IdleQuery for FixedIdle {
+        fn idle_time(&self) -> Option<Duration> {
+            self.0
+        }
+    }
+
+    fn defer(idle: Option<u64>) -> IdleDefer {
+        IdleDefer {
+            query: Box::new(FixedIdle(idle.map(Duration::from_secs))),
+            threshold: Duration::from_secs(300),
+        }
+    }
+
+    #[test]
+    fn test_defer_decision() {
+        let away = defer(Some(600)).check();
+        assert!(away(Priority::Normal));
+        assert!(away(Priority::Low));
+        assert!(!away(Priority::Critical));
+
+        let present = defer(Some(10)).check();
+        assert!(!present(Priority::Normal));
+
+        let at_threshold = defer(Some(300)).check();
+        assert!(at_threshold(Priority::Normal));
+
+        let unknown = defer(None).check();
+        assert!(!unknown(Priority::Normal));
+    }
+}
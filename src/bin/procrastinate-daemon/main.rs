@@ -0,0 +1,803 @@
+use core::panic;
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    error::Error,
+    hash::{Hash, Hasher},
+    num::NonZeroUsize,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use chrono::{Local, NaiveDateTime, TimeDelta};
+use clap::{Parser, ValueEnum};
+use env_logger::Builder;
+use log::LevelFilter;
+use notify::{PollWatcher, RecommendedWatcher, Watcher};
+use notify_rust::Notification;
+use procrastinate::{
+    check_key_arg_doc,
+    config::{config_path, Config},
+    file_arg_doc,
+    hook::{fired_titles, run_fire_hooks},
+    limit_due, local_arg_doc, max_age_arg_doc, procrastination_path,
+    time::{Calendar, Delay},
+    DesktopNotifications, NotificationErrors, NotificationType, NotifySettings,
+    ProcrastinationFile,
+};
+use tokio::{
+    select,
+    signal::unix::{signal, SignalKind},
+    sync::{mpsc, watch},
+};
+
+use crate::{
+    control::{default_socket_path, handle_connection, CommandFile, ControlSocket},
+    idle::{idle_query, IdleDefer},
+    signal::{signal_fired, DBusFireSignal, FireSignal},
+};
+
+mod control;
+#[cfg(feature = "http")]
+mod http;
+mod idle;
+mod signal;
+use tokio_stream::{wrappers::WatchStream, StreamExt};
+
+#[allow(clippy::too_many_arguments)]
+fn check_for_notifications(
+    path: &Path,
+    min: Duration,
+    max: Duration,
+    max_age: Option<Delay>,
+    idle_defer: Option<&IdleDefer>,
+    parallel: NonZeroUsize,
+    max_per_run: Option<NonZeroUsize>,
+    fire_signal: Option<&dyn FireSignal>,
+    on_fire: Option<&str>,
+    notification_ids: &mut HashMap<String, u32>,
+    unavailable_checks: &mut u32,
+    backups: usize,
+    settings: &NotifySettings,
+) -> Result<Duration, Box<dyn std::error::Error>> {
+    let calendar = &settings.calendar;
+    let mut proc_file = ProcrastinationFile::open(path)?;
+    proc_file.set_backups(backups);
+    proc_file
+        .data_mut()
+        .restore_notification_ids(notification_ids);
+    let now = Local::now().naive_local();
+    log::info!("check for notifications");
+    let checked = proc_file.data().iter().count();
+
+    let mut until_any_next = Duration::MAX;
+    let mut changed = false;
+
+    if let Some(max_age) = max_age {
+        changed |= proc_file.data_mut().remove_stale(now, max_age, calendar)?;
+    }
+
+    let defer = idle_defer.map(IdleDefer::check);
+    let mut limit = max_per_run.map(|max| limit_due(max, *calendar));
+    let report = proc_file.data_mut().notify_parallel_with(
+        parallel,
+        settings,
+        &DesktopNotifications,
+        |key, procrastination| {
+            let due = !matches!(
+                procrastination.should_notify(calendar),
+                Ok(NotificationType::None)
+            );
+            if due
+                && defer
+                    .as_ref()
+                    .is_some_and(|defer| defer(procrastination.priority))
+            {
+                log::info!("user is idle, deferring notification \"{key}\"");
+                until_any_next = until_any_next.min(IDLE_RECHECK_INTERVAL);
+                return false;
+            }
+            if due
+                && limit
+                    .as_mut()
+                    .is_some_and(|limit| !limit(key, procrastination))
+            {
+                log::info!("too many notifications, deferring \"{key}\" to the next check");
+                until_any_next = Duration::ZERO;
+                return false;
+            }
+            true
+        },
+    );
+    changed |= !report.fired.is_empty();
+    if let Some(quiet) = proc_file.data().defaults.quiet_hours {
+        if quiet.contains(now.time()) {
+            // deferred notifications are shown once the quiet hours end
+            until_any_next = until_any_next.min(until(quiet.until_end(now)));
+        }
+    }
+    *notification_ids = proc_file.data().notification_ids();
+    if let Some(fire_signal) = fire_signal {
+        signal_fired(fire_signal, &report.fired);
+    }
+    let fired = fired_titles(proc_file.data(), &report.fired);
+    let mut errors = report.failed;
+    if errors.iter().any(|(_, e)| e.is_transient()) {
+        *unavailable_checks += 1;
+        if let Some(delay) = settings.retry.delay(*unavailable_checks) {
+            // the failed procrastinations are still due and notified by the next check
+            log::warn!("notification server is not running, checking again in {delay:?}");
+            until_any_next = until_any_next.min(delay);
+            errors.retain(|(_, e)| !e.is_transient());
+        } else {
+            *unavailable_checks = 0;
+        }
+    } else {
+        *unavailable_checks = 0;
+    }
+
+    for (key, procrastination) in proc_file.data().iter() {
+        if !procrastination.can_notify_in_future() {
+            continue;
+        }
+
+        match procrastination.time_until_next_at(now, calendar) {
+            Ok(until_next) => {
+                until_any_next = until_any_next.min(until(until_next));
+            }
+            Err(e) => {
+                log::error!("Failed to find next notification: {e}");
+                errors.push((key.clone(), e.into()));
+            }
+        }
+    }
+    changed |= proc_file.data_mut().cleanup();
+
+    if changed {
+        proc_file.save()?;
+    }
+    drop(proc_file);
+    if let Some(command) = on_fire {
+        // the hooks run without blocking the daemon or holding the file lock
+        let command = command.to_string();
+        tokio::task::spawn_blocking(move || run_fire_hooks(&command, &fired));
+    }
+
+    let next_wake = until_any_next.clamp(min, max);
+    log::info!("{}", check_summary(checked, report.fired.len(), next_wake));
+    NotificationErrors::from_errors(errors)?;
+
+    Ok(next_wake)
+}
+
+/// The schedule of the procrastinations at `path`, as a line per procrastination
+/// followed by the time until the next check.
+///
+/// Like [check_for_notifications], but nothing is notified and the file is only read.
+fn dry_run(
+    path: &Path,
+    min: Duration,
+    max: Duration,
+    now: NaiveDateTime,
+    calendar: &Calendar,
+) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let data = ProcrastinationFile::read(path)?;
+    let mut until_any_next = Duration::MAX;
+    let mut lines = Vec::new();
+    for (key, procrastination) in data.iter_sorted_by_next(calendar)? {
+        if !procrastination.can_notify_in_future() {
+            continue;
+        }
+        let (_, next) = procrastination.next_notification(calendar)?;
+        // a due procrastination would be notified by the next check
+        let (until_next, when) = match next <= now {
+            true => (Duration::ZERO, "due now".to_string()),
+            false => (
+                until(next - now),
+                format!("at {}", next.format("%Y-%m-%d %H:%M:%S")),
+            ),
+        };
+        until_any_next = until_any_next.min(until_next);
+        lines.push(format!("{key}: {when}"));
+    }
+    let next_wake = until_any_next.clamp(min, max);
+    let next_wake = Delay::Seconds(next_wake.as_secs().try_into().unwrap_or(i64::MAX));
+    lines.push(format!("next wake in {next_wake}"));
+    Ok(lines)
+}
+
+/// One line summary of a notification check
+fn check_summary(checked: usize, fired: usize, next_wake: Duration) -> String {
+    let next_wake = Delay::Seconds(next_wake.as_secs().try_into().unwrap_or(i64::MAX));
+    format!("checked {checked} entries, fired {fired}, next wake in {next_wake}")
+}
+
+/// How often to check if the user returned, while notifications are deferred
+const IDLE_RECHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+/// The time to wait for `delta`.
+///
+/// Returns [Duration::MAX] if `delta` is negative, i.e. not in the future.
+fn until(delta: TimeDelta) -> Duration {
+    delta.to_std().unwrap_or(Duration::MAX)
+}
+
+/// How often the wall clock is compared against monotonic time
+const CLOCK_JUMP_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+/// Difference between wall clock and monotonic time that is considered a clock jump
+const CLOCK_JUMP_TOLERANCE: Duration = Duration::from_secs(5);
+
+/// Wall clock and monotonic time at the last notification check.
+///
+/// Timeouts are computed using the wall clock, but sleeping uses monotonic time.
+/// If the wall clock jumps (NTP sync, DST, suspend) the timeout is no longer
+/// correct and notifications need to be checked again.
+#[derive(Debug, Clone, Copy)]
+struct ClockReference {
+    wall: NaiveDateTime,
+    monotonic: Instant,
+}
+
+impl ClockReference {
+    fn now() -> Self {
+        Self {
+            wall: Local::now().naive_local(),
+            monotonic: Instant::now(),
+        }
+    }
+
+    /// returns `true` if the wall clock did not advance in step with monotonic time
+    fn jumped(&self, wall: NaiveDateTime, monotonic: Instant) -> bool {
+        let wall_elapsed = wall - self.wall;
+        let monotonic_elapsed = TimeDelta::from_std(monotonic - self.monotonic)
+            .expect("monotonic time should fit into TimeDelta");
+        let tolerance = TimeDelta::from_std(CLOCK_JUMP_TOLERANCE).unwrap();
+
+        (wall_elapsed - monotonic_elapsed).abs() > tolerance
+    }
+}
+
+#[derive(Parser, Debug)]
+#[command(version, about)]
+/// Continously checks notifications for all finished procrastinations.
+///
+/// To only check for notifications once use `procrastinate-work` insetead.
+pub struct Args {
+    #[arg(help = check_key_arg_doc!())]
+    pub key: Option<String>,
+
+    #[arg(short, long, help = local_arg_doc!())]
+    pub local: bool,
+
+    /// minimum time to wait before checking pending notifications in seconds
+    #[arg(short, long, default_value_t = 1)]
+    pub min: u64,
+
+    /// max time to wait before checking pending notifications in seconds
+    #[arg(short('M'), long, default_value_t = 300)]
+    pub max: u64,
+
+    /// procrastinate at file
+    #[arg(short, long, help = file_arg_doc!())]
+    pub file: Option<PathBuf>,
+
+    #[arg(short, long)]
+    pub verbose: bool,
+
+    #[arg(long, value_name = "DELAY", help = max_age_arg_doc!())]
+    pub max_age: Option<Delay>,
+
+    /// Listen for control commands on a unix socket.
+    ///
+    /// Defaults to `$XDG_RUNTIME_DIR/procrastinate.sock` if no path is given.
+    /// Supported commands are `reload`, `list`, `snooze <key> <delay>` and `done <key>`.
+    #[arg(long, value_name = "PATH")]
+    #[allow(clippy::option_option)]
+    pub socket: Option<Option<PathBuf>>,
+
+    /// Serve `GET /entries` and `POST /entries/<key>/snooze` over http at the given address,
+    /// e.g. `127.0.0.1:8080`
+    ///
+    /// Only loopback addresses are allowed, unless `--http-token` is set.
+    #[cfg(feature = "http")]
+    #[arg(long, value_name = "ADDRESS")]
+    pub http: Option<std::net::SocketAddr>,
+
+    /// Require `Authorization: Bearer <TOKEN>` for all http requests
+    #[cfg(feature = "http")]
+    #[arg(
+        long,
+        value_name = "TOKEN",
+        env = "PROCRASTINATE_HTTP_TOKEN",
+        requires = "http"
+    )]
+    pub http_token: Option<String>,
+
+    /// Hold back notifications, except critical ones, while the user
+    /// has been idle for at least the given delay
+    #[arg(long, value_name = "DELAY")]
+    pub defer_when_idle: Option<Delay>,
+
+    /// How to watch the procrastination file for changes
+    #[arg(long, value_enum, default_value_t = WatchMode::Auto)]
+    pub watch_mode: WatchMode,
+
+    /// time between checks for file changes in seconds, if the file is polled
+    #[arg(long, default_value_t = 5)]
+    pub poll_interval: u64,
+
+    /// Deliver due notifications concurrently using up to the given number of threads
+    #[arg(long, value_name = "THREADS", default_value = "1")]
+    pub parallel: NonZeroUsize,
+
+    /// Notify at most this many procrastinations per check, the others are notified
+    /// by the next check
+    #[arg(long, value_name = "N", visible_alias = "max-notifications-per-run")]
+    pub max_per_run: Option<NonZeroUsize>,
+
+    /// Emit the D-Bus signal `dev.procrastinate.Fired` with the key of every
+    /// procrastination that fires
+    #[arg(long)]
+    pub emit_dbus: bool,
+
+    /// Run this shell command after each notification.
+    ///
+    /// The key and title are passed as `$1` and `$2` as well as in
+    /// `PROCRASTINATE_KEY` and `PROCRASTINATE_TITLE`.
+    #[arg(long, value_name = "CMD")]
+    pub on_fire: Option<String>,
+
+    /// Print when each procrastination notifies and when the daemon would check next, then exit.
+    ///
+    /// Nothing is notified and the file is not written.
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+fn init_logger(verbose: bool) {
+    let mut builder = Builder::new();
+    if verbose {
+        builder.filter_level(LevelFilter::Info);
+    } else {
+        builder.filter_level(LevelFilter::Error);
+    }
+    builder.parse_default_env();
+    builder.init();
+}
+
+async fn shutdown_signal() -> SignalKind {
+    let mut term =
+        signal(SignalKind::terminate()).expect("failed to create terminate signal handler");
+    let mut quit = signal(SignalKind::quit()).expect("failed to create quit signal handler");
+    let mut int =
+        signal(SignalKind::interrupt()).expect("failed to create interrupt signal handler");
+
+    select! {
+        _ = term.recv() => { SignalKind::terminate() }
+        _ = quit.recv() => { SignalKind::quit() }
+        _ = int.recv() => { SignalKind::interrupt() }
+    }
+}
+
+async fn work(args: &Args, config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+    let min_dur = Duration::from_secs(args.min);
+    let max_dur = Duration::from_secs(args.max);
+
+    let path = procrastination_path(args.local, args.file.as_ref())?;
+    let settings = config.notify_settings();
+    let command_file = CommandFile {
+        path: path.clone(),
+        backups: config.backups,
+    };
+
+    let idle_defer = args.defer_when_idle.map(|threshold| IdleDefer {
+        query: idle_query(),
+        threshold: threshold.to_duration().to_std().unwrap_or(Duration::ZERO),
+    });
+    let fire_signal = if args.emit_dbus {
+        Some(DBusFireSignal::connect()?)
+    } else {
+        None
+    };
+    let mut notification_ids = HashMap::new();
+    let mut unavailable_checks = 0;
+    let mut check = || {
+        check_for_notifications(
+            &path,
+            min_dur,
+            max_dur,
+            args.max_age,
+            idle_defer.as_ref(),
+            args.parallel,
+            args.max_per_run,
+            fire_signal.as_ref().map(|s| s as &dyn FireSignal),
+            args.on_fire.as_deref(),
+            &mut notification_ids,
+            &mut unavailable_checks,
+            config.backups,
+            &settings,
+        )
+    };
+
+    let timeout = check().unwrap_or(min_dur);
+    let mut sleep = Box::pin(tokio::time::sleep(timeout));
+    let mut clock = ClockReference::now();
+    let mut clock_jump_check = tokio::time::interval(CLOCK_JUMP_CHECK_INTERVAL);
+
+    let mut self_writes = SelfWriteFilter::default();
+    if let Ok(content) = std::fs::read(&path) {
+        self_writes.record(&content);
+    }
+
+    let poll_interval = Duration::from_secs(args.poll_interval);
+    let (_file_watcher, mut file_watch) = watch(&path, args.watch_mode, poll_interval)?;
+    let mut last_n_iters_failed = 0;
+
+    let mut shutdown_signal = Box::pin(shutdown_signal());
+
+    let control_socket = match args.socket.as_ref() {
+        Some(socket) => {
+            let socket_path = socket.clone().unwrap_or_else(default_socket_path);
+            log::info!("listening for control commands at {socket_path:?}");
+            Some(ControlSocket::bind(&socket_path)?)
+        }
+        None => None,
+    };
+    let (reload_tx, mut reload_rx) = mpsc::unbounded_channel();
+
+    #[cfg(feature = "http")]
+    if let Some(addr) = args.http {
+        http::check_address(addr, args.http_token.as_deref())?;
+        let listener = tokio::net::TcpListener::bind(addr).await?;
+        log::info!("listening for http requests at {addr}");
+        tokio::spawn(http::serve(
+            listener,
+            command_file.clone(),
+            reload_tx.clone(),
+            args.http_token.clone(),
+            settings.calendar,
+        ));
+    }
+
+    loop {
+        let accept = async {
+            match control_socket.as_ref() {
+                Some(socket) => socket.listener.accept().await,
+                None => std::future::pending().await,
+            }
+        };
+
+        // Wait for either timeout, file change or control command
+        select! {
+            _ = &mut sleep => {
+                log::info!("wake from timeout");
+            }
+            _ = clock_jump_check.tick() => {
+                if !clock.jumped(Local::now().naive_local(), Instant::now()) {
+                    continue;
+                }
+                log::info!("wake from wall clock jump");
+            }
+            connection = accept => {
+                match connection {
+                    Ok((stream, _)) => {
+                        tokio::spawn(handle_connection(
+                            stream,
+                            command_file.clone(),
+                            reload_tx.clone(),
+                            settings.calendar,
+                        ));
+                    }
+                    Err(e) => log::error!("Failed to accept control connection: {e}"),
+                }
+                continue;
+            }
+            _ = reload_rx.recv() => {
+                log::info!("wake from control socket");
+            }
+            next = file_watch.next() => {
+                log::info!("wake from file watch");
+                if next.is_none() {
+                    let err: Box<dyn Error> = "File watch stream closed".into();
+                    display_error_notification(err.as_ref());
+                    return Err(err);
+                }
+                if let Ok(content) = std::fs::read(&path) {
+                    if self_writes.is_unchanged(&content) {
+                        log::info!("ignore file change without new content");
+                        continue;
+                    }
+                }
+            }
+            signal = &mut shutdown_signal => {
+                log::info!("Shutdown signal {:?} recieved", signal);
+                return Ok(());
+            }
+        }
+        let timeout = match check() {
+            Ok(timeout) => {
+                last_n_iters_failed = 0;
+                timeout
+            }
+            Err(err) => {
+                display_error_notification(err.as_ref());
+                if last_n_iters_failed == 2 {
+                    let err: Box<dyn Error> =
+                        "Notification check failed to often. Stopping daemon".into();
+                    display_error_notification(err.as_ref());
+                    return Err(err);
+                }
+                last_n_iters_failed += 1;
+                min_dur
+            }
+        };
+        sleep = Box::pin(tokio::time::sleep(timeout));
+        clock = ClockReference::now();
+        if let Ok(content) = std::fs::read(&path) {
+            self_writes.record(&content);
+        }
+    }
+}
+
+/// Detects file watch events that are caused by the daemon saving the file itself.
+///
+/// The daemon records the file content after each check. Events that don't change
+/// the content since then can be ignored.
+#[derive(Debug, Default)]
+struct SelfWriteFilter {
+    last_hash: Option<u64>,
+}
+
+impl SelfWriteFilter {
+    fn hash(content: &[u8]) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        content.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// remember `content` as the current content of the file
+    fn record(&mut self, content: &[u8]) {
+        self.last_hash = Some(Self::hash(content));
+    }
+
+    /// returns `true` if `content` is the same as the last recorded content
+    fn is_unchanged(&self, content: &[u8]) -> bool {
+        self.last_hash == Some(Self::hash(content))
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    #[allow(unused_mut)]
+    let mut args = Args::parse();
+
+    init_logger(args.verbose);
+
+    #[cfg(debug_assertions)]
+    {
+        if std::env::var("PROCRASTINATE_DEBUG_LOCAL").is_ok() {
+            args.local = true;
+            eprintln!("local debug override active");
+        }
+    }
+
+    if args.verbose {
+        log::info!("args: {args:?}");
+    }
+
+    let config = Config::load(&config_path())?;
+
+    if args.dry_run {
+        let path = procrastination_path(args.local, args.file.as_ref())?;
+        let now = Local::now().naive_local();
+        for line in dry_run(
+            &path,
+            Duration::from_secs(args.min),
+            Duration::from_secs(args.max),
+            now,
+            &config.calendar(),
+        )? {
+            println!("{line}");
+        }
+        return Ok(());
+    }
+
+    match work(&args, &config).await {
+        Ok(o) => Ok(o),
+        Err(e) => {
+            log::error!("Daemon failed with: {e}");
+            Err(e)
+        }
+    }
+}
+
+fn display_error_notification(err: &dyn Error) {
+    Notification::new()
+        .summary("Procrastinate-Daemon error")
+        .body(&format!("{err}"))
+        .show()
+        .expect("failed to notify about previous error");
+}
+
+/// How the procrastination file is watched for changes
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchMode {
+    /// use inotify and fall back to polling if inotify is not available
+    Auto,
+    /// use the file system notifications of the os, e.g. inotify
+    Inotify,
+    /// check the file for changes in a fixed interval.
+    /// Use this on network file systems, which don't support inotify
+    Poll,
+}
+
+/// Watches `path` for changes using `mode`.
+fn watch(
+    path: &Path,
+    mode: WatchMode,
+    poll_interval: Duration,
+) -> notify::Result<(Box<dyn Watcher + Send>, WatchStream<()>)> {
+    let poll_config = notify::Config::default()
+        .with_poll_interval(poll_interval)
+        .with_compare_contents(true);
+    match mode {
+        WatchMode::Inotify => watch_with::<RecommendedWatcher>(path, Default::default()),
+        WatchMode::Poll => watch_with::<PollWatcher>(path, poll_config),
+        WatchMode::Auto => {
+            watch_with::<RecommendedWatcher>(path, Default::default()).or_else(|e| {
+                log::warn!("Failed to watch file, falling back to polling: {e}");
+                watch_with::<PollWatcher>(path, poll_config)
+            })
+        }
+    }
+}
+
+fn watch_with<W: Watcher + Send + 'static>(
+    path: &Path,
+    config: notify::Config,
+) -> notify::Result<(Box<dyn Watcher + Send>, WatchStream<()>)> {
+    let (tx, rx) = watch::channel(());
+
+    let mut watcher = W::new(
+        move |event: Result<notify::Event, notify::Error>| match event {
+            Ok(event) => {
+                if let notify::EventKind::Create(_) | notify::EventKind::Modify(_) = &event.kind {
+                    tx.send(()).unwrap()
+                }
+            }
+            Err(err) => {
+                log::error!("File watcher error: {err}");
+                panic!("File watcher error: {err}")
+            }
+        },
+        config,
+    )?;
+    watcher.watch(path, notify::RecursiveMode::Recursive)?;
+
+    Ok((Box::new(watcher), WatchStream::from_changes(rx)))
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::NaiveDate;
+    use procrastinate::{time::Repeat, Procrastination, ProcrastinationFileData};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_poll_watcher_detects_change() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("procrastination.ron");
+        std::fs::write(&path, "()").unwrap();
+
+        let (_watcher, mut changes) =
+            watch(&path, WatchMode::Poll, Duration::from_millis(20)).unwrap();
+        // let the watcher record the initial state
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        std::fs::write(&path, "(procrastinations: {})").unwrap();
+
+        let changed = tokio::time::timeout(Duration::from_secs(5), changes.next()).await;
+        assert!(matches!(changed, Ok(Some(()))));
+    }
+
+    #[test]
+    fn test_timeout_after_clock_jump() {
+        let wall = NaiveDate::from_ymd_opt(2024, 10, 27)
+            .unwrap()
+            .and_hms_opt(1, 0, 0)
+            .unwrap();
+        let next = wall + TimeDelta::hours(2);
+        let clock = ClockReference {
+            wall,
+            monotonic: Instant::now(),
+        };
+        assert_eq!(until(next - wall), Duration::from_secs(2 * 60 * 60));
+
+        let monotonic = clock.monotonic + Duration::from_secs(10);
+        assert!(!clock.jumped(wall + TimeDelta::seconds(10), monotonic));
+
+        // the wall clock moved an hour ahead during the 10 seconds
+        let jumped_wall = wall + TimeDelta::seconds(10) + TimeDelta::hours(1);
+        assert!(clock.jumped(jumped_wall, monotonic));
+        assert_eq!(until(next - jumped_wall), Duration::from_secs(60 * 60 - 10));
+
+        // the wall clock moved an hour back during the 10 seconds
+        let jumped_wall = wall + TimeDelta::seconds(10) - TimeDelta::hours(1);
+        assert!(clock.jumped(jumped_wall, monotonic));
+        assert_eq!(
+            until(next - jumped_wall),
+            Duration::from_secs(3 * 60 * 60 - 10)
+        );
+
+        assert_eq!(until(wall - next), Duration::MAX);
+    }
+
+    #[test]
+    fn test_dry_run() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("procrastination.ron");
+        let now = NaiveDate::from_ymd_opt(2024, 6, 3)
+            .unwrap()
+            .and_hms_opt(10, 0, 0)
+            .unwrap();
+        let mut data = ProcrastinationFileData::empty();
+        for (key, timestamp) in [("due", now - TimeDelta::hours(2)), ("later", now)] {
+            let mut proc = Procrastination::new(
+                key.to_string(),
+                String::new(),
+                Repeat::Repeat {
+                    timing: "1h".parse().unwrap(),
+                },
+                false,
+            );
+            proc.timestamp = timestamp.and_local_timezone(Local).unwrap();
+            data.insert(key.to_string(), proc);
+        }
+        let content = ron::to_string(&data).unwrap();
+        std::fs::write(&path, &content).unwrap();
+
+        let lines = dry_run(
+            &path,
+            Duration::from_secs(1),
+            Duration::from_secs(300),
+            now,
+            &Calendar::default(),
+        )
+        .unwrap();
+        let unchanged = std::fs::read_to_string(&path).unwrap() == content;
+
+        assert_eq!(
+            lines,
+            [
+                "due: due now",
+                "later: at 2024-06-03 11:00:00",
+                "next wake in 1s"
+            ]
+        );
+        assert!(unchanged);
+    }
+
+    #[test]
+    fn test_check_summary() {
+        assert_eq!(
+            check_summary(3, 1, Duration::from_secs(90 * 60)),
+            "checked 3 entries, fired 1, next wake in 1h 30min"
+        );
+        assert_eq!(
+            check_summary(0, 0, Duration::from_secs(60)),
+            "checked 0 entries, fired 0, next wake in 1min"
+        );
+    }
+
+    #[test]
+    fn test_self_write_filter() {
+        let mut filter = SelfWriteFilter::default();
+        assert!(!filter.is_unchanged(b"{}"));
+
+        filter.record(b"{}");
+        assert!(filter.is_unchanged(b"{}"));
+        assert!(!filter.is_unchanged(b"{\"key\": ()}"));
+
+        filter.record(b"{\"key\": ()}");
+        assert!(!filter.is_unchanged(b"{}"));
+    }
+}
@@ -0,0 +1,91 @@
+//! Broadcasts a D-Bus signal whenever a procrastination fires.
+//!
+//! The signal `dev.procrastinate.Fired` is emitted on the session bus from the
+//! object `/dev/procrastinate` with the key of the procrastination as its only
+//! argument, so panels and widgets can react to it.
+
+use std::error::Error;
+
+use zbus::blocking::Connection;
+
+pub const OBJECT_PATH: &str = "/dev/procrastinate";
+pub const INTERFACE: &str = "dev.procrastinate";
+pub const FIRED: &str = "Fired";
+
+/// Announces procrastinations that fired
+pub trait FireSignal {
+    fn fired(&self, key: &str) -> Result<(), Box<dyn Error>>;
+}
+
+/// Emits [FIRED] on the D-Bus session bus
+pub struct DBusFireSignal {
+    connection: Connection,
+}
+
+impl DBusFireSignal {
+    pub fn connect() -> zbus::Result<Self> {
+        Ok(Self {
+            connection: Connection::session()?,
+        })
+    }
+}
+
+impl FireSignal for DBusFireSignal {
+    fn fired(&self, key: &str) -> Result<(), Box<dyn Error>> {
+        self.connection.emit_signal(
+            None::<&str>,
+            OBJECT_PATH,
+            INTERFACE,
+            FIRED,
+            &fired_body(key),
+        )?;
+        Ok(())
+    }
+}
+
+/// The arguments of the [FIRED] signal
+fn fired_body(key: &str) -> (&str,) {
+    (key,)
+}
+
+/// Signals every key in `fired`. Failures are logged, but never stop the daemon.
+pub fn signal_fired(signal: &dyn FireSignal, fired: &[String]) {
+    for key in fired {
+        if let Err(e) = signal.fired(key) {
+            log::error!("Failed to signal \"{key}\" fired: {e}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::cell::RefCell;
+
+    use zbus::zvariant::{DynamicType, Signature};
+
+    use super::*;
+
+    #[derive(Default)]
+    struct RecordSignal(RefCell<Vec<String>>);
+
+    impl FireSignal for RecordSignal {
+        fn fired(&self, key: &str) -> Result<(), Box<dyn Error>> {
+            self.0.borrow_mut().push(key.to_string());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_fired_payload() {
+        let body = fired_body("work/standup");
+        assert_eq!(body.0, "work/standup");
+        assert_eq!(
+            body.dynamic_signature(),
+            Signature::from_static_str_unchecked("(s)")
+        );
+
+        let signal = RecordSignal::default();
+        signal_fired(&signal, &["a".to_string(), "work/standup".to_string()]);
+        assert_eq!(*signal.0.borrow(), ["a", "work/standup"]);
+    }
+}
@@ -1,9 +1,22 @@
-use std::{error::Error, path::PathBuf};
+use std::{
+    error::Error,
+    num::NonZeroUsize,
+    path::{Path, PathBuf},
+};
 
-use clap::Parser;
+use chrono::{Local, NaiveDateTime};
+use clap::{Parser, ValueEnum};
 use procrastinate::{
-    check_key_arg_doc, file_arg_doc, local_arg_doc, procrastination_path, ProcrastinationFile,
+    check_key_arg_doc,
+    config::{config_path, Config},
+    file_arg_doc, group_arg_doc, group_key,
+    hook::{fired_titles, run_fire_hooks},
+    in_group, json, limit_due, local_arg_doc, max_age_arg_doc, procrastination_path,
+    time::Delay,
+    DesktopNotifications, NotificationErrors, NotifyReport, NotifySettings, ProcrastinationFile,
+    ProcrastinationFileData,
 };
+use serde::Serialize;
 
 #[derive(Parser, Debug)]
 #[command(version, about)]
@@ -27,6 +40,69 @@ pub struct Args {
 
     #[arg(short, long)]
     pub verbose: bool,
+
+    #[arg(long, value_name = "DELAY", help = max_age_arg_doc!())]
+    pub max_age: Option<Delay>,
+
+    #[arg(long, help = group_arg_doc!())]
+    pub group: Option<String>,
+
+    /// Deliver due notifications concurrently using up to the given number of threads
+    #[arg(long, value_name = "THREADS")]
+    pub parallel: Option<NonZeroUsize>,
+
+    /// Notify at most this many procrastinations, the others are notified by the next run
+    #[arg(long, value_name = "N", visible_alias = "max-notifications-per-run")]
+    pub max_per_run: Option<NonZeroUsize>,
+
+    /// Print a summary of the notified procrastinations and the next notification
+    #[arg(long, value_enum, value_name = "FORMAT")]
+    pub report: Option<ReportFormat>,
+
+    /// Run this shell command after each notification.
+    ///
+    /// The key and title are passed as `$1` and `$2` as well as in
+    /// `PROCRASTINATE_KEY` and `PROCRASTINATE_TITLE`.
+    #[arg(long, value_name = "CMD")]
+    pub on_fire: Option<String>,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportFormat {
+    /// `{"fired": [<key>], "skipped": [<key>], "failed": [{"key": <key>, "error": <error>}], "next": <time or null>}`
+    Json,
+}
+
+#[derive(Serialize)]
+struct JsonReport<'a> {
+    fired: &'a [String],
+    skipped: &'a [String],
+    failed: Vec<JsonFailure<'a>>,
+    next: Option<json::Timestamp>,
+}
+
+#[derive(Serialize)]
+struct JsonFailure<'a> {
+    key: &'a str,
+    error: String,
+}
+
+/// Formats `report` and the `next` notification as json.
+fn json_report(report: &NotifyReport, next: Option<NaiveDateTime>) -> String {
+    let report = JsonReport {
+        fired: &report.fired,
+        skipped: &report.skipped,
+        failed: report
+            .failed
+            .iter()
+            .map(|(key, e)| JsonFailure {
+                key,
+                error: e.to_string(),
+            })
+            .collect(),
+        next: next.map(json::Timestamp),
+    };
+    serde_json::to_string(&report).expect("reports can be serialized")
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
@@ -45,21 +121,224 @@ fn main() -> Result<(), Box<dyn Error>> {
         println!("args: {args:?}");
     }
 
+    let config = Config::load(&config_path())?;
+
     let path = procrastination_path(args.local, args.file.as_ref())?;
+    let (report, data) = work(&args, &path, &config)?;
+
+    if let Some(ReportFormat::Json) = args.report {
+        let next = data.next_notification(&config.calendar())?;
+        println!("{}", json_report(&report, next));
+    }
+
+    Ok(NotificationErrors::from_errors(report.failed)?)
+}
+
+/// Notifies all due procrastinations at `path`.
+///
+/// The file is only written if a procrastination was notified or removed,
+/// so that runs with nothing to do don't wake the daemon.
+fn work(
+    args: &Args,
+    path: &Path,
+    config: &Config,
+) -> Result<(NotifyReport, ProcrastinationFileData), Box<dyn Error>> {
+    let settings = &config.notify_settings();
+    let calendar = &settings.calendar;
+    let now = Local::now().naive_local();
+    if !path.exists() {
+        log::info!("{path:?} does not exist, nothing to notify");
+        return Ok((NotifyReport::default(), ProcrastinationFileData::empty()));
+    }
+
     let mut procrastination =
-        ProcrastinationFile::open(&path).expect("could not open procrastination file");
+        ProcrastinationFile::open(path).expect("could not open procrastination file");
+    procrastination.set_backups(config.backups);
+
+    let mut changed = false;
+    if let Some(max_age) = args.max_age {
+        changed |= procrastination
+            .data_mut()
+            .remove_stale(now, max_age, calendar)?;
+    }
 
-    if let Some(key) = args.key.as_ref() {
+    let mut report = if let Some(key) = args.key.as_ref() {
+        let key = &group_key(args.group.as_deref(), key);
+        let defaults = procrastination.data().defaults.clone();
         if let Some(procrastination) = procrastination.data_mut().get_mut(key) {
-            procrastination.notify()?;
+            let mut report = NotifyReport::default();
+            report.push(key.clone(), procrastination.notify(&defaults, settings));
+            report
         } else {
             panic!("No procrastination with key \"{key}\" found");
         }
+    } else if args.parallel.is_some() || args.max_per_run.is_some() {
+        let group = args.group.as_deref();
+        let mut limit = args.max_per_run.map(|max| limit_due(max, *calendar));
+        procrastination.data_mut().notify_parallel_with(
+            args.parallel.unwrap_or(NonZeroUsize::MIN),
+            settings,
+            &DesktopNotifications,
+            |key, procrastination| {
+                in_group(key, group)
+                    && limit
+                        .as_mut()
+                        .map_or(true, |limit| limit(key, procrastination))
+            },
+        )
     } else {
-        procrastination.data_mut().notify_all()?;
+        procrastination.data_mut().notify_group_with(
+            args.group.as_deref(),
+            settings,
+            &mut DesktopNotifications,
+        )
+    };
+    retry_unavailable(procrastination.data_mut(), &mut report, settings);
+    changed |= !report.fired.is_empty();
+    let fired = fired_titles(procrastination.data(), &report.fired);
+    changed |= procrastination.data_mut().cleanup();
+    if changed {
+        // save the notified procrastinations, even if some notifications failed
+        procrastination.save()?;
+    } else {
+        log::info!("nothing changed, not writing {path:?}");
+    }
+    // release the lock before running the hooks, they might use procrastinate themselves
+    let data = procrastination.into_data();
+    if let Some(command) = args.on_fire.as_deref() {
+        run_fire_hooks(command, &fired);
+    }
+
+    Ok((report, data))
+}
+
+/// Notifies the procrastinations of `report` that failed because the notification server
+/// was not running again, waiting between the attempts as configured in `settings`.
+fn retry_unavailable(
+    data: &mut ProcrastinationFileData,
+    report: &mut NotifyReport,
+    settings: &NotifySettings,
+) {
+    let mut failed = 1;
+    while let Some(delay) = settings.retry.delay(failed) {
+        let (unavailable, failures): (Vec<_>, Vec<_>) = std::mem::take(&mut report.failed)
+            .into_iter()
+            .partition(|(_, e)| e.is_transient());
+        report.failed = failures;
+        if unavailable.is_empty() {
+            return;
+        }
+        log::warn!("notification server is not running, retrying in {delay:?}");
+        std::thread::sleep(delay);
+        let defaults = data.defaults.clone();
+        for (key, _) in unavailable {
+            if let Some(procrastination) = data.get_mut(&key) {
+                report.push(key, procrastination.notify(&defaults, settings));
+            }
+        }
+        failed += 1;
     }
-    procrastination.data_mut().cleanup();
-    procrastination.save()?;
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::{NaiveDate, TimeDelta};
+    use notify_rust::Notification;
+    use procrastinate::{time::Repeat, NotificationSink, Procrastination, ProcrastinationFileData};
+
+    use super::*;
 
-    Ok(())
+    struct NoopSink;
+
+    impl NotificationSink for NoopSink {
+        fn show(&mut self, _: &Notification) -> Result<(), notify_rust::error::Error> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_nothing_due_no_write() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("procrastination.ron");
+        let mut data = ProcrastinationFileData::empty();
+        let later = Procrastination::new(
+            "later".to_string(),
+            String::new(),
+            Repeat::Repeat {
+                timing: "1h".parse().unwrap(),
+            },
+            false,
+        );
+        data.insert("later".to_string(), later);
+        std::fs::write(&path, ron::to_string(&data).unwrap()).unwrap();
+        let modified = std::fs::metadata(&path).unwrap().modified().unwrap();
+
+        let args = Args::parse_from(["procrastinate-work", "--file", path.to_str().unwrap()]);
+        let (report, _) = work(&args, &path, &Config::default()).unwrap();
+        let unchanged = std::fs::metadata(&path).unwrap().modified().unwrap() == modified;
+
+        assert_eq!(report.skipped, vec!["later".to_string()]);
+        assert!(report.fired.is_empty());
+        assert!(unchanged);
+    }
+
+    #[test]
+    fn test_noop_no_save() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("procrastination.ron");
+        // due, but fails to notify as no location for the sunrise is configured
+        let mut data = ProcrastinationFileData::empty();
+        let mut sunrise = Procrastination::new(
+            "sunrise".to_string(),
+            String::new(),
+            Repeat::Once {
+                timing: "sunrise".parse().unwrap(),
+            },
+            false,
+        );
+        sunrise.timestamp = Local::now() - TimeDelta::days(1);
+        data.insert("sunrise".to_string(), sunrise);
+        let content = ron::to_string(&data).unwrap();
+        std::fs::write(&path, &content).unwrap();
+        let modified = std::fs::metadata(&path).unwrap().modified().unwrap();
+
+        let args = Args::parse_from(["procrastinate-work", "--file", path.to_str().unwrap()]);
+        let (report, _) = work(&args, &path, &Config::default()).unwrap();
+        let unchanged = std::fs::metadata(&path).unwrap().modified().unwrap() == modified
+            && std::fs::read_to_string(&path).unwrap() == content;
+
+        assert_eq!(report.failed.len(), 1);
+        assert!(report.fired.is_empty());
+        assert!(unchanged);
+    }
+
+    #[test]
+    fn test_json_report() {
+        let now = Local::now();
+        let mut data = ProcrastinationFileData::empty();
+        for (key, timing) in [("due", "5min"), ("later", "1h")] {
+            let mut proc = Procrastination::new(
+                key.to_string(),
+                String::new(),
+                Repeat::Repeat {
+                    timing: timing.parse().unwrap(),
+                },
+                false,
+            );
+            proc.timestamp = now - TimeDelta::minutes(10);
+            data.insert(key.to_string(), proc);
+        }
+
+        let report = data.notify_all_with(&NotifySettings::default(), &mut NoopSink);
+        assert_eq!(report.fired, vec!["due".to_string()]);
+
+        let next = NaiveDate::from_ymd_opt(2024, 3, 1)
+            .unwrap()
+            .and_hms_opt(9, 0, 0)
+            .unwrap();
+        assert_eq!(
+            json_report(&report, Some(next)),
+            r#"{"fired":["due"],"skipped":["later"],"failed":[],"next":"2024-03-01T09:00:00"}"#
+        );
+    }
 }
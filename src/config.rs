@@ -0,0 +1,168 @@
+use std::{
+    collections::HashMap,
+    env,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+use unwrap_infallible::UnwrapInfallible;
+
+use crate::{
+    locale::Locale,
+    time::{sun::Location, Calendar, WeekStart},
+    Error, NotifySettings, Priority,
+};
+
+pub const CONFIG_FILE_NAME: &str = "config.ron";
+pub const DEFAULT_CONFIG_LOCATION: &str = ".config";
+pub const CONFIG_DIR_NAME: &str = "procrastinate";
+
+/// User configuration, stored at [config_path]
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Config {
+    /// Named defaults for new procrastinations, see [TemplateDef]
+    #[serde(default)]
+    pub templates: HashMap<String, TemplateDef>,
+    /// The first day of the week, which decides e.g. if "sunday" is in the past or future
+    #[serde(default)]
+    pub week_start: WeekStart,
+    /// Icons for notifications of procrastinations without their own icon
+    #[serde(default)]
+    pub urgency_icons: UrgencyIcons,
+    /// Where to compute sunrise and sunset for timings like "sunset -30m"
+    #[serde(default)]
+    pub location: Option<Location>,
+    /// How often to retry notifications while the notification server is unavailable
+    #[serde(default)]
+    pub notification_retry: NotificationRetry,
+    /// How many backups of the procrastination file are kept when it is saved, 0 keeps none
+    #[serde(default)]
+    pub backups: usize,
+}
+
+/// Retries of notifications that fail because the notification server is unavailable,
+/// e.g. `(attempts: 3, backoff_ms: 200)`.
+///
+/// The wait between attempts starts at `backoff_ms` and doubles after each attempt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NotificationRetry {
+    /// attempts including the first one, at least 1
+    pub attempts: u32,
+    pub backoff_ms: u64,
+}
+
+impl NotificationRetry {
+    /// The wait after `failed` failed attempts, `None` once all attempts are used
+    pub fn delay(&self, failed: u32) -> Option<Duration> {
+        if failed >= self.attempts {
+            return None;
+        }
+        let factor = 2u32.saturating_pow(failed.saturating_sub(1));
+        Some(Duration::from_millis(self.backoff_ms).saturating_mul(factor))
+    }
+}
+
+impl Default for NotificationRetry {
+    fn default() -> Self {
+        DEFAULT_NOTIFICATION_RETRY
+    }
+}
+
+const DEFAULT_NOTIFICATION_RETRY: NotificationRetry = NotificationRetry {
+    attempts: 3,
+    backoff_ms: 200,
+};
+
+/// Notification icons by [Priority], e.g. `(low: Some("dialog-information"), critical: Some("dialog-warning"))`.
+///
+/// Procrastinations with their own icon ignore these.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UrgencyIcons {
+    #[serde(default)]
+    pub low: Option<String>,
+    #[serde(default)]
+    pub normal: Option<String>,
+    #[serde(default)]
+    pub critical: Option<String>,
+}
+
+impl UrgencyIcons {
+    pub fn icon(&self, priority: Priority) -> Option<&str> {
+        match priority {
+            Priority::Low => self.low.as_deref(),
+            Priority::Normal => self.normal.as_deref(),
+            Priority::Critical => self.critical.as_deref(),
+        }
+    }
+}
+
+/// Default values for a new procrastination.
+///
+/// Any value given on the command line takes precedence over the template.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct TemplateDef {
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub message: Option<String>,
+    /// The timing in the same format as on the command line, e.g. `"monday 9:00"`.
+    ///
+    /// This is parsed as once or repeat timing, depending on the command it is used with.
+    #[serde(default)]
+    pub timing: Option<String>,
+    #[serde(default)]
+    pub sticky: bool,
+}
+
+pub fn config_dir_path() -> PathBuf {
+    if let Ok(config) = env::var("XDG_CONFIG_HOME") {
+        PathBuf::from_str(&config).unwrap_infallible()
+    } else {
+        let home = env::var("HOME").expect("neither XDG_CONFIG_HOME nor HOME are set");
+        let home = PathBuf::from_str(&home).unwrap_infallible();
+        home.join(DEFAULT_CONFIG_LOCATION)
+    }
+}
+
+pub fn config_path() -> PathBuf {
+    config_dir_path()
+        .join(CONFIG_DIR_NAME)
+        .join(CONFIG_FILE_NAME)
+}
+
+impl Config {
+    /// Loads the config at `path`. Returns the default config if the file does not exist.
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = std::fs::read_to_string(path)?;
+        Ok(ron::from_str(&content)?)
+    }
+
+    /// The settings dates are computed and named with.
+    ///
+    /// The locale is taken from the environment, see [Locale::from_env].
+    pub fn calendar(&self) -> Calendar {
+        Calendar {
+            week_start: self.week_start,
+            locale: Locale::from_env(),
+            location: self.location,
+        }
+    }
+
+    /// The settings notifications are shown with
+    pub fn notify_settings(&self) -> NotifySettings {
+        NotifySettings {
+            calendar: self.calendar(),
+            urgency_icons: self.urgency_icons.clone(),
+            retry: self.notification_retry,
+        }
+    }
+
+    pub fn template(&self, name: &str) -> Option<&TemplateDef> {
+        self.templates.get(name)
+    }
+}
@@ -0,0 +1,132 @@
+//! Import procrastinations from crontab files
+
+use crate::{
+    time::{cron_schedule, Repeat, RepeatTiming},
+    Procrastination,
+};
+
+/// The result of [parse_crontab]
+#[derive(Debug)]
+pub struct CrontabImport {
+    /// a cron procrastination for every valid line, by key
+    pub entries: Vec<(String, Procrastination)>,
+    /// a message for every skipped line
+    pub warnings: Vec<String>,
+}
+
+/// Creates a [RepeatTiming::Cron] procrastination for every line of `content`.
+///
+/// Lines have the form `min hour day-of-month month day-of-week command # title`.
+/// The command is used as message and the title defaults to the command.
+/// Keys are derived from the title, e.g. `backup-photos`.
+/// Comment-only and malformed lines are skipped with a warning.
+pub fn parse_crontab(content: &str) -> CrontabImport {
+    let mut entries: Vec<(String, Procrastination)> = Vec::new();
+    let mut warnings = Vec::new();
+
+    for (index, line) in content.lines().enumerate() {
+        let line_number = index + 1;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        if line.starts_with('#') {
+            warnings.push(format!("line {line_number}: skipped comment"));
+            continue;
+        }
+
+        let (entry, comment) = match line.split_once('#') {
+            Some((entry, comment)) => (entry.trim(), Some(comment.trim())),
+            None => (line, None),
+        };
+        let fields: Vec<_> = entry.split_whitespace().collect();
+        if fields.len() < 6 {
+            warnings.push(format!(
+                "line {line_number}: expected 5 cron fields and a command: \"{line}\""
+            ));
+            continue;
+        }
+        let expr = fields[..5].join(" ");
+        if let Err(e) = cron_schedule(&expr) {
+            warnings.push(format!("line {line_number}: {e}"));
+            continue;
+        }
+        let command = fields[5..].join(" ");
+        let title = comment
+            .filter(|comment| !comment.is_empty())
+            .unwrap_or(&command)
+            .to_string();
+
+        let mut key = slug(&title);
+        if entries.iter().any(|(existing, _)| *existing == key) {
+            key = format!("{key}-{line_number}");
+        }
+        let timing = Repeat::Repeat {
+            timing: RepeatTiming::Cron(expr),
+        };
+        entries.push((key, Procrastination::new(title, command, timing, false)));
+    }
+
+    CrontabImport { entries, warnings }
+}
+
+/// A key for `title`, with all non alphanumeric characters replaced by `-`
+fn slug(title: &str) -> String {
+    let slug = title
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-");
+    if slug.is_empty() {
+        "cron".to_string()
+    } else {
+        slug
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_crontab() {
+        let crontab = "\
+# m h dom mon dow command
+0 9 * * 1-5 notify-send standup # Daily standup
+*/30 * * * * drink-water.sh
+
+@reboot start.sh
+61 * * * * invalid # Invalid minute
+";
+        let import = parse_crontab(crontab);
+
+        assert_eq!(import.entries.len(), 2);
+
+        let (key, standup) = &import.entries[0];
+        assert_eq!(key, "daily-standup");
+        assert_eq!(standup.title, "Daily standup");
+        assert_eq!(standup.message, "notify-send standup");
+        assert_eq!(
+            standup.timing,
+            Repeat::Repeat {
+                timing: RepeatTiming::Cron("0 9 * * 1-5".to_string())
+            }
+        );
+
+        let (key, water) = &import.entries[1];
+        assert_eq!(key, "drink-water-sh");
+        assert_eq!(water.title, "drink-water.sh");
+        assert_eq!(
+            water.timing,
+            Repeat::Repeat {
+                timing: RepeatTiming::Cron("*/30 * * * *".to_string())
+            }
+        );
+
+        assert_eq!(import.warnings.len(), 3, "{:?}", import.warnings);
+        assert!(import.warnings[0].starts_with("line 1:"));
+        assert!(import.warnings[1].starts_with("line 5:"));
+        assert!(import.warnings[2].starts_with("line 6:"));
+    }
+}
@@ -0,0 +1,93 @@
+//! Commands run after a procrastination fired, see `--on-fire`
+
+use std::process::Command;
+
+use crate::ProcrastinationFileData;
+
+/// Runs `command` with `sh` for a procrastination that fired.
+///
+/// The key and title are passed as `$1` and `$2` as well as in `PROCRASTINATE_KEY`
+/// and `PROCRASTINATE_TITLE`. Failures are logged, so that they never abort notifying.
+pub fn run_fire_hook(command: &str, key: &str, title: &str) {
+    let status = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .arg("sh")
+        .arg(key)
+        .arg(title)
+        .env("PROCRASTINATE_KEY", key)
+        .env("PROCRASTINATE_TITLE", title)
+        .status();
+    match status {
+        Ok(status) if status.success() => {}
+        Ok(status) => log::error!("on fire hook for \"{key}\" failed: {status}"),
+        Err(e) => log::error!("Failed to run on fire hook for \"{key}\": {e}"),
+    }
+}
+
+/// The keys in `fired` with the titles of their procrastinations.
+///
+/// Collected before the file is cleaned up and saved, so that the hooks can run
+/// after its lock is released.
+pub fn fired_titles(data: &ProcrastinationFileData, fired: &[String]) -> Vec<(String, String)> {
+    fired
+        .iter()
+        .map(|key| {
+            let title = data.get(key).map_or(String::new(), |procrastination| {
+                procrastination.title.clone()
+            });
+            (key.clone(), title)
+        })
+        .collect()
+}
+
+/// Runs `command` for each key and title in `fired`, see [run_fire_hook]
+pub fn run_fire_hooks(command: &str, fired: &[(String, String)]) {
+    for (key, title) in fired {
+        run_fire_hook(command, key, title);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_fire_hook() {
+        let dir = tempfile::tempdir().unwrap();
+        let marker = dir.path().join("hook");
+        let command = format!(
+            "printf '%s|%s|%s' \"$1\" \"$2\" \"$PROCRASTINATE_KEY\" > '{}'",
+            marker.display()
+        );
+        run_fire_hook(&command, "work/standup", "Daily standup");
+        let written = std::fs::read_to_string(&marker).unwrap();
+        assert_eq!(written, "work/standup|Daily standup|work/standup");
+
+        // failures are only logged
+        run_fire_hook("exit 3", "key", "title");
+    }
+
+    #[test]
+    fn test_fired_titles() {
+        let mut data = ProcrastinationFileData::empty();
+        let standup = crate::Procrastination::new(
+            "Daily standup".to_string(),
+            String::new(),
+            crate::time::Repeat::Repeat {
+                timing: "daily 9:00".parse().unwrap(),
+            },
+            false,
+        );
+        data.insert("work/standup".to_string(), standup);
+
+        let fired = ["work/standup".to_string(), "removed".to_string()];
+        assert_eq!(
+            fired_titles(&data, &fired),
+            [
+                ("work/standup".to_string(), "Daily standup".to_string()),
+                ("removed".to_string(), String::new()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,137 @@
+//! iCalendar output of the agenda, see `procrastinate list --agenda-ics`
+
+use std::fmt::Write;
+
+use chrono::{Local, NaiveDateTime, Utc};
+
+use crate::{local_datetime, AgendaDay};
+
+/// The maximum length of a content line in octets, longer lines are folded
+const MAX_LINE_OCTETS: usize = 75;
+
+/// An iCalendar feed with a `VEVENT` for every notification in `agenda`.
+///
+/// Repeats are written as individual events instead of `RRULE`s, which simple
+/// calendar readers handle better. Times are floating local times, except for
+/// `DTSTAMP`, which has to be in UTC.
+pub fn agenda(agenda: &[AgendaDay], now: NaiveDateTime) -> String {
+    let mut ics = String::new();
+    let mut line = |content: &str| fold(content, &mut ics);
+    let stamp = local_datetime(now, &Local).with_timezone(&Utc).naive_utc();
+    line("BEGIN:VCALENDAR");
+    line("VERSION:2.0");
+    line("PRODID:-//procrastinate//agenda//EN");
+    for (time, key, procrastination) in agenda.iter().flat_map(|day| day.entries.iter()) {
+        line("BEGIN:VEVENT");
+        line(&format!(
+            "UID:{}-{}@procrastinate",
+            escape(key),
+            timestamp(*time)
+        ));
+        line(&format!("DTSTAMP:{}Z", timestamp(stamp)));
+        line(&format!("DTSTART:{}", timestamp(*time)));
+        line(&format!("SUMMARY:{}", escape(&procrastination.title)));
+        if !procrastination.message.is_empty() {
+            line(&format!("DESCRIPTION:{}", escape(&procrastination.message)));
+        }
+        line("END:VEVENT");
+    }
+    line("END:VCALENDAR");
+    ics
+}
+
+/// Appends `content` to `ics` as lines of at most [MAX_LINE_OCTETS] octets.
+///
+/// Continuation lines start with a space, which counts towards their length.
+/// Lines are only split between characters, never within one.
+fn fold(content: &str, ics: &mut String) {
+    let mut length = 0;
+    for c in content.chars() {
+        if length + c.len_utf8() > MAX_LINE_OCTETS {
+            ics.push_str("\r\n ");
+            length = 1;
+        }
+        ics.push(c);
+        length += c.len_utf8();
+    }
+    ics.push_str("\r\n");
+}
+
+/// A date time without a time zone, e.g. `20240603T090000`
+fn timestamp(time: NaiveDateTime) -> String {
+    time.format("%Y%m%dT%H%M%S").to_string()
+}
+
+/// Escapes a text value
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' | ';' | ',' => {
+                let _ = write!(escaped, "\\{c}");
+            }
+            '\n' => escaped.push_str("\\n"),
+            '\r' => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::{Local, NaiveDate};
+
+    use super::*;
+    use crate::{
+        time::{Calendar, Repeat},
+        Procrastination, ProcrastinationFileData,
+    };
+
+    #[test]
+    fn test_daily_events() {
+        let now = NaiveDate::from_ymd_opt(2024, 6, 3)
+            .unwrap()
+            .and_hms_opt(8, 0, 0)
+            .unwrap();
+        let mut daily = Procrastination::new(
+            "Standup, daily".to_string(),
+            "notes\nagain".to_string(),
+            Repeat::Repeat {
+                timing: "daily 9:00".parse().unwrap(),
+            },
+            false,
+        );
+        daily.timestamp = now.and_local_timezone(Local).unwrap();
+        let mut data = ProcrastinationFileData::empty();
+        data.insert("standup".to_string(), daily);
+
+        let ics = agenda(&data.agenda(now, 30, &Calendar::default()).unwrap(), now);
+        assert_eq!(ics.matches("BEGIN:VEVENT\r\n").count(), 30);
+        assert!(ics.starts_with("BEGIN:VCALENDAR\r\n"));
+        assert!(ics.ends_with("END:VCALENDAR\r\n"));
+        assert!(ics.contains("DTSTART:20240603T090000\r\n"));
+        assert!(ics.contains("DTSTART:20240702T090000\r\n"));
+        assert!(ics.contains("SUMMARY:Standup\\, daily\r\n"));
+        assert!(ics.contains("DESCRIPTION:notes\\nagain\r\n"));
+        let stamp = now.and_local_timezone(Local).unwrap().with_timezone(&Utc);
+        assert!(ics.contains(&format!("DTSTAMP:{}Z\r\n", stamp.format("%Y%m%dT%H%M%S"))));
+    }
+
+    #[test]
+    fn test_fold() {
+        let content = format!("SUMMARY:{}", "ü".repeat(80));
+        let mut ics = String::new();
+        fold(&content, &mut ics);
+
+        let lines: Vec<_> = ics.strip_suffix("\r\n").unwrap().split("\r\n").collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines.iter().all(|line| line.len() <= MAX_LINE_OCTETS));
+        assert!(lines[1..].iter().all(|line| line.starts_with(' ')));
+        assert_eq!(ics.replace("\r\n ", ""), format!("{content}\r\n"));
+
+        let mut ics = String::new();
+        fold("BEGIN:VEVENT", &mut ics);
+        assert_eq!(ics, "BEGIN:VEVENT\r\n");
+    }
+}
@@ -0,0 +1,123 @@
+//! Json output for scripts
+
+use chrono::NaiveDateTime;
+use serde::{Serialize, Serializer};
+
+use crate::{
+    time::{Calendar, Repeat},
+    Procrastination,
+};
+
+/// The version of the `list --json` format. Increased on incompatible changes.
+pub const LIST_SCHEMA: u32 = 1;
+
+/// A timestamp serialized as string, e.g. `"2024-03-01T09:00:00"`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Timestamp(pub NaiveDateTime);
+
+impl Serialize for Timestamp {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(&self.0.format("%Y-%m-%dT%H:%M:%S"))
+    }
+}
+
+/// The output of `procrastinate list --json`
+#[derive(Debug, Serialize)]
+pub struct List<'a> {
+    schema: u32,
+    entries: Vec<Entry<'a>>,
+}
+
+#[derive(Debug, Serialize)]
+struct Entry<'a> {
+    key: &'a str,
+    title: &'a str,
+    message: &'a str,
+    next: Option<Timestamp>,
+    created: Timestamp,
+    flags: Vec<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    timing: Option<&'a Repeat>,
+}
+
+impl<'a> List<'a> {
+    /// Each entry contains `key`, `title`, `message`, `next`, `created` and `flags`.
+    /// With `flatten_timing` entries also contain the `timing` as stored in the file,
+    /// e.g. `{"Repeat": {"timing": {"Delay": {"Seconds": 3600}}}}`.
+    pub fn new(
+        entries: &[(&'a String, &'a Procrastination)],
+        flatten_timing: bool,
+        calendar: &Calendar,
+    ) -> Self {
+        let entries = entries
+            .iter()
+            .map(|(key, procrastination)| Entry {
+                key,
+                title: &procrastination.title,
+                message: &procrastination.message,
+                next: procrastination
+                    .next_notification(calendar)
+                    .ok()
+                    .map(|(_, next)| Timestamp(next)),
+                created: Timestamp(procrastination.timestamp.naive_local()),
+                flags: procrastination.flags(),
+                timing: flatten_timing.then_some(&procrastination.timing),
+            })
+            .collect();
+        Self {
+            schema: LIST_SCHEMA,
+            entries,
+        }
+    }
+}
+
+/// The procrastinations as `{"schema": 1, "entries": [...]}`, see [List::new].
+pub fn list(
+    entries: &[(&String, &Procrastination)],
+    flatten_timing: bool,
+    calendar: &Calendar,
+) -> String {
+    serde_json::to_string(&List::new(entries, flatten_timing, calendar))
+        .expect("lists can be serialized")
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::{Local, NaiveDate};
+
+    use super::*;
+    use crate::time::Repeat;
+
+    #[test]
+    fn test_list() {
+        let mut proc = Procrastination::new(
+            "Title".to_string(),
+            "message".to_string(),
+            Repeat::Repeat {
+                timing: "1h".parse().unwrap(),
+            },
+            false,
+        );
+        proc.timestamp = NaiveDate::from_ymd_opt(2024, 3, 1)
+            .unwrap()
+            .and_hms_opt(9, 0, 0)
+            .unwrap()
+            .and_local_timezone(Local)
+            .unwrap();
+        let key = "key".to_string();
+
+        assert_eq!(
+            list(&[(&key, &proc)], false, &Calendar::default()),
+            r#"{"schema":1,"entries":[{"key":"key","title":"Title","message":"message","next":"2024-03-01T10:00:00","created":"2024-03-01T09:00:00","flags":["repeating"]}]}"#
+        );
+        assert_eq!(
+            list(&[], false, &Calendar::default()),
+            r#"{"schema":1,"entries":[]}"#
+        );
+
+        assert_eq!(
+            list(&[(&key, &proc)], true, &Calendar::default()),
+            r#"{"schema":1,"entries":[{"key":"key","title":"Title","message":"message","next":"2024-03-01T10:00:00","created":"2024-03-01T09:00:00","flags":["repeating"],"timing":{"Repeat":{"timing":{"Delay":{"Seconds":3600}}}}}]}"#
+        );
+    }
+}
@@ -1,48 +1,298 @@
 pub mod arg_help;
+pub mod config;
+pub mod crontab;
+pub mod hook;
+pub mod ics;
+pub mod json;
+pub mod list_format;
+pub mod locale;
+pub mod markdown;
 pub mod nom_ext;
 pub mod time;
 
 use std::{
-    collections::HashMap,
+    collections::{hash_map::Entry, HashMap},
     env,
-    io::{Read, Write},
+    fs::Permissions,
+    io::{Read, Seek, Write},
+    num::NonZeroUsize,
     path::{Path, PathBuf},
     str::FromStr,
+    sync::OnceLock,
 };
 
 use chrono::{
-    format::DelayedFormat, DateTime, Datelike, Local, NaiveDateTime, NaiveTime, TimeDelta, Timelike,
+    format::DelayedFormat, offset::LocalResult, DateTime, Datelike, Local, Months, NaiveDate,
+    NaiveDateTime, NaiveTime, Offset, TimeDelta, TimeZone, Timelike,
 };
 use file_lock::{FileLock, FileOptions};
+use locale::{Locale, LocalizedDisplay};
 use notify_rust::Notification;
 use ron::ser::PrettyConfig;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
-use time::{Delay, OnceTiming, TimeError};
+use time::{Calendar, Delay, OnceTiming, RepeatTiming, RoughInstant, TimeError};
 use unwrap_infallible::UnwrapInfallible;
 
 use crate::time::Repeat;
 
 #[derive(Debug, Serialize, Deserialize)]
-#[serde(transparent)]
-pub struct ProcrastinationFileData(HashMap<String, Procrastination>);
+pub struct ProcrastinationFileData {
+    #[serde(default)]
+    pub defaults: FileDefaults,
+    procrastinations: HashMap<String, Procrastination>,
+}
+
+/// Settings that apply to all procrastinations in a file
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct FileDefaults {
+    /// how long notifications are shown, unless they are sticky
+    #[serde(default)]
+    pub timeout_ms: Option<u32>,
+    /// how titles that are too long for the summary are shown
+    #[serde(default)]
+    pub summary_overflow: SummaryOverflow,
+    /// only critical procrastinations notify during these hours, all others are
+    /// deferred until the quiet hours end
+    #[serde(default)]
+    pub quiet_hours: Option<QuietHours>,
+}
+
+/// Settings from the [config::Config] that notifications are shown with
+#[derive(Debug, Default, Clone)]
+pub struct NotifySettings {
+    pub calendar: Calendar,
+    /// icons for procrastinations without their own icon
+    pub urgency_icons: config::UrgencyIcons,
+    /// how often [transient](NotificationError::is_transient) failures are retried
+    pub retry: config::NotificationRetry,
+}
+
+/// A daily time range, e.g. `(start: "22:00:00", end: "07:00:00")`.
+///
+/// If `end` is before `start` the range spans midnight.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuietHours {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+impl QuietHours {
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        if self.start <= self.end {
+            self.start <= time && time < self.end
+        } else {
+            self.start <= time || time < self.end
+        }
+    }
+
+    /// The time from `now` until the quiet hours end next
+    pub fn until_end(&self, now: NaiveDateTime) -> TimeDelta {
+        let end = now.date().and_time(self.end);
+        if end > now {
+            end - now
+        } else {
+            end + TimeDelta::days(1) - now
+        }
+    }
+
+    /// If a notification with `priority` is deferred at `now`
+    pub fn defers(&self, priority: Priority, now: NaiveDateTime) -> bool {
+        priority != Priority::Critical && self.contains(now.time())
+    }
+}
+
+/// The schema version of procrastination files written by this version,
+/// see [ProcrastinationFileData::schema]
+pub const FILE_SCHEMA: u32 = 2;
+
+/// Formats [ProcrastinationFileData] can be read from and written in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum FileFormat {
+    /// the format of procrastination files
+    Ron,
+    Json,
+}
 
 impl ProcrastinationFileData {
     pub fn empty() -> Self {
-        Self(HashMap::new())
+        Self {
+            defaults: FileDefaults::default(),
+            procrastinations: HashMap::new(),
+        }
+    }
+
+    /// Parses the content of a procrastination file.
+    ///
+    /// Files of older versions only contain the map of procrastinations. These are still supported.
+    /// An empty file contains no procrastinations.
+    pub fn from_ron(content: &str) -> Result<Self, ron::error::SpannedError> {
+        match Self::schema(content) {
+            None => Ok(Self::empty()),
+            Some(1) => Ok(Self {
+                defaults: FileDefaults::default(),
+                procrastinations: ron::from_str(content)?,
+            }),
+            Some(_) => ron::from_str(content),
+        }
+    }
+
+    /// The schema version of the procrastination file `content`, `None` if it is empty.
+    ///
+    /// Version 1 files only contain the map of procrastinations,
+    /// version 2 files also contain [FileDefaults].
+    pub fn schema(content: &str) -> Option<u32> {
+        if content.trim().is_empty() {
+            None
+        } else if content.trim_start().starts_with('{') {
+            Some(1)
+        } else {
+            Some(FILE_SCHEMA)
+        }
+    }
+
+    /// Reads procrastinations in the given `format`, see [Self::from_ron] for RON.
+    pub fn from_reader<R: Read>(mut reader: R, format: FileFormat) -> Result<Self, Error> {
+        match format {
+            FileFormat::Ron => {
+                let mut content = String::new();
+                reader.read_to_string(&mut content)?;
+                Ok(Self::from_ron(&content)?)
+            }
+            FileFormat::Json => Ok(serde_json::from_reader(reader)?),
+        }
     }
 
-    pub fn notify_all(&mut self) -> Result<(), NotificationError> {
-        for procrastination in self.0.values_mut() {
-            procrastination.notify()?;
+    /// Writes all procrastinations in the given `format`.
+    pub fn to_writer<W: Write>(&self, writer: W, format: FileFormat) -> Result<(), Error> {
+        match format {
+            FileFormat::Ron => ron::ser::to_writer_pretty(writer, self, PrettyConfig::default())?,
+            FileFormat::Json => serde_json::to_writer_pretty(writer, self)?,
         }
         Ok(())
     }
 
+    pub fn notify_all(&mut self, settings: &NotifySettings) -> Result<(), NotificationErrors> {
+        NotificationErrors::from_errors(
+            self.notify_all_with(settings, &mut DesktopNotifications)
+                .failed,
+        )
+    }
+
+    /// Notifies all procrastinations that are due using `sink`.
+    ///
+    /// A failed notification does not stop the remaining procrastinations from being notified.
+    pub fn notify_all_with(
+        &mut self,
+        settings: &NotifySettings,
+        sink: &mut impl NotificationSink,
+    ) -> NotifyReport {
+        self.notify_group_with(None, settings, sink)
+    }
+
+    /// Notifies all procrastinations in `group` that are due using `sink`.
+    ///
+    /// All procrastinations are notified if `group` is `None`, see [in_group].
+    pub fn notify_group_with(
+        &mut self,
+        group: Option<&str>,
+        settings: &NotifySettings,
+        sink: &mut impl NotificationSink,
+    ) -> NotifyReport {
+        let mut report = NotifyReport::default();
+        let mut entries: Vec<_> = self
+            .procrastinations
+            .iter_mut()
+            .filter(|(key, _)| in_group(key, group))
+            .collect();
+        entries.sort_by(|a, b| a.0.cmp(b.0));
+        for (key, procrastination) in entries {
+            report.push(
+                key.clone(),
+                procrastination.notify_with(&self.defaults, settings, sink),
+            );
+        }
+        report
+    }
+
+    /// Notifies all procrastinations accepted by `filter` that are due, using up to
+    /// `parallel` threads with a clone of `sink` each.
+    ///
+    /// `filter` is called in key order before any notification is shown.
+    /// The report is ordered by key, independent of the order notifications are delivered in.
+    pub fn notify_parallel_with<S>(
+        &mut self,
+        parallel: NonZeroUsize,
+        settings: &NotifySettings,
+        sink: &S,
+        mut filter: impl FnMut(&str, &Procrastination) -> bool,
+    ) -> NotifyReport
+    where
+        S: NotificationSink + Clone + Send,
+    {
+        let mut entries: Vec<_> = self.procrastinations.iter_mut().collect();
+        entries.sort_by(|a, b| a.0.cmp(b.0));
+        entries.retain(|(key, procrastination)| filter(key, procrastination));
+
+        let defaults = &self.defaults;
+        let notify_chunk = |chunk: &mut [(&String, &mut Procrastination)], mut sink: S| {
+            chunk
+                .iter_mut()
+                .map(|(key, procrastination)| {
+                    (
+                        key.to_string(),
+                        procrastination.notify_with(defaults, settings, &mut sink),
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let chunk_size = entries.len().div_ceil(parallel.get()).max(1);
+        let results: Vec<_> = if parallel.get() == 1 {
+            notify_chunk(&mut entries, sink.clone())
+        } else {
+            std::thread::scope(|scope| {
+                let handles: Vec<_> = entries
+                    .chunks_mut(chunk_size)
+                    .map(|chunk| {
+                        let sink = sink.clone();
+                        scope.spawn(move || notify_chunk(chunk, sink))
+                    })
+                    .collect();
+                handles
+                    .into_iter()
+                    .flat_map(|handle| handle.join().expect("notification thread panicked"))
+                    .collect()
+            })
+        };
+
+        let mut report = NotifyReport::default();
+        for (key, result) in results {
+            report.push(key, result);
+        }
+        report
+    }
+
+    /// The earliest next notification of all procrastinations that can still notify.
+    pub fn next_notification(
+        &self,
+        calendar: &Calendar,
+    ) -> Result<Option<NaiveDateTime>, TimeError> {
+        let mut next = None;
+        for procrastination in self.procrastinations.values() {
+            if !procrastination.can_notify_in_future() {
+                continue;
+            }
+            let (_, at) = procrastination.next_notification(calendar)?;
+            next = Some(next.map_or(at, |next: NaiveDateTime| next.min(at)));
+        }
+        Ok(next)
+    }
+
     /// delete already send notifications that are Timing::Once
     pub fn cleanup(&mut self) -> bool {
         let mut changed = false;
-        self.0.retain(|_k, v| {
+        self.procrastinations.retain(|_k, v| {
             let retain = v.dirty != Dirt::Delete;
             if !retain {
                 changed = true;
@@ -52,28 +302,211 @@ impl ProcrastinationFileData {
         changed
     }
 
+    /// delete [Repeat::Once] procrastinations whose notification is more than `max_age` overdue,
+    /// without notifying.
+    pub fn remove_stale(
+        &mut self,
+        now: NaiveDateTime,
+        max_age: Delay,
+        calendar: &Calendar,
+    ) -> Result<bool, TimeError> {
+        let mut stale = Vec::new();
+        for (key, procrastination) in self.procrastinations.iter() {
+            if procrastination.is_stale(now, max_age, calendar)? {
+                stale.push(key.clone());
+            }
+        }
+        for key in stale.iter() {
+            log::info!("Removing stale procrastination \"{key}\"");
+            self.procrastinations.remove(key);
+        }
+        Ok(!stale.is_empty())
+    }
+
+    /// Inserts all procrastinations of `other`, resolving duplicate keys with `on_conflict`.
+    ///
+    /// Returns the conflicting keys of `other` and the key they are stored as, ordered by key.
+    pub fn merge(
+        &mut self,
+        other: ProcrastinationFileData,
+        on_conflict: OnConflict,
+    ) -> Vec<(String, String)> {
+        let mut other: Vec<_> = other.procrastinations.into_iter().collect();
+        other.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let mut conflicts = Vec::new();
+        for (key, procrastination) in other {
+            if let Entry::Vacant(entry) = self.procrastinations.entry(key.clone()) {
+                entry.insert(procrastination);
+                continue;
+            }
+            let new_key = match on_conflict {
+                OnConflict::Skip => key.clone(),
+                OnConflict::Overwrite => {
+                    self.procrastinations.insert(key.clone(), procrastination);
+                    key.clone()
+                }
+                OnConflict::Rename => {
+                    let new_key = (2..)
+                        .map(|i| format!("{key}-{i}"))
+                        .find(|new_key| !self.procrastinations.contains_key(new_key))
+                        .expect("there is always a free key");
+                    self.procrastinations
+                        .insert(new_key.clone(), procrastination);
+                    new_key
+                }
+            };
+            conflicts.push((key, new_key));
+        }
+        conflicts
+    }
+
     pub fn get(&self, k: &str) -> Option<&Procrastination> {
-        self.0.get(k)
+        self.procrastinations.get(k)
+    }
+
+    /// The ids of the last notifications shown, see [Procrastination::notification_id].
+    ///
+    /// The ids are not stored in the file, use [Self::restore_notification_ids]
+    /// to keep replacing notifications after the file is read again.
+    pub fn notification_ids(&self) -> HashMap<String, u32> {
+        self.procrastinations
+            .iter()
+            .filter_map(|(key, procrastination)| {
+                Some((key.clone(), procrastination.notification_id?))
+            })
+            .collect()
+    }
+
+    /// Sets the notification ids of all procrastinations in `ids`, see [Self::notification_ids]
+    pub fn restore_notification_ids(&mut self, ids: &HashMap<String, u32>) {
+        for (key, id) in ids {
+            if let Some(procrastination) = self.procrastinations.get_mut(key) {
+                procrastination.notification_id = Some(*id);
+            }
+        }
     }
 
     pub fn get_mut(&mut self, k: &str) -> Option<&mut Procrastination> {
-        self.0.get_mut(k)
+        self.procrastinations.get_mut(k)
     }
 
     pub fn insert(&mut self, k: String, v: Procrastination) -> Option<Procrastination> {
-        self.0.insert(k, v)
+        self.procrastinations.insert(k, v)
     }
 
     pub fn remove(&mut self, key: &str) -> Option<Procrastination> {
-        self.0.remove(key)
+        self.procrastinations.remove(key)
     }
 
     pub fn iter(&self) -> impl Iterator<Item = (&String, &Procrastination)> {
-        self.0.iter()
+        self.procrastinations.iter()
     }
 
     pub fn iter_mut(&mut self) -> impl Iterator<Item = (&String, &mut Procrastination)> {
-        self.0.iter_mut()
+        self.procrastinations.iter_mut()
+    }
+
+    /// All procrastinations whose next notification can't be computed, ordered by key.
+    pub fn validate(&self, calendar: &Calendar) -> Vec<(&String, TimeError)> {
+        let mut invalid: Vec<_> = self
+            .procrastinations
+            .iter()
+            .filter_map(|(key, procrastination)| {
+                procrastination
+                    .next_notification(calendar)
+                    .err()
+                    .map(|e| (key, e))
+            })
+            .collect();
+        invalid.sort_by(|a, b| a.0.cmp(b.0));
+        invalid
+    }
+
+    /// All procrastinations ordered by their next notification.
+    ///
+    /// Procrastinations with the same next notification are ordered by key.
+    pub fn iter_sorted_by_next(
+        &self,
+        calendar: &Calendar,
+    ) -> Result<Vec<(&String, &Procrastination)>, TimeError> {
+        let mut entries = Vec::with_capacity(self.procrastinations.len());
+        for (key, procrastination) in self.procrastinations.iter() {
+            let (_, next) = procrastination.next_notification(calendar)?;
+            entries.push((next, key, procrastination));
+        }
+        entries.sort_by(|a, b| a.0.cmp(&b.0).then(a.1.cmp(b.1)));
+        Ok(entries
+            .into_iter()
+            .map(|(_, key, procrastination)| (key, procrastination))
+            .collect())
+    }
+
+    /// All notifications from `now` until the end of the `days`th day, grouped by day.
+    ///
+    /// The result contains an entry for every day, even if there are no notifications on it.
+    pub fn agenda(
+        &self,
+        now: NaiveDateTime,
+        days: u32,
+        calendar: &Calendar,
+    ) -> Result<Vec<AgendaDay<'_>>, TimeError> {
+        let today = now.date();
+        let mut agenda: Vec<AgendaDay> = (0..days)
+            .map(|day| AgendaDay {
+                date: today + TimeDelta::days(day.into()),
+                entries: Vec::new(),
+                locale: calendar.locale,
+            })
+            .collect();
+
+        let Some(last_day) = agenda.last() else {
+            return Ok(agenda);
+        };
+        let end = last_day.date.and_hms_opt(23, 59, 59).unwrap();
+
+        for (key, procrastination) in self.iter() {
+            for occurrence in procrastination.occurrences_between(now, end, calendar)? {
+                let day = (occurrence.date() - today).num_days() as usize;
+                agenda[day].entries.push((occurrence, key, procrastination));
+            }
+        }
+        for day in agenda.iter_mut() {
+            day.entries.sort_by(|a, b| a.0.cmp(&b.0).then(a.1.cmp(b.1)));
+        }
+
+        Ok(agenda)
+    }
+}
+
+/// The notifications of a single day, see [ProcrastinationFileData::agenda]
+#[derive(Debug)]
+pub struct AgendaDay<'a> {
+    pub date: NaiveDate,
+    /// notification time, key and procrastination sorted by time
+    pub entries: Vec<(NaiveDateTime, &'a str, &'a Procrastination)>,
+    /// The language of the weekday name
+    pub locale: Locale,
+}
+
+impl std::fmt::Display for AgendaDay<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let us_dates = f.sign_minus();
+
+        let weekday = self
+            .locale
+            .weekday(self.date.weekday().num_days_from_monday() as u8);
+        let mut chars = weekday.chars();
+        if let Some(first) = chars.next() {
+            f.write_fmt(format_args!("{}{}", first.to_uppercase(), chars.as_str()))?;
+        }
+        f.write_fmt(format_args!(" {}", format_timestamp(self.date, us_dates)))?;
+        for (time, key, procrastination) in self.entries.iter() {
+            f.write_str("\n    ")?;
+            format_time(time.time(), f)?;
+            f.write_fmt(format_args!(" {key}: {}", procrastination.title))?;
+        }
+        Ok(())
     }
 }
 
@@ -89,27 +522,354 @@ pub struct Procrastination {
     pub sticky: bool,
     #[serde(default)]
     pub sleep: Option<Sleep>,
+    /// only show the title, even if there is a message
+    #[serde(default)]
+    pub no_body: bool,
+    /// render the message as markdown, if the notification server supports it
+    #[serde(default)]
+    pub markdown: bool,
+    /// offset in seconds added to every notification, see [jitter_offset]
+    #[serde(default)]
+    pub offset: i64,
+    /// how often a notification was shown for this procrastination
+    #[serde(default)]
+    pub fire_count: u32,
+    #[serde(default)]
+    pub priority: Priority,
+    /// never notify before this time, even if the timing would
+    #[serde(default)]
+    pub start_after: Option<NaiveDateTime>,
+    /// keep [Repeat::Once] procrastinations after they fired, instead of deleting them
+    #[serde(default)]
+    pub keep: bool,
+    /// set once a kept [Repeat::Once] procrastination fired. It will not notify again.
+    #[serde(default)]
+    pub fired: bool,
+    /// notify [Repeat::Once] procrastinations on every check until they are removed with `done`
+    #[serde(default)]
+    pub require_ack: bool,
+    /// when the last notification was shown. No notification is shown again
+    /// within [DEDUP_WINDOW_SECS], e.g. if two checks overlap.
+    #[serde(default)]
+    pub last_shown: Option<DateTime<Local>>,
+    /// icon of the notification, defaults to the icon configured for the priority
+    #[serde(default)]
+    pub icon: Option<String>,
+    /// id of the last notification shown for this procrastination, replaced by the next one
+    #[serde(skip)]
+    pub notification_id: Option<u32>,
+    /// how a long title is shown, defaults to [FileDefaults::summary_overflow]
+    #[serde(default)]
+    pub summary_overflow: Option<SummaryOverflow>,
+    /// dates on which a [Repeat::Repeat] procrastination does not notify, e.g. holidays
+    #[serde(default)]
+    pub skip_dates: Vec<NaiveDate>,
+    /// paused procrastinations don't notify until they are resumed, see [Procrastination::notifies]
+    #[serde(default)]
+    pub paused: bool,
+}
+
+/// A partial update of a [Procrastination], see [Procrastination::merge_from].
+///
+/// Only fields that are `Some` are applied. Fields that are optional on
+/// [Procrastination] itself are cleared with `Some(None)`.
+#[derive(Debug, Default)]
+pub struct ProcrastinationPatch {
+    pub title: Option<String>,
+    pub message: Option<String>,
+    pub timing: Option<Repeat>,
+    pub timestamp: Option<DateTime<Local>>,
+    pub sticky: Option<bool>,
+    pub sleep: Option<Option<Sleep>>,
+    pub no_body: Option<bool>,
+    pub markdown: Option<bool>,
+    pub offset: Option<i64>,
+    pub priority: Option<Priority>,
+    pub start_after: Option<Option<NaiveDateTime>>,
+    pub keep: Option<bool>,
+    pub require_ack: Option<bool>,
+    pub icon: Option<Option<String>>,
+    pub summary_overflow: Option<Option<SummaryOverflow>>,
+    pub skip_dates: Option<Vec<NaiveDate>>,
+}
+
+/// Seconds after a notification during which the same procrastination is not shown again
+pub const DEDUP_WINDOW_SECS: i64 = 30;
+
+/// How to handle procrastinations with the same key when merging files
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum OnConflict {
+    /// keep the existing procrastination
+    Skip,
+    /// replace the existing procrastination
+    Overwrite,
+    /// insert the procrastination with a numeric suffix, e.g. `key-2`
+    Rename,
+}
+
+/// The urgency of a notification
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+pub enum Priority {
+    Low,
+    #[default]
+    Normal,
+    /// shown even if do not disturb is enabled
+    Critical,
+}
+
+impl Priority {
+    fn urgency(self) -> notify_rust::Urgency {
+        match self {
+            Priority::Low => notify_rust::Urgency::Low,
+            Priority::Normal => notify_rust::Urgency::Normal,
+            Priority::Critical => notify_rust::Urgency::Critical,
+        }
+    }
+}
+
+/// How titles longer than `max` characters are shown in the notification.
+///
+/// Some notification servers cut long summaries off awkwardly.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SummaryOverflow {
+    /// the whole title is the summary
+    #[default]
+    Keep,
+    /// the summary is cut with an ellipsis, the body starts with the full title
+    Truncate { max: usize },
+    /// the summary ends at the last word that fits, the body starts with the rest of the title
+    MoveToBody { max: usize },
+}
+
+impl SummaryOverflow {
+    /// The summary for `title` and the text the body starts with, if any
+    fn split(self, title: &str) -> (String, Option<String>) {
+        let max = match self {
+            SummaryOverflow::Keep => return (title.to_string(), None),
+            SummaryOverflow::Truncate { max } | SummaryOverflow::MoveToBody { max } => max,
+        };
+        let Some((cut, _)) = title.char_indices().nth(max) else {
+            return (title.to_string(), None);
+        };
+        // leave room for the ellipsis
+        let cut = title[..cut]
+            .char_indices()
+            .last()
+            .map_or(0, |(last, _)| last);
+
+        match self {
+            SummaryOverflow::Keep => unreachable!("returned above"),
+            SummaryOverflow::Truncate { .. } => (
+                format!("{}…", title[..cut].trim_end()),
+                Some(title.to_string()),
+            ),
+            SummaryOverflow::MoveToBody { .. } => {
+                let cut = match title[..cut].rfind(char::is_whitespace) {
+                    Some(space) if space > 0 => space,
+                    _ => cut,
+                };
+                (
+                    format!("{}…", title[..cut].trim_end()),
+                    Some(format!("…{}", title[cut..].trim_start())),
+                )
+            }
+        }
+    }
 }
 
 impl Procrastination {
     pub fn new(title: String, message: String, timing: Repeat, sticky: bool) -> Self {
+        Self::new_at(title, message, timing, sticky, Local::now())
+    }
+
+    /// Creates a procrastination as if it was created at `timestamp`
+    pub fn new_at(
+        title: String,
+        message: String,
+        timing: Repeat,
+        sticky: bool,
+        timestamp: DateTime<Local>,
+    ) -> Self {
         Procrastination {
             title,
             message,
-            timing,
-            timestamp: Local::now(),
+            timing: timing.with_creation_time(timestamp.naive_local()),
+            timestamp,
             dirty: Default::default(),
             sticky,
             sleep: None,
+            no_body: false,
+            markdown: false,
+            offset: 0,
+            fire_count: 0,
+            priority: Priority::Normal,
+            start_after: None,
+            keep: false,
+            fired: false,
+            require_ack: false,
+            last_shown: None,
+            icon: None,
+            notification_id: None,
+            summary_overflow: None,
+            skip_dates: Vec::new(),
+            paused: false,
+        }
+    }
+
+    /// applies all fields of `patch` that are set, leaving everything else untouched
+    pub fn merge_from(&mut self, patch: ProcrastinationPatch) {
+        let ProcrastinationPatch {
+            title,
+            message,
+            timing,
+            timestamp,
+            sticky,
+            sleep,
+            no_body,
+            markdown,
+            offset,
+            priority,
+            start_after,
+            keep,
+            require_ack,
+            icon,
+            summary_overflow,
+            skip_dates,
+        } = patch;
+        fn apply<T>(field: &mut T, value: Option<T>) {
+            if let Some(value) = value {
+                *field = value;
+            }
+        }
+        apply(&mut self.title, title);
+        apply(&mut self.message, message);
+        // a new `daily` timing repeats at the time of day it was set
+        let now = Local::now().naive_local();
+        apply(
+            &mut self.timing,
+            timing.map(|timing| timing.with_creation_time(now)),
+        );
+        apply(&mut self.timestamp, timestamp);
+        apply(&mut self.sticky, sticky);
+        apply(&mut self.sleep, sleep);
+        apply(&mut self.no_body, no_body);
+        apply(&mut self.markdown, markdown);
+        apply(&mut self.offset, offset);
+        apply(&mut self.priority, priority);
+        apply(&mut self.start_after, start_after);
+        apply(&mut self.keep, keep);
+        apply(&mut self.require_ack, require_ack);
+        apply(&mut self.icon, icon);
+        apply(&mut self.summary_overflow, summary_overflow);
+        apply(&mut self.skip_dates, skip_dates);
+    }
+
+    /// Display this procrastination with ANSI colors if `color` is set
+    pub fn colored<'a>(
+        &'a self,
+        color: bool,
+        calendar: &'a Calendar,
+    ) -> ColoredProcrastination<'a> {
+        ColoredProcrastination {
+            procrastination: self,
+            calendar,
+            color,
+            relative: false,
+        }
+    }
+
+    /// returns `true` if the next notification is already in the past
+    pub fn is_overdue(&self, calendar: &Calendar) -> bool {
+        self.notifies()
+            && matches!(self.next_notification(calendar), Ok((_, next)) if local_datetime(next, &Local) <= Local::now())
+    }
+
+    /// returns `true` if this is a [Repeat::Once] procrastination whose
+    /// notification is more than `max_age` overdue
+    pub fn is_stale(
+        &self,
+        now: NaiveDateTime,
+        max_age: Delay,
+        calendar: &Calendar,
+    ) -> Result<bool, TimeError> {
+        if !self.notifies() || self.require_ack || !matches!(self.timing, Repeat::Once { .. }) {
+            return Ok(false);
         }
+        let (_, next) = self.next_notification(calendar)?;
+        Ok(apply_delay(next, max_age) < now)
+    }
+
+    /// notify again once `delay` has passed since `now`, see [Sleep]
+    pub fn snooze(&mut self, now: NaiveDateTime, delay: Delay) {
+        let date = apply_delay(now, delay);
+        self.sleep = Some(Sleep {
+            timing: OnceTiming::Instant(RoughInstant::Date { date }),
+        });
     }
 
     pub fn can_notify_in_future(&self) -> bool {
-        self.dirty != Dirt::Delete
+        self.dirty != Dirt::Delete && self.notifies()
+    }
+
+    /// returns `false` if this procrastination will not notify again by itself,
+    /// because it is paused or a kept [Repeat::Once] procrastination that already fired
+    pub fn notifies(&self) -> bool {
+        !self.fired && !self.paused
+    }
+
+    /// returns `true` if the next notification is between `now` and `now + delay`
+    pub fn is_due_within(
+        &self,
+        now: NaiveDateTime,
+        delay: Delay,
+        calendar: &Calendar,
+    ) -> Result<bool, TimeError> {
+        if !self.notifies() {
+            return Ok(false);
+        }
+        let (_, next) = self.next_notification(calendar)?;
+        Ok(now <= next && next <= apply_delay(now, delay))
+    }
+
+    /// The time until the next notification, negative if it is overdue.
+    pub fn time_until_next(&self, calendar: &Calendar) -> Result<TimeDelta, TimeError> {
+        self.time_until_next_at(Local::now().naive_local(), calendar)
+    }
+
+    /// The time from `now` until the next notification, negative if it is overdue.
+    pub fn time_until_next_at(
+        &self,
+        now: NaiveDateTime,
+        calendar: &Calendar,
+    ) -> Result<TimeDelta, TimeError> {
+        let (_, next) = self.next_notification(calendar)?;
+        Ok(next - now)
+    }
+}
+
+pub(crate) const ANSI_RED: &str = "\x1b[31m";
+pub(crate) const ANSI_GREY: &str = "\x1b[90m";
+pub(crate) const ANSI_RESET: &str = "\x1b[0m";
+
+/// Displays a [Procrastination], optionally highlighting it with ANSI colors.
+///
+/// Overdue procrastinations are red and sleeping procrastinations are grey.
+pub struct ColoredProcrastination<'a> {
+    procrastination: &'a Procrastination,
+    calendar: &'a Calendar,
+    color: bool,
+    relative: bool,
+}
+
+impl ColoredProcrastination<'_> {
+    /// Display the next notification relative to now, e.g. `in 2h`
+    pub fn relative(mut self, relative: bool) -> Self {
+        self.relative = relative;
+        self
     }
 }
 
-impl std::fmt::Display for Procrastination {
+impl std::fmt::Display for ColoredProcrastination<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let write_nl = |f: &mut std::fmt::Formatter<'_>| {
             if f.alternate() {
@@ -119,87 +879,116 @@ impl std::fmt::Display for Procrastination {
             }
         };
 
+        let proc = self.procrastination;
         let us_dates = f.sign_minus();
 
-        f.write_str(&self.title)?;
+        let color = match self.color {
+            true if proc.is_overdue(self.calendar) => Some(ANSI_RED),
+            true if proc.sleep.is_some() => Some(ANSI_GREY),
+            _ => None,
+        };
+        if let Some(color) = color {
+            f.write_str(color)?;
+        }
+        f.write_str(&proc.title)?;
+        if color.is_some() {
+            f.write_str(ANSI_RESET)?;
+        }
 
-        if !self.message.is_empty() {
+        if !proc.message.is_empty() {
             write_nl(f)?;
             write_nl(f)?;
-            f.write_str(&self.message)?;
+            f.write_str(&proc.message)?;
             write_nl(f)?;
         }
 
-        let last_message = match self.timing {
-            Repeat::Once { .. } => "created at",
-            Repeat::Repeat { .. } => "last notification",
+        let last_message = match proc.timing {
+            Repeat::Repeat { .. } if proc.fire_count > 0 => "last notification",
+            _ => "created at",
         };
         write_nl(f)?;
         f.write_fmt(format_args!(
             "{last_message}: {}",
-            format_timestamp(self.timestamp.naive_local(), us_dates)
+            format_timestamp(proc.timestamp.naive_local(), us_dates)
         ))?;
         write_nl(f)?;
-        match self.next_notification() {
+        match proc.next_notification(self.calendar) {
             Ok((_, next)) => {
                 f.write_str("next notification: ")?;
-                format_upcoming_timestamp(next, us_dates, f)?;
+                if self.relative {
+                    f.write_str(&time::format_relative(next - Local::now().naive_local()))?;
+                } else {
+                    format_upcoming_timestamp(
+                        next,
+                        Local::now().naive_local(),
+                        us_dates,
+                        proc.notifies_by_date(),
+                        f,
+                    )?;
+                }
             }
             Err(e) => {
-                eprintln!("failed to get next notification time: {e:?}");
+                log::error!("failed to get next notification time: {e:?}");
             }
         }
 
         write_nl(f)?;
         f.write_str("flags: ")?;
-        let repeat_flag = match self.timing {
-            Repeat::Once { .. } => "once",
-            Repeat::Repeat { .. } => "repeating",
-        };
-        f.write_str(repeat_flag)?;
-        if self.sticky {
-            f.write_str(", sticky")?;
-        }
-        if self.sleep.is_some() {
-            f.write_str(", sleeping")?;
-        }
+        f.write_str(&proc.flags().join(", "))?;
 
         Ok(())
     }
 }
 
+/// Displays a timestamp using [format_upcoming_timestamp]
+struct UpcomingTimestamp(NaiveDateTime, bool, bool);
+
+impl std::fmt::Display for UpcomingTimestamp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        format_upcoming_timestamp(self.0, Local::now().naive_local(), self.1, self.2, f)
+    }
+}
+
+/// Formats a notification time relative to the day of `now`.
+///
+/// If `date_only` is set the time of day is omitted, e.g. for delays created
+/// in days or weeks. Otherwise notifications fire at an exact time, so fires at
+/// midnight always show `0:00` instead of looking like an all-day date.
 fn format_upcoming_timestamp(
     timestamp: NaiveDateTime,
+    now: NaiveDateTime,
     us_date: bool,
+    date_only: bool,
     f: &mut std::fmt::Formatter<'_>,
 ) -> std::fmt::Result {
-    let now = Local::now().naive_local();
-
     if timestamp <= now {
         return f.write_str("now");
     }
 
-    let display_time = timestamp.second() != 0 || timestamp.minute() != 0 || timestamp.hour() != 0;
-    let today = Local::now().date_naive();
+    let today = now.date();
     let tomorrow = today + TimeDelta::days(1);
 
+    if date_only {
+        return match timestamp.date() {
+            date if date == today => f.write_str("today"),
+            date if date == tomorrow => f.write_str("tomorrow"),
+            date => f.write_fmt(format_args!("{}", format_timestamp(date, us_date))),
+        };
+    }
+
     if timestamp.date() == today {
-        if display_time {
-            return format_time(timestamp.time(), f);
-        } else {
-            return f.write_str("today");
-        }
+        return format_time(timestamp.time(), f);
     }
     if timestamp.date() == tomorrow {
-        f.write_str("tomorrow")?;
-        if display_time {
-            f.write_str(" at ")?;
-            format_time(timestamp.time(), f)?;
-        }
-        return Ok(());
+        f.write_str("tomorrow at ")?;
+        return format_time(timestamp.time(), f);
     }
 
-    f.write_fmt(format_args!("{}", format_timestamp(timestamp, us_date)))
+    f.write_fmt(format_args!("{}", format_timestamp(timestamp, us_date)))?;
+    if timestamp.time() == NaiveTime::MIN {
+        f.write_str(" 0:00")?;
+    }
+    Ok(())
 }
 
 fn format_time(time: NaiveTime, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -246,6 +1035,38 @@ pub struct Sleep {
     pub timing: OnceTiming,
 }
 
+impl Sleep {
+    /// Creates a new sleep starting at `now`.
+    ///
+    /// Instants are resolved to the next matching date after `now`, so that
+    /// e.g. "monday" never refers to a monday in the past.
+    pub fn new(
+        timing: OnceTiming,
+        now: NaiveDateTime,
+        calendar: &Calendar,
+    ) -> Result<Self, TimeError> {
+        let timing = match timing {
+            OnceTiming::Instant(instant) => OnceTiming::Instant(RoughInstant::Date {
+                date: instant.next_after(now, calendar)?,
+            }),
+            delay @ OnceTiming::Delay(_) => delay,
+        };
+        Ok(Self { timing })
+    }
+}
+
+/// The first time `timing` refers to after `now`.
+pub fn resolve_once_timing(
+    timing: &OnceTiming,
+    now: NaiveDateTime,
+    calendar: &Calendar,
+) -> Result<NaiveDateTime, TimeError> {
+    match timing {
+        OnceTiming::Instant(instant) => instant.next_after(now, calendar),
+        OnceTiming::Delay(delay) => Ok(apply_delay(now, *delay)),
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, Default)]
 enum Dirt {
     #[default]
@@ -258,75 +1079,523 @@ enum Dirt {
 pub enum NotificationError {
     #[error("Could not deliver notification")]
     Notification(#[from] notify_rust::error::Error),
+    #[error("Could not deliver notification, the notification server is not running")]
+    ServerUnavailable(notify_rust::error::Error),
     #[error("invalid timing information for notification")]
     InvalidTiming(#[from] TimeError),
 }
 
-#[derive(Debug, PartialEq, Eq)]
-pub enum NotificationType {
-    Normal,
-    Sleep,
-    None,
+impl NotificationError {
+    /// If notifying again later might succeed, see [NotificationSink::server_running]
+    pub fn is_transient(&self) -> bool {
+        matches!(self, NotificationError::ServerUnavailable(_))
+    }
 }
 
-impl NotificationType {
-    pub fn changed(&self) -> bool {
-        match self {
-            Self::Normal | Self::Sleep => true,
-            Self::None => false,
+/// The result of [ProcrastinationFileData::notify_all_with], ordered by key
+#[derive(Debug, Default)]
+pub struct NotifyReport {
+    /// procrastinations that were notified
+    pub fired: Vec<String>,
+    /// procrastinations that were not due
+    pub skipped: Vec<String>,
+    /// procrastinations that could not be notified
+    pub failed: Vec<(String, NotificationError)>,
+}
+
+impl NotifyReport {
+    /// Adds the result of [Procrastination::notify] for `key`.
+    pub fn push(&mut self, key: String, result: Result<NotificationType, NotificationError>) {
+        match result {
+            Ok(NotificationType::None) => self.skipped.push(key),
+            Ok(_) => self.fired.push(key),
+            Err(e) => {
+                log::error!("Failed to notify \"{key}\": {e}");
+                self.failed.push((key, e));
+            }
         }
     }
 }
 
-impl Procrastination {
-    pub fn notify(&mut self) -> Result<NotificationType, NotificationError> {
-        let not_type = self.should_notify()?;
-        if not_type == NotificationType::None {
-            return Ok(not_type);
+/// The errors of all procrastinations that could not be notified, by key
+#[derive(Debug, Error)]
+#[error("failed to notify {}", self.keys())]
+pub struct NotificationErrors(pub Vec<(String, NotificationError)>);
+
+impl NotificationErrors {
+    /// `Ok` if there are no errors
+    pub fn from_errors(errors: Vec<(String, NotificationError)>) -> Result<(), Self> {
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(Self(errors))
         }
+    }
 
-        log::info!("Notification:\n{}\n\n{}", self.title, self.message);
-        let mut notification = Notification::new();
-        notification.summary(&self.title).body(&self.message);
-
-        if self.sticky {
-            notification.hint(notify_rust::Hint::Resident(true));
-            notification.timeout(0);
-        }
+    fn keys(&self) -> String {
+        self.0
+            .iter()
+            .map(|(key, e)| format!("\"{key}\" ({e})"))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
 
-        notification.show()?;
+/// Displays notifications
+pub trait NotificationSink {
+    fn show(&mut self, notification: &Notification) -> Result<(), notify_rust::error::Error>;
+
+    /// Shows `notification` in place of the notification with id `replaces`.
+    ///
+    /// Returns the id of the shown notification, if the sink supports replacing notifications.
+    fn show_replacing(
+        &mut self,
+        notification: &Notification,
+        replaces: Option<u32>,
+    ) -> Result<Option<u32>, notify_rust::error::Error> {
+        let _ = replaces;
+        self.show(notification).map(|_| None)
+    }
 
-        self.sleep = None;
+    /// The features of the notification server. Hints it does not support are not sent.
+    fn capabilities(&self) -> ServerCapabilities {
+        ServerCapabilities::ALL
+    }
 
-        self.dirty = match &self.timing {
-            Repeat::Once { timing: _ } => Dirt::Delete,
-            Repeat::Repeat { timing: _ } => {
-                self.timestamp = Local::now();
-                Dirt::Update
-            }
-        };
-        Ok(not_type)
+    /// If the notification server is running, asked after a notification failed.
+    ///
+    /// Notifications that fail while it is not are [transient](NotificationError::is_transient).
+    fn server_running(&self) -> bool {
+        true
     }
+}
 
-    pub fn should_notify(&self) -> Result<NotificationType, TimeError> {
-        let last_timestamp = self.timestamp.naive_local();
-        let (typ, next_notification) = self.next_notification()?;
-        if next_notification > last_timestamp && Local::now().naive_local() > next_notification {
-            Ok(typ)
-        } else {
-            Ok(NotificationType::None)
+/// Features of a notification server that change how a notification is built
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ServerCapabilities {
+    /// html in the body, `body-markup`
+    pub body_markup: bool,
+    /// notifications that stay until they are dismissed, required for the resident hint
+    pub persistence: bool,
+}
+
+impl ServerCapabilities {
+    pub const ALL: ServerCapabilities = ServerCapabilities {
+        body_markup: true,
+        persistence: true,
+    };
+    pub const NONE: ServerCapabilities = ServerCapabilities {
+        body_markup: false,
+        persistence: false,
+    };
+
+    /// The capabilities from the names reported by the notification server
+    pub fn from_names(names: &[String]) -> Self {
+        let has = |name: &str| names.iter().any(|n| n == name);
+        ServerCapabilities {
+            body_markup: has("body-markup"),
+            persistence: has("persistence"),
         }
     }
+}
 
-    pub fn next_notification(&self) -> Result<(NotificationType, NaiveDateTime), TimeError> {
-        let last_timestamp = self.timestamp.naive_local();
-        let next_notification = match &self.timing {
-            Repeat::Once { timing } => next_once_timing(timing, last_timestamp)?,
-            Repeat::Repeat { timing } => next_repeat_timing(timing, last_timestamp)?,
+/// The capabilities of the desktop notification server, queried once per process
+static DESKTOP_CAPABILITIES: OnceLock<ServerCapabilities> = OnceLock::new();
+
+/// The capabilities in `cache`, or the result of `query` if it is empty.
+///
+/// Only successful queries are cached, so a server that starts later is queried again.
+/// Until then all capabilities are assumed, which is how notifications were built
+/// before capabilities were queried.
+fn cached_capabilities<E: std::fmt::Display>(
+    cache: &OnceLock<ServerCapabilities>,
+    query: impl FnOnce() -> Result<Vec<String>, E>,
+) -> ServerCapabilities {
+    if let Some(capabilities) = cache.get() {
+        return *capabilities;
+    }
+    match query() {
+        Ok(names) => *cache.get_or_init(|| ServerCapabilities::from_names(&names)),
+        Err(e) => {
+            log::warn!("failed to query notification server capabilities: {e}");
+            ServerCapabilities::ALL
+        }
+    }
+}
+
+/// Shows notifications on the desktop
+#[derive(Debug, Clone, Copy)]
+pub struct DesktopNotifications;
+
+impl NotificationSink for DesktopNotifications {
+    fn show(&mut self, notification: &Notification) -> Result<(), notify_rust::error::Error> {
+        notification.show().map(|_| ())
+    }
+
+    fn show_replacing(
+        &mut self,
+        notification: &Notification,
+        replaces: Option<u32>,
+    ) -> Result<Option<u32>, notify_rust::error::Error> {
+        let notification = match replaces {
+            Some(id) => notification.clone().id(id).finalize(),
+            None => notification.clone(),
         };
+        notification.show().map(|handle| Some(handle.id()))
+    }
+
+    fn capabilities(&self) -> ServerCapabilities {
+        cached_capabilities(&DESKTOP_CAPABILITIES, notify_rust::get_capabilities)
+    }
+
+    /// notify_rust does not expose the kind of its errors, so this asks the session bus
+    /// if the notification service has an owner.
+    fn server_running(&self) -> bool {
+        let running = zbus::blocking::Connection::session().and_then(|connection| {
+            let name = zbus::names::BusName::from_static_str(NOTIFICATION_SERVICE)?;
+            Ok(zbus::blocking::fdo::DBusProxy::new(&connection)?.name_has_owner(name)?)
+        });
+        match running {
+            Ok(running) => running,
+            // the session bus itself is not reachable, e.g. while the session starts
+            Err(zbus::Error::InputOutput(_) | zbus::Error::Handshake(_)) => false,
+            Err(e) => {
+                log::warn!("failed to check if the notification server is running: {e}");
+                true
+            }
+        }
+    }
+}
+
+/// The D-Bus name of the desktop notification server
+const NOTIFICATION_SERVICE: &str = "org.freedesktop.Notifications";
+
+/// Records notifications instead of showing them
+#[derive(Debug, Default, Clone)]
+pub struct RecordingSink(pub Vec<Notification>);
+
+impl NotificationSink for RecordingSink {
+    fn show(&mut self, notification: &Notification) -> Result<(), notify_rust::error::Error> {
+        self.0.push(notification.clone());
+        Ok(())
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum NotificationType {
+    Normal,
+    Sleep,
+    None,
+}
+
+impl NotificationType {
+    pub fn changed(&self) -> bool {
+        match self {
+            Self::Normal | Self::Sleep => true,
+            Self::None => false,
+        }
+    }
+}
+
+impl Procrastination {
+    pub fn notify(
+        &mut self,
+        defaults: &FileDefaults,
+        settings: &NotifySettings,
+    ) -> Result<NotificationType, NotificationError> {
+        self.notify_with(defaults, settings, &mut DesktopNotifications)
+    }
+
+    pub fn notify_with(
+        &mut self,
+        defaults: &FileDefaults,
+        settings: &NotifySettings,
+        sink: &mut impl NotificationSink,
+    ) -> Result<NotificationType, NotificationError> {
+        let not_type = self.should_notify(&settings.calendar)?;
+        if not_type == NotificationType::None {
+            return Ok(not_type);
+        }
+        if defaults
+            .quiet_hours
+            .is_some_and(|quiet| quiet.defers(self.priority, Local::now().naive_local()))
+        {
+            log::info!("quiet hours, deferring notification \"{}\"", self.title);
+            return Ok(NotificationType::None);
+        }
+
+        log::info!("Notification:\n{}\n\n{}", self.title, self.message);
+        let capabilities = sink.capabilities();
+        let mut notification = Notification::new();
+        let (summary, overflow) = self
+            .summary_overflow
+            .unwrap_or(defaults.summary_overflow)
+            .split(&self.title);
+        notification.summary(&summary);
+        let body = match (overflow, self.notification_body()) {
+            (Some(overflow), Some(body)) => Some(format!("{overflow}\n\n{body}")),
+            (overflow, body) => overflow.or(body.map(str::to_string)),
+        };
+        if let Some(body) = body {
+            if self.markdown && capabilities.body_markup {
+                notification.body(&markdown::markdown_to_html(&body));
+            } else {
+                notification.body(&body);
+            }
+        }
+
+        let resident = capabilities.persistence;
+        if self.sticky {
+            if resident {
+                notification.hint(notify_rust::Hint::Resident(true));
+            }
+            notification.timeout(0);
+        } else if let Some(timeout_ms) = defaults.timeout_ms {
+            notification.timeout(notify_rust::Timeout::Milliseconds(timeout_ms));
+        }
+
+        notification.hint(notify_rust::Hint::Urgency(self.priority.urgency()));
+        if let Some(icon) = self
+            .icon
+            .as_deref()
+            .or_else(|| settings.urgency_icons.icon(self.priority))
+        {
+            notification.icon(icon);
+        }
+        if self.priority == Priority::Critical && resident {
+            // most notification servers only show resident notifications while do not disturb is enabled
+            notification.hint(notify_rust::Hint::Resident(true));
+        }
+
+        let shown = match sink.show_replacing(&notification, self.notification_id) {
+            Ok(shown) => shown,
+            Err(e) if !sink.server_running() => {
+                return Err(NotificationError::ServerUnavailable(e))
+            }
+            Err(e) => return Err(e.into()),
+        };
+        if let Some(id) = shown {
+            self.notification_id = Some(id);
+        }
+        self.mark_notified();
+
+        Ok(not_type)
+    }
+
+    /// Updates the procrastination after a notification was shown.
+    fn mark_notified(&mut self) {
+        self.sleep = None;
+        self.fire_count += 1;
+        self.last_shown = Some(Local::now());
+
+        self.dirty = match &self.timing {
+            Repeat::Once { timing: _ } if self.require_ack => Dirt::Update,
+            Repeat::Once { timing: _ } if self.keep => {
+                self.fired = true;
+                Dirt::Update
+            }
+            Repeat::Once { timing: _ } => Dirt::Delete,
+            Repeat::Repeat { timing: _ } => {
+                self.timestamp = Local::now();
+                Dirt::Update
+            }
+        };
+    }
+
+    /// Describes the schedule of this procrastination, e.g. "every monday at 9:00" or "once, in 5min"
+    pub fn describe_timing(&self) -> String {
+        match &self.timing {
+            Repeat::Once { timing } => format!("once, {timing}"),
+            Repeat::Repeat { timing } => timing.to_string(),
+        }
+    }
+
+    /// Whether the timing is a delay given in whole days, weeks, months or years,
+    /// in which case the time of day of the next notification is not shown
+    pub fn notifies_by_date(&self) -> bool {
+        let delay = match &self.timing {
+            Repeat::Once {
+                timing: OnceTiming::Delay(delay),
+            }
+            | Repeat::Repeat {
+                timing: RepeatTiming::Delay(delay),
+            } => delay,
+            _ => return false,
+        };
+        delay.notifies_at_midnight()
+    }
+
+    /// Describes when this procrastination will notify,
+    /// e.g. "Will first notify: tomorrow at 9:00; repeats daily at 9:00"
+    pub fn explain(&self, us_date: bool, calendar: &Calendar) -> Result<String, TimeError> {
+        let (_, mut next) = self.next_notification(calendar)?;
+        if let Repeat::Repeat { timing } = &self.timing {
+            let last_timestamp = self.timestamp.naive_local();
+            if next <= last_timestamp {
+                // exact timings can refer to a time earlier today, which is skipped
+                next = repeat_timing_after(timing, last_timestamp, calendar)?;
+            }
+        }
+        let next = UpcomingTimestamp(next, us_date, self.notifies_by_date());
+        Ok(match &self.timing {
+            Repeat::Once { .. } => format!("Will notify: {next}"),
+            Repeat::Repeat { timing } => format!(
+                "Will first notify: {next}; repeats {}",
+                timing.localized(calendar.locale)
+            ),
+        })
+    }
+
+    /// Short descriptions of the settings of this procrastination, e.g. "sticky"
+    pub fn flags(&self) -> Vec<&'static str> {
+        let mut flags = vec![match self.timing {
+            Repeat::Once { .. } => "once",
+            Repeat::Repeat { .. } => "repeating",
+        }];
+        if self.fired {
+            flags.push("done");
+        }
+        if self.paused {
+            flags.push("paused");
+        }
+        if self.require_ack {
+            flags.push("requires ack");
+        }
+        if self.sticky {
+            flags.push("sticky");
+        }
+        if self.sleep.is_some() {
+            flags.push("sleeping");
+        }
+        if self.no_body {
+            flags.push("no body");
+        }
+        if self.markdown {
+            flags.push("markdown");
+        }
+        match self.priority {
+            Priority::Low => flags.push("low priority"),
+            Priority::Normal => {}
+            Priority::Critical => flags.push("critical"),
+        }
+        flags
+    }
+
+    /// the body of the notification, `None` if only the summary should be shown
+    pub fn notification_body(&self) -> Option<&str> {
+        if self.no_body || self.message.is_empty() {
+            None
+        } else {
+            Some(&self.message)
+        }
+    }
+
+    /// All notification times between `start` and `end` (inclusive).
+    ///
+    /// The result is sorted and contains at most [MAX_OCCURRENCES] entries.
+    pub fn occurrences_between(
+        &self,
+        start: NaiveDateTime,
+        end: NaiveDateTime,
+        calendar: &Calendar,
+    ) -> Result<Vec<NaiveDateTime>, TimeError> {
+        if !self.notifies() {
+            return Ok(Vec::new());
+        }
+        let offset = TimeDelta::seconds(self.offset);
+        let last_timestamp = self.timestamp.naive_local();
+        let in_range = |t: &NaiveDateTime| start <= *t && *t <= end;
 
+        let mut occurrences = Vec::new();
         if let Some(sleep) = self.sleep.as_ref() {
-            let next_sleep_notification = next_once_timing(&sleep.timing, last_timestamp)?;
+            let next = next_once_timing(&sleep.timing, last_timestamp, calendar)?;
+            if in_range(&next) {
+                occurrences.push(next);
+            }
+        }
+
+        match &self.timing {
+            Repeat::Once { timing } => {
+                let mut next = next_once_timing(timing, last_timestamp - offset, calendar)?;
+                if let Some(start_after) = self.start_after {
+                    next = clamp_to_start(&self.timing, next, start_after - offset, calendar)?;
+                }
+                let next = next + offset;
+                if in_range(&next) {
+                    occurrences.push(next);
+                }
+            }
+            Repeat::Repeat { timing } => {
+                let (start, end) = (start - offset, end - offset);
+                let in_range = |t: &NaiveDateTime| start <= *t && *t <= end;
+                let mut last = last_timestamp - offset;
+                if let Some(start_after) = self.start_after.map(|start| start - offset) {
+                    if last < start_after {
+                        let next = repeat_timing_after(timing, last, calendar)?;
+                        let first = clamp_to_start(&self.timing, next, start_after, calendar)?;
+                        if in_range(&first) {
+                            occurrences.push(first + offset);
+                        }
+                        last = first;
+                    }
+                }
+                if let time::RepeatTiming::Delay(Delay::Seconds(secs)) = timing {
+                    // skip ahead instead of stepping through every repeat before `start`
+                    let secs = *secs;
+                    if secs > 0 && last < start {
+                        let skipped = ((start - last).num_seconds() - 1) / secs;
+                        last += TimeDelta::seconds(skipped * secs);
+                    }
+                }
+                while occurrences.len() < MAX_OCCURRENCES {
+                    let next = repeat_timing_after(timing, last, calendar)?;
+                    if next <= last || next > end {
+                        break;
+                    }
+                    if in_range(&next) {
+                        occurrences.push(next + offset);
+                    }
+                    last = next;
+                }
+            }
+        }
+
+        occurrences.sort();
+        occurrences.truncate(MAX_OCCURRENCES);
+        Ok(occurrences)
+    }
+
+    pub fn should_notify(&self, calendar: &Calendar) -> Result<NotificationType, TimeError> {
+        if !self.notifies() || self.shown_within_dedup_window(Local::now()) {
+            return Ok(NotificationType::None);
+        }
+        let last_timestamp = self.timestamp.naive_local();
+        let (typ, next_notification) = self.next_notification(calendar)?;
+        if next_notification > last_timestamp
+            && Local::now() > local_datetime(next_notification, &Local)
+        {
+            Ok(typ)
+        } else {
+            Ok(NotificationType::None)
+        }
+    }
+
+    /// returns `true` if a notification was shown less than [DEDUP_WINDOW_SECS] before `now`
+    fn shown_within_dedup_window(&self, now: DateTime<Local>) -> bool {
+        self.last_shown
+            .is_some_and(|shown| now - shown < TimeDelta::seconds(DEDUP_WINDOW_SECS))
+    }
+
+    pub fn next_notification(
+        &self,
+        calendar: &Calendar,
+    ) -> Result<(NotificationType, NaiveDateTime), TimeError> {
+        let last_timestamp = self.timestamp.naive_local();
+        let next_notification = self.next_timing_notification(calendar)?;
+
+        if let Some(sleep) = self.sleep.as_ref() {
+            let next_sleep_notification =
+                next_once_timing(&sleep.timing, last_timestamp, calendar)?;
             if next_sleep_notification < next_notification {
                 Ok((NotificationType::Sleep, next_sleep_notification))
             } else {
@@ -336,31 +1605,268 @@ impl Procrastination {
             Ok((NotificationType::Normal, next_notification))
         }
     }
+
+    /// The next `n` notifications of the timing, ignoring [Procrastination::sleep].
+    ///
+    /// [Repeat::Once] procrastinations return at most one notification and
+    /// at most [MAX_OCCURRENCES] are returned.
+    pub fn next_n_notifications(
+        &self,
+        n: usize,
+        calendar: &Calendar,
+    ) -> Result<Vec<NaiveDateTime>, TimeError> {
+        if n == 0 || !self.notifies() {
+            return Ok(Vec::new());
+        }
+        let first = self.next_timing_notification(calendar)?;
+        let Repeat::Repeat { timing } = &self.timing else {
+            return Ok(vec![first]);
+        };
+
+        let offset = TimeDelta::seconds(self.offset);
+        let mut last = first - offset;
+        if first <= self.timestamp.naive_local() {
+            // exact timings can refer to a time earlier today, which is skipped
+            last = repeat_timing_after(timing, self.timestamp.naive_local() - offset, calendar)?;
+        }
+        let mut notifications = vec![last + offset];
+        while notifications.len() < n.min(MAX_OCCURRENCES) {
+            let next = repeat_timing_after(timing, last, calendar)?;
+            if next <= last {
+                break;
+            }
+            last = next;
+            if !self.skip_dates.contains(&(next + offset).date()) {
+                notifications.push(local_datetime(next + offset, &Local).naive_local());
+            }
+        }
+        Ok(notifications)
+    }
+
+    /// The next notification of the timing, ignoring [Procrastination::sleep]
+    fn next_timing_notification(&self, calendar: &Calendar) -> Result<NaiveDateTime, TimeError> {
+        let last_timestamp = self.timestamp.naive_local();
+        // the timing is evaluated without the offset, so that it does not add up with every repeat
+        let offset = TimeDelta::seconds(self.offset);
+        let mut next_notification = match &self.timing {
+            Repeat::Once { timing } => next_once_timing(timing, last_timestamp - offset, calendar)?,
+            Repeat::Repeat { timing } => {
+                next_repeat_timing(timing, last_timestamp - offset, calendar)?
+            }
+        };
+        if let Some(start_after) = self.start_after {
+            next_notification = clamp_to_start(
+                &self.timing,
+                next_notification,
+                start_after - offset,
+                calendar,
+            )?;
+        }
+        if let Repeat::Repeat { timing } = &self.timing {
+            // a repeat on a skipped date is replaced by the following one
+            for _ in 0..MAX_OCCURRENCES {
+                if !self
+                    .skip_dates
+                    .contains(&(next_notification + offset).date())
+                {
+                    break;
+                }
+                next_notification = repeat_timing_after(timing, next_notification, calendar)?;
+            }
+        }
+        // the timings are computed on the wall clock, which might skip the result on a DST change
+        Ok(local_datetime(next_notification + offset, &Local).naive_local())
+    }
 }
 
+/// The key of `key` within `group`, e.g. `work/standup`.
+pub fn group_key(group: Option<&str>, key: &str) -> String {
+    match group {
+        Some(group) => format!("{group}/{key}"),
+        None => key.to_string(),
+    }
+}
+
+/// returns `true` if `key` is part of `group`. Every key is part of the `None` group.
+pub fn in_group(key: &str, group: Option<&str>) -> bool {
+    match group {
+        Some(group) => key
+            .strip_prefix(group)
+            .is_some_and(|rest| rest.starts_with('/')),
+        None => true,
+    }
+}
+
+/// A deterministic offset in seconds for `key` within `0..jitter`.
+///
+/// Used to spread out notifications of procrastinations with the same timing.
+pub fn jitter_offset(key: &str, jitter: Delay) -> i64 {
+    let jitter = match jitter {
+        Delay::Seconds(secs) => secs,
+        _ => jitter.to_duration().num_seconds(),
+    };
+    if jitter <= 0 {
+        return 0;
+    }
+    // FNV-1a, the std hashers are not guaranteed to be stable between releases
+    let hash = key.bytes().fold(0xcbf29ce484222325u64, |hash, b| {
+        (hash ^ b as u64).wrapping_mul(0x100000001b3)
+    });
+    (hash % jitter as u64) as i64
+}
+
+/// A filter for [ProcrastinationFileData::notify_parallel_with] that accepts at most
+/// `max` due procrastinations.
+///
+/// The other due procrastinations are not notified and stay due for the next check.
+/// Procrastinations that are not due are always accepted.
+pub fn limit_due(
+    max: NonZeroUsize,
+    calendar: Calendar,
+) -> impl FnMut(&str, &Procrastination) -> bool {
+    let mut due = 0;
+    move |_, procrastination| {
+        if matches!(
+            procrastination.should_notify(&calendar),
+            Ok(NotificationType::None)
+        ) {
+            return true;
+        }
+        due += 1;
+        due <= max.get()
+    }
+}
+
+/// The maximum number of occurrences returned by [Procrastination::occurrences_between]
+pub const MAX_OCCURRENCES: usize = 1000;
+
 fn apply_delay(timestamp: NaiveDateTime, delay: Delay) -> NaiveDateTime {
     match delay {
         Delay::Seconds(secs) => timestamp + TimeDelta::seconds(secs),
-        Delay::Days(days) => (timestamp.date() + TimeDelta::days(days)).into(),
+        Delay::Days(_) | Delay::Weeks(_) => {
+            let days = delay.whole_days().unwrap_or_default();
+            (timestamp.date() + TimeDelta::days(days)).into()
+        }
+        Delay::Calendar {
+            months,
+            days,
+            seconds: 0,
+        } => (timestamp.date() + Months::new(months) + TimeDelta::days(days)).into(),
+        Delay::Calendar {
+            months,
+            days,
+            seconds,
+        } => timestamp + Months::new(months) + TimeDelta::days(days) + TimeDelta::seconds(seconds),
+    }
+}
+
+/// Converts the wall clock time `naive` into a time in `tz`, accounting for DST changes.
+///
+/// Times skipped by a DST change are moved forward by the skipped duration, e.g.
+/// 2:30 becomes 3:30 if the clock jumps from 2:00 to 3:00.
+/// Times that occur twice resolve to the later one.
+fn local_datetime<Tz: TimeZone>(naive: NaiveDateTime, tz: &Tz) -> DateTime<Tz> {
+    match tz.from_local_datetime(&naive) {
+        LocalResult::Single(datetime) => datetime,
+        LocalResult::Ambiguous(_, later) => later,
+        LocalResult::None => {
+            // interpret the time with the offset before the clock jumped
+            let before = tz.offset_from_utc_datetime(&(naive - TimeDelta::days(1)));
+            let utc = naive - TimeDelta::seconds(before.fix().local_minus_utc().into());
+            tz.from_utc_datetime(&utc)
+        }
     }
 }
 
 fn next_repeat_timing(
     timing: &time::RepeatTiming,
     last_timestamp: NaiveDateTime,
+    calendar: &Calendar,
 ) -> Result<NaiveDateTime, TimeError> {
     Ok(match timing {
-        time::RepeatTiming::Exact(e) => e.notification_date()?,
+        time::RepeatTiming::Exact(e) => e.notification_date(last_timestamp, calendar)?,
         time::RepeatTiming::Delay(delay) => apply_delay(last_timestamp, *delay),
+        time::RepeatTiming::Cron(expr) => next_cron_timing(expr, last_timestamp)?,
+        time::RepeatTiming::AnchoredDelay { delay, at } => {
+            apply_anchored_delay(last_timestamp, *delay, *at)
+        }
+        time::RepeatTiming::AlignedDelay { delay, align } => {
+            time::align_after(apply_delay(last_timestamp, *delay), last_timestamp, *align)
+        }
     })
 }
 
+/// Applies `delay` to `timestamp` such that the result is aligned to the time of day `at`.
+///
+/// Day based delays notify at `at` on the resulting day. Time based delays notify at
+/// the first `at + n * delay` after `timestamp`.
+fn apply_anchored_delay(timestamp: NaiveDateTime, delay: Delay, at: NaiveTime) -> NaiveDateTime {
+    match delay {
+        Delay::Seconds(secs) if secs > 0 => {
+            let anchor = NaiveDateTime::new(timestamp.date(), at);
+            let since_anchor = (timestamp - anchor).num_seconds();
+            let repeats = since_anchor.div_euclid(secs) + 1;
+            anchor + TimeDelta::seconds(repeats * secs)
+        }
+        Delay::Seconds(secs) => timestamp + TimeDelta::seconds(secs),
+        _ => NaiveDateTime::new(apply_delay(timestamp, delay).date(), at),
+    }
+}
+
+/// The next repeat after `after`, ignoring the current time.
+fn repeat_timing_after(
+    timing: &time::RepeatTiming,
+    after: NaiveDateTime,
+    calendar: &Calendar,
+) -> Result<NaiveDateTime, TimeError> {
+    Ok(match timing {
+        time::RepeatTiming::Exact(e) => e.next_after(after, calendar)?,
+        time::RepeatTiming::Delay(delay) => apply_delay(after, *delay),
+        time::RepeatTiming::Cron(expr) => next_cron_timing(expr, after)?,
+        time::RepeatTiming::AnchoredDelay { delay, at } => apply_anchored_delay(after, *delay, *at),
+        time::RepeatTiming::AlignedDelay { delay, align } => {
+            time::align_after(apply_delay(after, *delay), after, *align)
+        }
+    })
+}
+
+/// Returns the first notification at or after `start`, if `next` is before `start`.
+///
+/// Exact and cron timings keep their schedule, all other timings notify at `start`.
+fn clamp_to_start(
+    timing: &Repeat,
+    next: NaiveDateTime,
+    start: NaiveDateTime,
+    calendar: &Calendar,
+) -> Result<NaiveDateTime, TimeError> {
+    if next >= start {
+        return Ok(next);
+    }
+    match timing {
+        Repeat::Repeat {
+            timing: timing @ (time::RepeatTiming::Exact(_) | time::RepeatTiming::Cron(_)),
+        } => repeat_timing_after(timing, start - TimeDelta::seconds(1), calendar),
+        _ => Ok(start),
+    }
+}
+
+fn next_cron_timing(expr: &str, last_timestamp: NaiveDateTime) -> Result<NaiveDateTime, TimeError> {
+    let schedule = time::cron_schedule(expr)?;
+    let last_timestamp = local_datetime(last_timestamp, &Local);
+    schedule
+        .after(&last_timestamp)
+        .next()
+        .map(|next| next.naive_local())
+        .ok_or_else(|| TimeError::InvalidCron(expr.to_string()))
+}
+
 fn next_once_timing(
     timing: &OnceTiming,
     last_timestamp: NaiveDateTime,
+    calendar: &Calendar,
 ) -> Result<NaiveDateTime, TimeError> {
     Ok(match timing {
-        time::OnceTiming::Instant(instant) => instant.notification_date()?,
+        time::OnceTiming::Instant(instant) => instant.notification_date(calendar)?,
         time::OnceTiming::Delay(delay) => apply_delay(last_timestamp, *delay),
     })
 }
@@ -368,13 +1874,23 @@ fn next_once_timing(
 pub struct ProcrastinationFile {
     data: ProcrastinationFileData,
     lock: FileLock,
+    /// permissions of the file at the time it was opened.
+    ///
+    /// These are restored after each save.
+    permissions: Option<Permissions>,
+    path: PathBuf,
+    /// backups kept on save, see [ProcrastinationFile::set_backups]
+    backups: usize,
 }
 
-pub const FILE_NAME: &'static str = "procrastination.ron";
-pub const DEFAULT_LOCATION: &'static str = ".local/share";
+pub const FILE_NAME: &str = "procrastination.ron";
+pub const DEFAULT_LOCATION: &str = ".local/share";
 
+/// `$PROCRASTINATE_DATA_DIR`, `$XDG_DATA_HOME` or `~/.local/share`
 pub fn data_dir_path() -> PathBuf {
-    if let Ok(config) = env::var("XDG_DATA_HOME") {
+    if let Ok(dir) = env::var("PROCRASTINATE_DATA_DIR") {
+        PathBuf::from_str(&dir).unwrap_infallible()
+    } else if let Ok(config) = env::var("XDG_DATA_HOME") {
         PathBuf::from_str(&config).unwrap_infallible()
     } else {
         let home = env::var("HOME").expect("neither XDG_DATA_HOME nor HOME are set");
@@ -388,7 +1904,7 @@ pub fn procrastination_path(is_local: bool, path: Option<&PathBuf>) -> std::io::
         let current_dir = env::current_dir()?;
         current_dir.join(FILE_NAME)
     } else if let Some(file) = path {
-        file.clone()
+        expand_path(file)?
     } else {
         let config_dir = data_dir_path();
         config_dir.join(FILE_NAME)
@@ -396,6 +1912,65 @@ pub fn procrastination_path(is_local: bool, path: Option<&PathBuf>) -> std::io::
     Ok(path)
 }
 
+/// Expands a leading `~` to `$HOME` as well as any `$VAR` or `${VAR}`
+/// to the value of the environment variable `VAR`.
+///
+/// Variable names consist of ascii alphanumeric characters and `_`.
+/// Fails if a referenced variable is not set.
+pub fn expand_path(path: &Path) -> std::io::Result<PathBuf> {
+    let Some(path) = path.to_str() else {
+        return Ok(path.to_path_buf());
+    };
+
+    let var = |name: &str| {
+        env::var(name).map_err(|_| {
+            std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("environment variable \"{name}\" is not set"),
+            )
+        })
+    };
+
+    let mut expanded = String::with_capacity(path.len());
+    let mut rest = path;
+    if rest == "~" || rest.starts_with("~/") {
+        expanded.push_str(&var("HOME")?);
+        rest = &rest[1..];
+    }
+
+    while let Some(start) = rest.find('$') {
+        expanded.push_str(&rest[..start]);
+        rest = &rest[start + 1..];
+
+        let (name, remaining) = if let Some(braced) = rest.strip_prefix('{') {
+            match braced.split_once('}') {
+                Some(split) => split,
+                None => {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::InvalidInput,
+                        format!("missing closing '}}' in path \"{path}\""),
+                    ))
+                }
+            }
+        } else {
+            let end = rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len());
+            rest.split_at(end)
+        };
+
+        if name.is_empty() {
+            expanded.push('$');
+        } else {
+            expanded.push_str(&var(name)?);
+        }
+        rest = remaining;
+    }
+    expanded.push_str(rest);
+
+    Ok(PathBuf::from(expanded))
+}
+
 #[derive(Error, Debug)]
 pub enum Error {
     #[error("IO error on file-open {0}")]
@@ -404,27 +1979,80 @@ pub enum Error {
     Parse(#[from] ron::error::SpannedError),
     #[error("Failed to serialize data")]
     Serialization(#[from] ron::Error),
+    #[error("Invalid json {0}")]
+    Json(#[from] serde_json::Error),
 }
 
 impl ProcrastinationFile {
-    pub fn new(data: ProcrastinationFileData, lock: FileLock) -> Self {
-        Self { data, lock }
+    pub fn new(data: ProcrastinationFileData, lock: FileLock, path: &Path) -> Self {
+        let permissions = lock.file.metadata().ok().map(|m| m.permissions());
+        Self {
+            data,
+            lock,
+            permissions,
+            path: path.to_path_buf(),
+            backups: 0,
+        }
     }
 
     pub fn open(path: &Path) -> Result<Self, Error> {
+        Self::open_with(path, FileOptions::new().read(true).append(true))
+    }
+
+    fn open_with(path: &Path, options: FileOptions) -> Result<Self, Error> {
         if let Some(parent) = path.parent() {
             std::fs::create_dir_all(parent)?;
         }
 
-        let options = FileOptions::new().read(true).append(true);
         let mut lock = FileLock::lock(path, true, options)?;
 
-        let mut content = String::new();
-        lock.file.read_to_string(&mut content)?;
+        let data = ProcrastinationFileData::from_reader(&mut lock.file, FileFormat::Ron)?;
+        let permissions = Some(lock.file.metadata()?.permissions());
 
-        let data = ron::from_str(&content)?;
+        Ok(Self {
+            data,
+            lock,
+            permissions,
+            path: path.to_path_buf(),
+            backups: 0,
+        })
+    }
 
-        Ok(Self { data, lock })
+    /// Keep `count` backups of the file, rotated on each save.
+    ///
+    /// The previous content is copied to `<file>.bak`, older backups are moved to
+    /// `<file>.bak.2` up to `<file>.bak.<count>`. No backups are kept by default.
+    pub fn set_backups(&mut self, count: usize) {
+        self.backups = count;
+    }
+
+    /// Opens the procrastination file at `path` and passes its data to `f`.
+    ///
+    /// `f` returns its result and if it changed the data. Changed data is saved before the
+    /// file lock is released. The file is created if it does not exist.
+    pub fn with_lock<F, R>(path: &Path, f: F) -> Result<R, Error>
+    where
+        F: FnOnce(&mut ProcrastinationFileData) -> (R, bool),
+    {
+        let options = FileOptions::new().read(true).append(true).create(true);
+        let mut file = Self::open_with(path, options)?;
+        let (result, changed) = f(file.data_mut());
+        if changed {
+            file.save()?;
+        }
+        Ok(result)
+    }
+
+    /// Reads the procrastinations at `path` without opening it for writing.
+    ///
+    /// Only a shared lock is held while reading, so other readers are not blocked.
+    /// A missing file contains no procrastinations.
+    pub fn read(path: &Path) -> Result<ProcrastinationFileData, Error> {
+        if !path.exists() {
+            return Ok(ProcrastinationFileData::empty());
+        }
+        let mut lock = FileLock::lock(path, true, FileOptions::new().read(true))?;
+        ProcrastinationFileData::from_reader(&mut lock.file, FileFormat::Ron)
     }
 
     pub fn data(&self) -> &ProcrastinationFileData {
@@ -435,12 +2063,24 @@ impl ProcrastinationFile {
         &mut self.data
     }
 
+    /// The data of this file. The file lock is released.
+    pub fn into_data(self) -> ProcrastinationFileData {
+        self.data
+    }
+
     pub fn save(&mut self) -> Result<(), Error> {
+        rotate_backups(&self.path, self.backups)?;
         self.lock.file.set_len(0)?;
+        // files created with write access keep their cursor after the last save
+        self.lock.file.rewind()?;
 
-        ron::ser::to_writer_pretty(&mut self.lock.file, &self.data, PrettyConfig::default())?;
+        self.data.to_writer(&mut self.lock.file, FileFormat::Ron)?;
 
         self.lock.file.flush()?;
+
+        if let Some(permissions) = self.permissions.as_ref() {
+            self.lock.file.set_permissions(permissions.clone())?;
+        }
         Ok(())
     }
 
@@ -448,3 +2088,1700 @@ impl ProcrastinationFile {
         ron::ser::to_string_pretty(&self.data, PrettyConfig::default())
     }
 }
+
+/// Copies the file at `path` to the newest of `count` backups, see [ProcrastinationFile::set_backups]
+fn rotate_backups(path: &Path, count: usize) -> std::io::Result<()> {
+    if count == 0 || std::fs::metadata(path)?.len() == 0 {
+        return Ok(());
+    }
+    let backup = |n: usize| {
+        let mut name = path.as_os_str().to_owned();
+        name.push(".bak");
+        if n > 1 {
+            name.push(format!(".{n}"));
+        }
+        PathBuf::from(name)
+    };
+    for n in (1..count).rev() {
+        if backup(n).exists() {
+            std::fs::rename(backup(n), backup(n + 1))?;
+        }
+    }
+    std::fs::copy(path, backup(1))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn once_in(now: NaiveDateTime, secs: i64) -> Procrastination {
+        let mut proc = Procrastination::new(
+            "title".to_string(),
+            String::new(),
+            Repeat::Once {
+                timing: OnceTiming::Delay(Delay::Seconds(secs)),
+            },
+            false,
+        );
+        proc.timestamp = now.and_local_timezone(Local).unwrap();
+        proc
+    }
+
+    /// Central european time in 2024: the clock jumps from 2:00 to 3:00 on 03-31
+    /// and back from 3:00 to 2:00 on 10-27.
+    #[derive(Debug, Clone)]
+    struct Cet2024;
+
+    impl Cet2024 {
+        fn summer_time() -> std::ops::Range<NaiveDateTime> {
+            NaiveDate::from_ymd_opt(2024, 3, 31)
+                .unwrap()
+                .and_hms_opt(1, 0, 0)
+                .unwrap()
+                ..NaiveDate::from_ymd_opt(2024, 10, 27)
+                    .unwrap()
+                    .and_hms_opt(1, 0, 0)
+                    .unwrap()
+        }
+    }
+
+    impl TimeZone for Cet2024 {
+        type Offset = chrono::FixedOffset;
+
+        fn from_offset(_: &Self::Offset) -> Self {
+            Cet2024
+        }
+
+        fn offset_from_local_date(&self, local: &NaiveDate) -> LocalResult<Self::Offset> {
+            self.offset_from_local_datetime(&local.and_time(NaiveTime::MIN))
+        }
+
+        fn offset_from_local_datetime(&self, local: &NaiveDateTime) -> LocalResult<Self::Offset> {
+            let valid: Vec<_> = [1, 2]
+                .into_iter()
+                .map(|hours| chrono::FixedOffset::east_opt(hours * 60 * 60).unwrap())
+                .filter(|offset| self.offset_from_utc_datetime(&(*local - *offset)) == *offset)
+                .collect();
+            match valid[..] {
+                [offset] => LocalResult::Single(offset),
+                // summer time is the earlier of the two
+                [winter, summer] => LocalResult::Ambiguous(summer, winter),
+                _ => LocalResult::None,
+            }
+        }
+
+        fn offset_from_utc_date(&self, utc: &NaiveDate) -> Self::Offset {
+            self.offset_from_utc_datetime(&utc.and_time(NaiveTime::MIN))
+        }
+
+        fn offset_from_utc_datetime(&self, utc: &NaiveDateTime) -> Self::Offset {
+            let hours = if Cet2024::summer_time().contains(utc) {
+                2
+            } else {
+                1
+            };
+            chrono::FixedOffset::east_opt(hours * 60 * 60).unwrap()
+        }
+    }
+
+    #[test]
+    fn test_local_datetime_dst() {
+        let utc = |local: NaiveDateTime| local_datetime(local, &Cet2024).naive_utc();
+
+        // spring forward: 2:30 does not exist
+        let date = NaiveDate::from_ymd_opt(2024, 3, 31).unwrap();
+        assert_eq!(
+            local_datetime(date.and_hms_opt(2, 30, 0).unwrap(), &Cet2024).naive_local(),
+            date.and_hms_opt(3, 30, 0).unwrap()
+        );
+        assert_eq!(
+            utc(date.and_hms_opt(1, 30, 0).unwrap()),
+            date.and_hms_opt(0, 30, 0).unwrap()
+        );
+        assert_eq!(
+            utc(date.and_hms_opt(3, 30, 0).unwrap()),
+            date.and_hms_opt(1, 30, 0).unwrap()
+        );
+
+        // fall back: 2:30 exists twice, the later one is used
+        let date = NaiveDate::from_ymd_opt(2024, 10, 27).unwrap();
+        assert_eq!(
+            utc(date.and_hms_opt(2, 30, 0).unwrap()),
+            date.and_hms_opt(1, 30, 0).unwrap()
+        );
+        assert_eq!(
+            utc(date.and_hms_opt(9, 0, 0).unwrap()),
+            date.and_hms_opt(8, 0, 0).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_merge_from() {
+        let timing = Repeat::Repeat {
+            timing: "1h".parse().unwrap(),
+        };
+        let mut p = Procrastination::new("title".into(), "old".into(), timing.clone(), true);
+        let timestamp = p.timestamp;
+        p.start_after = Some(at(2025, 3, 1, 12, 0));
+
+        p.merge_from(ProcrastinationPatch {
+            message: Some("new".into()),
+            ..Default::default()
+        });
+        assert_eq!(p.message, "new");
+        assert_eq!(p.title, "title");
+        assert_eq!(p.timing, timing);
+        assert_eq!(p.timestamp, timestamp);
+        assert!(p.sticky);
+        assert_eq!(p.start_after, Some(at(2025, 3, 1, 12, 0)));
+
+        p.merge_from(ProcrastinationPatch {
+            sticky: Some(false),
+            start_after: Some(None),
+            ..Default::default()
+        });
+        assert!(!p.sticky);
+        assert_eq!(p.start_after, None);
+        assert_eq!(p.message, "new");
+
+        p.merge_from(ProcrastinationPatch {
+            icon: Some(Some("alarm".into())),
+            skip_dates: Some(vec![at(2025, 3, 2, 0, 0).date()]),
+            ..Default::default()
+        });
+        assert_eq!(p.icon.as_deref(), Some("alarm"));
+        assert_eq!(p.skip_dates, [at(2025, 3, 2, 0, 0).date()]);
+        assert_eq!(p.summary_overflow, None);
+    }
+
+    /// Fails to compile if a field is added to [Procrastination],
+    /// so that it is either added to [ProcrastinationPatch] or listed as state here.
+    #[test]
+    fn test_patch_covers_fields() {
+        let Procrastination {
+            title,
+            message,
+            timing,
+            timestamp,
+            sticky,
+            sleep,
+            no_body,
+            markdown,
+            offset,
+            priority,
+            start_after,
+            keep,
+            require_ack,
+            icon,
+            summary_overflow,
+            skip_dates,
+            // state that is updated by notifying, not by a patch
+            dirty: _,
+            fire_count: _,
+            fired: _,
+            last_shown: _,
+            notification_id: _,
+            // toggled by `pause` and `resume`
+            paused: _,
+        } = Procrastination::new(
+            String::new(),
+            String::new(),
+            Repeat::Once {
+                timing: OnceTiming::Delay(Delay::Seconds(0)),
+            },
+            false,
+        );
+        let _ = ProcrastinationPatch {
+            title: Some(title),
+            message: Some(message),
+            timing: Some(timing),
+            timestamp: Some(timestamp),
+            sticky: Some(sticky),
+            sleep: Some(sleep),
+            no_body: Some(no_body),
+            markdown: Some(markdown),
+            offset: Some(offset),
+            priority: Some(priority),
+            start_after: Some(start_after),
+            keep: Some(keep),
+            require_ack: Some(require_ack),
+            icon: Some(icon),
+            summary_overflow: Some(summary_overflow),
+            skip_dates: Some(skip_dates),
+        };
+    }
+
+    #[test]
+    fn test_describe_timing() {
+        let describe = |timing: Repeat| {
+            Procrastination::new(String::new(), String::new(), timing, false).describe_timing()
+        };
+        let once = |timing: &str| {
+            describe(Repeat::Once {
+                timing: timing.parse().unwrap(),
+            })
+        };
+        let repeat = |timing: &str| {
+            describe(Repeat::Repeat {
+                timing: timing.parse().unwrap(),
+            })
+        };
+        let instant = |instant| {
+            describe(Repeat::Once {
+                timing: OnceTiming::Instant(instant),
+            })
+        };
+
+        assert_eq!(once("1h 30min"), "once, in 1h 30min");
+        assert_eq!(once("monday 9:00"), "once, on monday at 9:00");
+        assert_eq!(once("dom 12"), "once, on day 12 of the month");
+        assert_eq!(
+            instant(RoughInstant::Date {
+                date: at(2025, 3, 1, 12, 0)
+            }),
+            "once, on 2025-03-01 at 12:00"
+        );
+        assert_eq!(
+            instant(RoughInstant::Date {
+                date: at(2025, 3, 1, 0, 0)
+            }),
+            "once, on 2025-03-01"
+        );
+        assert_eq!(instant(RoughInstant::Month { month: 3 }), "once, in march");
+
+        assert_eq!(repeat("daily 9:00"), "daily at 9:00");
+        assert_eq!(repeat("daily 9:00,17:30"), "daily at 9:00, 17:30");
+        assert_eq!(repeat("monday 9:00"), "every monday at 9:00");
+        assert_eq!(repeat("monthly 1"), "monthly on day 1");
+        assert_eq!(repeat("2h"), "every 2h");
+        assert_eq!(
+            repeat("cron 0 9 * * 1-5"),
+            "on the cron schedule \"0 9 * * 1-5\""
+        );
+        assert_eq!(
+            describe(Repeat::Repeat {
+                timing: time::RepeatTiming::AnchoredDelay {
+                    delay: Delay::Seconds(2 * 60 * 60),
+                    at: NaiveTime::from_hms_opt(9, 0, 0).unwrap(),
+                },
+            }),
+            "every 2h aligned to 9:00"
+        );
+    }
+
+    #[test]
+    fn test_in_group() {
+        assert_eq!(group_key(Some("work"), "standup"), "work/standup");
+        assert_eq!(group_key(None, "standup"), "standup");
+
+        assert!(in_group("work/standup", Some("work")));
+        assert!(!in_group("workout/run", Some("work")));
+        assert!(!in_group("standup", Some("work")));
+        assert!(in_group("standup", None));
+    }
+
+    #[test]
+    fn test_time_until_next() {
+        let now = NaiveDate::from_ymd_opt(2024, 3, 1)
+            .unwrap()
+            .and_hms_opt(9, 0, 0)
+            .unwrap();
+
+        let future = once_in(now, 60 * 60);
+        assert_eq!(
+            future
+                .time_until_next_at(now, &Calendar::default())
+                .unwrap(),
+            TimeDelta::hours(1)
+        );
+
+        let overdue = once_in(now - TimeDelta::hours(2), 60 * 60);
+        assert_eq!(
+            overdue
+                .time_until_next_at(now, &Calendar::default())
+                .unwrap(),
+            TimeDelta::hours(-1)
+        );
+    }
+
+    #[test]
+    fn test_is_due_within() {
+        let now = Local::now().naive_local().with_nanosecond(0).unwrap();
+        let window = Delay::Seconds(4 * 60 * 60);
+
+        assert!(once_in(now, 0)
+            .is_due_within(now, window, &Calendar::default())
+            .unwrap());
+        assert!(once_in(now, 60 * 60)
+            .is_due_within(now, window, &Calendar::default())
+            .unwrap());
+        assert!(once_in(now, 4 * 60 * 60)
+            .is_due_within(now, window, &Calendar::default())
+            .unwrap());
+        assert!(!once_in(now, 4 * 60 * 60 + 1)
+            .is_due_within(now, window, &Calendar::default())
+            .unwrap());
+        assert!(!once_in(now, 5 * 60 * 60)
+            .is_due_within(now, window, &Calendar::default())
+            .unwrap());
+        assert!(!once_in(now, -60)
+            .is_due_within(now, window, &Calendar::default())
+            .unwrap());
+    }
+
+    #[test]
+    fn test_next_cron_timing() {
+        // 2024-10-04 is a friday
+        let friday = at(2024, 10, 4, 10, 0);
+        assert_eq!(
+            next_cron_timing("0 9 * * 1-5", friday).unwrap(),
+            at(2024, 10, 7, 9, 0)
+        );
+        assert_eq!(
+            next_cron_timing("0 0 * * 0", friday).unwrap(),
+            at(2024, 10, 6, 0, 0)
+        );
+        assert_eq!(
+            next_cron_timing("0 0 * * 7", friday).unwrap(),
+            at(2024, 10, 6, 0, 0)
+        );
+        assert_eq!(
+            next_cron_timing("*/15 * * * *", friday).unwrap(),
+            at(2024, 10, 4, 10, 15)
+        );
+        assert_eq!(
+            next_cron_timing("30 8 1 * *", at(2024, 12, 15, 0, 0)).unwrap(),
+            at(2025, 1, 1, 8, 30)
+        );
+    }
+
+    fn repeat_at(timestamp: NaiveDateTime, timing: &str) -> Procrastination {
+        Procrastination::new_at(
+            "title".to_string(),
+            String::new(),
+            Repeat::Repeat {
+                timing: timing.parse().unwrap(),
+            },
+            false,
+            timestamp.and_local_timezone(Local).unwrap(),
+        )
+    }
+
+    fn at(y: i32, m: u32, d: u32, h: u32, min: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(y, m, d)
+            .unwrap()
+            .and_hms_opt(h, min, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_upcoming_midnight() {
+        struct Upcoming(NaiveDateTime, NaiveDateTime);
+        impl std::fmt::Display for Upcoming {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                format_upcoming_timestamp(self.0, self.1, false, false, f)
+            }
+        }
+        let year = Local::now().year();
+        let upcoming = |timestamp, now| Upcoming(timestamp, now).to_string();
+
+        let midnight = at(year, 5, 17, 0, 0);
+        assert_eq!(upcoming(midnight, midnight), "now");
+        assert_eq!(upcoming(at(year, 5, 17, 0, 1), midnight), "0:01");
+        let evening = at(year, 5, 16, 21, 0);
+        assert_eq!(upcoming(midnight, evening), "tomorrow at 0:00");
+        assert_eq!(upcoming(at(year, 5, 20, 0, 0), evening), "20.05 0:00");
+        assert_eq!(upcoming(at(year, 5, 20, 9, 30), evening), "20.05 9:30");
+    }
+
+    #[test]
+    fn test_upcoming_date_only() {
+        struct Upcoming(NaiveDateTime, NaiveDateTime, bool);
+        impl std::fmt::Display for Upcoming {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                format_upcoming_timestamp(self.0, self.1, false, self.2, f)
+            }
+        }
+        let year = Local::now().year();
+        let created = at(year, 5, 16, 21, 0);
+        let upcoming = |timing| {
+            let proc = repeat_at(created, timing);
+            let (_, next) = proc.next_notification(&Calendar::default()).unwrap();
+            Upcoming(next, created, proc.notifies_by_date()).to_string()
+        };
+
+        assert_eq!(upcoming("1d"), "tomorrow");
+        assert_eq!(upcoming("1w"), "23.05");
+        assert_eq!(upcoming("1M"), "16.06");
+        assert_eq!(upcoming("24h"), "tomorrow at 21:00");
+    }
+
+    #[test]
+    fn test_occurrences_daily() {
+        let proc = repeat_at(at(2024, 10, 7, 8, 0), "daily 9:00");
+        let occurrences = proc
+            .occurrences_between(
+                at(2024, 10, 7, 0, 0),
+                at(2024, 10, 14, 0, 0),
+                &Calendar::default(),
+            )
+            .unwrap();
+        let expected: Vec<_> = (7..14).map(|d| at(2024, 10, d, 9, 0)).collect();
+        assert_eq!(occurrences, expected);
+    }
+
+    #[test]
+    fn test_daily_at_creation_time() {
+        let proc = repeat_at(at(2024, 10, 7, 14, 30), "daily");
+        let occurrences = proc
+            .occurrences_between(
+                at(2024, 10, 7, 0, 0),
+                at(2024, 10, 9, 23, 59),
+                &Calendar::default(),
+            )
+            .unwrap();
+        assert_eq!(
+            occurrences,
+            [at(2024, 10, 8, 14, 30), at(2024, 10, 9, 14, 30)]
+        );
+
+        // a late notification updates the timestamp, but not the time of day
+        let mut proc = proc;
+        proc.timestamp = at(2024, 10, 8, 15, 2).and_local_timezone(Local).unwrap();
+        let occurrences = proc
+            .occurrences_between(
+                at(2024, 10, 8, 0, 0),
+                at(2024, 10, 9, 23, 59),
+                &Calendar::default(),
+            )
+            .unwrap();
+        assert_eq!(occurrences, [at(2024, 10, 9, 14, 30)]);
+        assert_eq!(proc.describe_timing(), "daily at 14:30");
+    }
+
+    #[test]
+    fn test_occurrences_monthly() {
+        let proc = repeat_at(at(2024, 1, 1, 0, 0), "monthly 31");
+        let occurrences = proc
+            .occurrences_between(
+                at(2024, 1, 1, 0, 0),
+                at(2025, 1, 1, 0, 0),
+                &Calendar::default(),
+            )
+            .unwrap();
+        let expected: Vec<_> = [1, 3, 5, 7, 8, 10, 12]
+            .into_iter()
+            .map(|m| at(2024, m, 31, 0, 0))
+            .collect();
+        assert_eq!(occurrences, expected);
+    }
+
+    #[test]
+    fn test_occurrences_capped() {
+        let proc = repeat_at(at(2024, 1, 1, 0, 0), "1s");
+        let occurrences = proc
+            .occurrences_between(
+                at(2024, 6, 1, 0, 0),
+                at(2025, 1, 1, 0, 0),
+                &Calendar::default(),
+            )
+            .unwrap();
+        assert_eq!(occurrences.len(), MAX_OCCURRENCES);
+        assert_eq!(occurrences[0], at(2024, 6, 1, 0, 0));
+    }
+
+    #[test]
+    fn test_agenda_daily() {
+        let now = at(2024, 10, 7, 8, 0);
+        let mut data = ProcrastinationFileData::empty();
+        data.insert("daily".to_string(), repeat_at(now, "daily 9:00"));
+        data.insert("friday".to_string(), repeat_at(now, "friday 12:00"));
+
+        let agenda = data.agenda(now, 5, &Calendar::default()).unwrap();
+        assert_eq!(agenda.len(), 5);
+        for (i, day) in agenda.iter().enumerate() {
+            assert_eq!(day.date, at(2024, 10, 7 + i as u32, 0, 0).date());
+            assert_eq!(day.entries[0].0, at(2024, 10, 7 + i as u32, 9, 0));
+            assert_eq!(day.entries[0].1, "daily");
+        }
+        assert_eq!(agenda[4].entries.len(), 2);
+        assert_eq!(agenda[4].entries[1].1, "friday");
+    }
+
+    #[test]
+    fn test_expand_path() {
+        let home = PathBuf::from(env::var("HOME").unwrap());
+        assert_eq!(
+            expand_path(Path::new("~/foo.ron")).unwrap(),
+            home.join("foo.ron")
+        );
+        assert_eq!(
+            expand_path(Path::new("$HOME/foo.ron")).unwrap(),
+            home.join("foo.ron")
+        );
+        assert_eq!(
+            expand_path(Path::new("${HOME}/foo.ron")).unwrap(),
+            home.join("foo.ron")
+        );
+        assert_eq!(
+            expand_path(Path::new("/tmp/~/$")).unwrap(),
+            PathBuf::from("/tmp/~/$")
+        );
+        assert!(expand_path(Path::new("$PROCRASTINATE_TEST_UNSET_VAR/foo.ron")).is_err());
+    }
+
+    #[test]
+    fn test_apply_anchored_delay() {
+        let nine = NaiveTime::from_hms_opt(9, 0, 0).unwrap();
+        let day = Delay::Days(1);
+        let six_hours = Delay::Seconds(6 * 60 * 60);
+
+        assert_eq!(
+            apply_anchored_delay(at(2024, 10, 7, 15, 37), day, nine),
+            at(2024, 10, 8, 9, 0)
+        );
+        assert_eq!(
+            apply_anchored_delay(at(2024, 10, 7, 8, 0), day, nine),
+            at(2024, 10, 8, 9, 0)
+        );
+        assert_eq!(
+            apply_anchored_delay(at(2024, 10, 7, 8, 0), six_hours, nine),
+            at(2024, 10, 7, 9, 0)
+        );
+        assert_eq!(
+            apply_anchored_delay(at(2024, 10, 7, 9, 0), six_hours, nine),
+            at(2024, 10, 7, 15, 0)
+        );
+        assert_eq!(
+            apply_anchored_delay(at(2024, 10, 7, 15, 37), six_hours, nine),
+            at(2024, 10, 7, 21, 0)
+        );
+        assert_eq!(
+            apply_anchored_delay(at(2024, 10, 7, 1, 0), six_hours, nine),
+            at(2024, 10, 7, 3, 0)
+        );
+    }
+
+    #[test]
+    fn test_remove_stale() {
+        let now = Local::now().naive_local();
+        let mut data = ProcrastinationFileData::empty();
+        data.insert(
+            "stale".to_string(),
+            once_in(now - TimeDelta::days(41), 24 * 60 * 60),
+        );
+        data.insert("late".to_string(), once_in(now - TimeDelta::days(3), 0));
+        data.insert(
+            "repeat".to_string(),
+            repeat_at(now - TimeDelta::days(40), "1d"),
+        );
+
+        assert!(data
+            .remove_stale(now, Delay::Days(7), &Calendar::default())
+            .unwrap());
+        assert!(data.get("stale").is_none());
+        assert!(data.get("late").is_some());
+        assert!(data.get("repeat").is_some());
+        assert!(!data
+            .remove_stale(now, Delay::Days(7), &Calendar::default())
+            .unwrap());
+    }
+
+    #[test]
+    fn test_iter_sorted_by_next() {
+        let now = at(2024, 10, 7, 8, 0);
+        let mut data = ProcrastinationFileData::empty();
+        data.insert("c".to_string(), once_in(now, 60));
+        data.insert("a".to_string(), repeat_at(now, "2d"));
+        data.insert("b".to_string(), once_in(now, 60));
+        data.insert("d".to_string(), repeat_at(now, "12h"));
+
+        let keys: Vec<_> = data
+            .iter_sorted_by_next(&Calendar::default())
+            .unwrap()
+            .into_iter()
+            .map(|(key, _)| key.as_str())
+            .collect();
+        assert_eq!(keys, ["b", "c", "d", "a"]);
+    }
+
+    /// Fails for every notification with the given summary
+    struct FailingSink(&'static str);
+
+    impl NotificationSink for FailingSink {
+        fn show(&mut self, notification: &Notification) -> Result<(), notify_rust::error::Error> {
+            if notification.summary == self.0 {
+                Err("notification server unavailable".into())
+            } else {
+                Ok(())
+            }
+        }
+    }
+
+    /// Hands out increasing notification ids and records which id each notification replaced
+    #[derive(Default)]
+    struct ReplacingSink {
+        next_id: u32,
+        replaced: Vec<Option<u32>>,
+    }
+
+    impl NotificationSink for ReplacingSink {
+        fn show(&mut self, _: &Notification) -> Result<(), notify_rust::error::Error> {
+            Ok(())
+        }
+
+        fn show_replacing(
+            &mut self,
+            _: &Notification,
+            replaces: Option<u32>,
+        ) -> Result<Option<u32>, notify_rust::error::Error> {
+            self.replaced.push(replaces);
+            self.next_id += 1;
+            Ok(Some(replaces.unwrap_or(self.next_id)))
+        }
+    }
+
+    #[test]
+    fn test_notification_replaces_previous() {
+        let defaults = FileDefaults::default();
+        let mut sink = ReplacingSink::default();
+        let mut proc = repeat_at(Local::now().naive_local() - TimeDelta::hours(2), "1h");
+
+        proc.notify_with(&defaults, &NotifySettings::default(), &mut sink)
+            .unwrap();
+        assert_eq!(proc.notification_id, Some(1));
+
+        let mut data = ProcrastinationFileData::empty();
+        data.insert("repeat".to_string(), proc);
+        let ids = data.notification_ids();
+        assert_eq!(ids.get("repeat"), Some(&1));
+
+        // the id is not stored in the file
+        let mut data = ProcrastinationFileData::from_ron(&ron::to_string(&data).unwrap()).unwrap();
+        assert_eq!(data.get("repeat").unwrap().notification_id, None);
+        data.restore_notification_ids(&ids);
+
+        let proc = data.get_mut("repeat").unwrap();
+        proc.timestamp -= TimeDelta::hours(2);
+        proc.last_shown = None;
+        proc.notify_with(&defaults, &NotifySettings::default(), &mut sink)
+            .unwrap();
+        assert_eq!(sink.replaced, vec![None, Some(1)]);
+        assert_eq!(proc.notification_id, Some(1));
+    }
+
+    /// Delays each notification by the length of its summary, reversing the delivery order
+    /// of notifications with shorter titles.
+    #[derive(Clone)]
+    struct SlowSink;
+
+    impl NotificationSink for SlowSink {
+        fn show(&mut self, notification: &Notification) -> Result<(), notify_rust::error::Error> {
+            let delay = 10 - notification.summary.len().min(10) as u64;
+            std::thread::sleep(std::time::Duration::from_millis(delay * 5));
+            if notification.summary == "fail" {
+                Err("notification server unavailable".into())
+            } else {
+                Ok(())
+            }
+        }
+    }
+
+    #[test]
+    fn test_limit_due() {
+        let now = Local::now().naive_local();
+        let mut data = ProcrastinationFileData::empty();
+        for i in 0..5 {
+            data.insert(
+                format!("due-{i}"),
+                repeat_at(now - TimeDelta::hours(2), "1h"),
+            );
+        }
+        data.insert("later".to_string(), repeat_at(now, "1h"));
+
+        let max = NonZeroUsize::new(2).unwrap();
+        let report = data.notify_parallel_with(
+            NonZeroUsize::MIN,
+            &NotifySettings::default(),
+            &SlowSink,
+            limit_due(max, Calendar::default()),
+        );
+        assert_eq!(report.fired, ["due-0", "due-1"]);
+        assert_eq!(report.skipped, ["later"]);
+
+        let still_due = data
+            .iter()
+            .filter(|(_, p)| {
+                p.should_notify(&Calendar::default()).unwrap() != NotificationType::None
+            })
+            .count();
+        assert_eq!(still_due, 3);
+    }
+
+    #[test]
+    fn test_notify_parallel_same_state() {
+        let now = Local::now().naive_local();
+        let data = || {
+            let mut data = ProcrastinationFileData::empty();
+            for (i, title) in ["a", "bb", "fail", "dddd", "eeeee", "ffffff"]
+                .into_iter()
+                .enumerate()
+            {
+                let mut proc = once_in(now - TimeDelta::minutes(2), 60);
+                proc.title = title.to_string();
+                proc.keep = i % 2 == 0;
+                data.insert(format!("key-{i}"), proc);
+            }
+            data.insert("later".to_string(), once_in(now, 60 * 60));
+            data
+        };
+        let state = |data: &ProcrastinationFileData| {
+            let mut state: Vec<_> = data
+                .iter()
+                .map(|(key, proc)| (key.clone(), proc.fired, proc.fire_count))
+                .collect();
+            state.sort();
+            state
+        };
+
+        let mut serial = data();
+        let serial_report = serial.notify_parallel_with(
+            NonZeroUsize::MIN,
+            &NotifySettings::default(),
+            &SlowSink,
+            |_, _| true,
+        );
+        serial.cleanup();
+
+        let mut parallel = data();
+        let parallel_report = parallel.notify_parallel_with(
+            NonZeroUsize::new(4).unwrap(),
+            &NotifySettings::default(),
+            &SlowSink,
+            |_, _| true,
+        );
+        parallel.cleanup();
+
+        assert_eq!(state(&serial), state(&parallel));
+        assert_eq!(serial_report.fired, parallel_report.fired);
+        assert_eq!(serial_report.skipped, vec!["later".to_string()]);
+        assert_eq!(serial_report.skipped, parallel_report.skipped);
+        let failed = |report: &NotifyReport| {
+            report
+                .failed
+                .iter()
+                .map(|(key, _)| key.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(failed(&parallel_report), vec!["key-2".to_string()]);
+        assert_eq!(failed(&serial_report), failed(&parallel_report));
+    }
+
+    #[test]
+    fn test_critical_priority_hints() {
+        let now = Local::now().naive_local();
+        let mut sink = RecordingSink::default();
+
+        let mut critical = repeat_at(now - TimeDelta::hours(2), "1h");
+        critical.priority = Priority::Critical;
+        critical
+            .notify_with(
+                &FileDefaults::default(),
+                &NotifySettings::default(),
+                &mut sink,
+            )
+            .unwrap();
+        let mut normal = repeat_at(now - TimeDelta::hours(2), "1h");
+        normal
+            .notify_with(
+                &FileDefaults::default(),
+                &NotifySettings::default(),
+                &mut sink,
+            )
+            .unwrap();
+
+        let hints = &sink.0[0].hints;
+        assert!(hints.contains(&notify_rust::Hint::Urgency(notify_rust::Urgency::Critical)));
+        assert!(hints.contains(&notify_rust::Hint::Resident(true)));
+
+        let hints = &sink.0[1].hints;
+        assert!(hints.contains(&notify_rust::Hint::Urgency(notify_rust::Urgency::Normal)));
+        assert!(!hints.contains(&notify_rust::Hint::Resident(true)));
+    }
+
+    #[test]
+    fn test_quiet_hours() {
+        let now = Local::now().naive_local();
+        let defaults = FileDefaults {
+            quiet_hours: Some(QuietHours {
+                start: (now - TimeDelta::hours(1)).time(),
+                end: (now + TimeDelta::hours(1)).time(),
+            }),
+            ..Default::default()
+        };
+        let mut sink = RecordingSink::default();
+
+        let mut normal = repeat_at(now - TimeDelta::hours(2), "1h");
+        assert_eq!(
+            normal
+                .notify_with(&defaults, &NotifySettings::default(), &mut sink)
+                .unwrap(),
+            NotificationType::None
+        );
+        assert!(sink.0.is_empty());
+        assert_eq!(
+            normal.should_notify(&Calendar::default()).unwrap(),
+            NotificationType::Normal
+        );
+
+        let mut critical = repeat_at(now - TimeDelta::hours(2), "1h");
+        critical.priority = Priority::Critical;
+        assert_eq!(
+            critical
+                .notify_with(&defaults, &NotifySettings::default(), &mut sink)
+                .unwrap(),
+            NotificationType::Normal
+        );
+        assert_eq!(sink.0.len(), 1);
+    }
+
+    #[test]
+    fn test_quiet_hours_range() {
+        let time = |h| NaiveTime::from_hms_opt(h, 0, 0).unwrap();
+        let night = QuietHours {
+            start: time(22),
+            end: time(7),
+        };
+        assert!(night.contains(time(23)));
+        assert!(night.contains(time(3)));
+        assert!(!night.contains(time(7)));
+        assert!(!night.contains(time(12)));
+
+        let noon = at(2024, 1, 1, 12, 0);
+        assert_eq!(night.until_end(noon), TimeDelta::hours(19));
+        let lunch = QuietHours {
+            start: time(12),
+            end: time(13),
+        };
+        assert!(lunch.contains(time(12)));
+        assert!(!lunch.contains(time(13)));
+        assert_eq!(lunch.until_end(noon), TimeDelta::hours(1));
+    }
+
+    /// Records all shown notifications of a server with limited capabilities
+    struct LimitedSink(ServerCapabilities, Vec<Notification>);
+
+    impl NotificationSink for LimitedSink {
+        fn show(&mut self, notification: &Notification) -> Result<(), notify_rust::error::Error> {
+            self.1.push(notification.clone());
+            Ok(())
+        }
+
+        fn capabilities(&self) -> ServerCapabilities {
+            self.0
+        }
+    }
+
+    #[test]
+    fn test_unsupported_capabilities() {
+        let now = Local::now().naive_local();
+        let sticky = || {
+            let mut proc = repeat_at(now - TimeDelta::hours(2), "1h");
+            proc.message = "**done**".to_string();
+            proc.markdown = true;
+            proc.sticky = true;
+            proc.priority = Priority::Critical;
+            proc
+        };
+
+        let mut limited = LimitedSink(ServerCapabilities::NONE, Vec::new());
+        sticky()
+            .notify_with(
+                &FileDefaults::default(),
+                &NotifySettings::default(),
+                &mut limited,
+            )
+            .unwrap();
+        let notification = &limited.1[0];
+        assert!(!notification
+            .hints
+            .contains(&notify_rust::Hint::Resident(true)));
+        assert_eq!(notification.body, "**done**");
+
+        let mut full = LimitedSink(ServerCapabilities::ALL, Vec::new());
+        sticky()
+            .notify_with(
+                &FileDefaults::default(),
+                &NotifySettings::default(),
+                &mut full,
+            )
+            .unwrap();
+        let notification = &full.1[0];
+        assert!(notification
+            .hints
+            .contains(&notify_rust::Hint::Resident(true)));
+        assert_eq!(notification.body, "<b>done</b>");
+
+        let names = ["body".to_string(), "body-markup".to_string()];
+        assert_eq!(
+            ServerCapabilities::from_names(&names),
+            ServerCapabilities {
+                body_markup: true,
+                persistence: false,
+            }
+        );
+    }
+
+    #[test]
+    fn test_cached_capabilities() {
+        let cache = OnceLock::new();
+        let failed = cached_capabilities(&cache, || Err("no server"));
+        assert_eq!(failed, ServerCapabilities::ALL);
+        assert!(cache.get().is_none());
+
+        let queried = cached_capabilities(&cache, || Ok::<_, String>(Vec::new()));
+        assert_eq!(queried, ServerCapabilities::NONE);
+        let cached = cached_capabilities(&cache, || Ok::<_, String>(vec!["persistence".into()]));
+        assert_eq!(cached, ServerCapabilities::NONE);
+    }
+
+    #[test]
+    fn test_urgency_icon() {
+        let now = Local::now().naive_local();
+        let mut sink = RecordingSink::default();
+        let settings = NotifySettings {
+            urgency_icons: config::UrgencyIcons {
+                critical: Some("dialog-warning".to_string()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let mut critical = repeat_at(now - TimeDelta::hours(2), "1h");
+        critical.priority = Priority::Critical;
+        critical
+            .notify_with(&FileDefaults::default(), &settings, &mut sink)
+            .unwrap();
+        let mut own_icon = repeat_at(now - TimeDelta::hours(2), "1h");
+        own_icon.priority = Priority::Critical;
+        own_icon.icon = Some("alarm".to_string());
+        own_icon
+            .notify_with(&FileDefaults::default(), &settings, &mut sink)
+            .unwrap();
+        let mut normal = repeat_at(now - TimeDelta::hours(2), "1h");
+        normal
+            .notify_with(&FileDefaults::default(), &settings, &mut sink)
+            .unwrap();
+
+        assert_eq!(sink.0[0].icon, "dialog-warning");
+        assert_eq!(sink.0[1].icon, "alarm");
+        assert_eq!(sink.0[2].icon, "");
+    }
+
+    #[test]
+    fn test_require_ack() {
+        let now = Local::now().naive_local();
+        let mut data = ProcrastinationFileData::empty();
+        let mut proc = once_in(now - TimeDelta::minutes(2), 60);
+        proc.require_ack = true;
+        data.insert("ack".to_string(), proc);
+
+        let mut sink = RecordingSink::default();
+        for _ in 0..3 {
+            let report = data.notify_all_with(&NotifySettings::default(), &mut sink);
+            assert_eq!(report.fired, vec!["ack".to_string()]);
+            data.cleanup();
+            // the next check happens after the dedup window
+            let ack = data.get_mut("ack").unwrap();
+            ack.last_shown = ack
+                .last_shown
+                .map(|shown| shown - TimeDelta::seconds(DEDUP_WINDOW_SECS));
+        }
+        assert_eq!(sink.0.len(), 3);
+        assert_eq!(data.get("ack").unwrap().fire_count, 3);
+
+        data.remove("ack");
+        assert_eq!(data.iter().count(), 0);
+    }
+
+    #[test]
+    fn test_dedup_window() {
+        let now = Local::now().naive_local();
+        let mut proc = once_in(now - TimeDelta::minutes(2), 60);
+        proc.require_ack = true;
+
+        let mut sink = RecordingSink::default();
+        let defaults = FileDefaults::default();
+        assert_eq!(
+            proc.notify_with(&defaults, &NotifySettings::default(), &mut sink)
+                .unwrap(),
+            NotificationType::Normal
+        );
+        assert_eq!(
+            proc.notify_with(&defaults, &NotifySettings::default(), &mut sink)
+                .unwrap(),
+            NotificationType::None
+        );
+        assert_eq!(sink.0.len(), 1);
+        assert!(proc.is_overdue(&Calendar::default()));
+
+        proc.last_shown = Some(Local::now() - TimeDelta::seconds(DEDUP_WINDOW_SECS));
+        assert_eq!(
+            proc.notify_with(&defaults, &NotifySettings::default(), &mut sink)
+                .unwrap(),
+            NotificationType::Normal
+        );
+        assert_eq!(sink.0.len(), 2);
+    }
+
+    #[test]
+    fn test_keep_fired_once() {
+        let now = Local::now().naive_local();
+        let mut data = ProcrastinationFileData::empty();
+        let created = now - TimeDelta::minutes(2);
+        let mut kept = once_in(created, 60);
+        kept.keep = true;
+        data.insert("kept".to_string(), kept);
+        data.insert("deleted".to_string(), once_in(created, 60));
+
+        let report =
+            data.notify_all_with(&NotifySettings::default(), &mut RecordingSink::default());
+        assert_eq!(
+            report.fired,
+            vec!["deleted".to_string(), "kept".to_string()]
+        );
+        assert!(data.cleanup());
+
+        assert!(data.get("deleted").is_none());
+        let kept = data.get("kept").unwrap();
+        assert!(kept.fired);
+        assert!(kept.flags().contains(&"done"));
+        assert!(!kept.is_overdue(&Calendar::default()));
+        assert!(!kept.can_notify_in_future());
+        assert_eq!(
+            kept.should_notify(&Calendar::default()).unwrap(),
+            NotificationType::None
+        );
+
+        let report =
+            data.notify_all_with(&NotifySettings::default(), &mut RecordingSink::default());
+        assert_eq!(report.skipped, vec!["kept".to_string()]);
+        assert!(!data.cleanup());
+        assert!(data.get("kept").is_some());
+    }
+
+    #[test]
+    fn test_paused() {
+        let now = Local::now().naive_local();
+        let mut data = ProcrastinationFileData::empty();
+        let mut paused = once_in(now - TimeDelta::minutes(2), 60);
+        paused.paused = true;
+        data.insert("paused".to_string(), paused);
+
+        let report =
+            data.notify_all_with(&NotifySettings::default(), &mut RecordingSink::default());
+        assert_eq!(report.skipped, vec!["paused".to_string()]);
+        assert!(!data.cleanup());
+
+        let paused = data.get_mut("paused").unwrap();
+        assert!(paused.flags().contains(&"paused"));
+        assert!(!paused.is_overdue(&Calendar::default()));
+        assert!(!paused.can_notify_in_future());
+
+        paused.paused = false;
+        let report =
+            data.notify_all_with(&NotifySettings::default(), &mut RecordingSink::default());
+        assert_eq!(report.fired, vec!["paused".to_string()]);
+    }
+
+    #[test]
+    fn test_explain() {
+        let proc = repeat_at(Local::now().naive_local(), "daily 9:00");
+        let explanation = proc.explain(false, &Calendar::default()).unwrap();
+        assert!(
+            explanation.starts_with("Will first notify: 9:00")
+                || explanation.starts_with("Will first notify: tomorrow at 9:00"),
+            "{explanation}"
+        );
+        assert!(
+            explanation.ends_with("; repeats daily at 9:00"),
+            "{explanation}"
+        );
+    }
+
+    #[test]
+    fn test_skip_dates() {
+        let mut proc = repeat_at(at(2024, 12, 24, 10, 0), "1d");
+        let (_, next) = proc.next_notification(&Calendar::default()).unwrap();
+        assert_eq!(next.date(), NaiveDate::from_ymd_opt(2024, 12, 25).unwrap());
+
+        proc.skip_dates = vec![NaiveDate::from_ymd_opt(2024, 12, 25).unwrap()];
+        let (_, skipped) = proc.next_notification(&Calendar::default()).unwrap();
+        assert_eq!(skipped, next + TimeDelta::days(1));
+    }
+
+    #[test]
+    fn test_next_n_notifications() {
+        let daily = repeat_at(at(2024, 6, 3, 10, 0), "1d");
+        let days: Vec<_> = (4..9).map(|day| at(2024, 6, day, 0, 0)).collect();
+        assert_eq!(
+            daily.next_n_notifications(5, &Calendar::default()).unwrap(),
+            days
+        );
+        assert!(daily
+            .next_n_notifications(0, &Calendar::default())
+            .unwrap()
+            .is_empty());
+
+        let now = Local::now().naive_local();
+        let once = once_in(now, 60);
+        assert_eq!(
+            once.next_n_notifications(5, &Calendar::default())
+                .unwrap()
+                .len(),
+            1
+        );
+
+        let tiny = repeat_at(at(2024, 6, 3, 10, 0), "1s");
+        assert_eq!(
+            tiny.next_n_notifications(usize::MAX, &Calendar::default())
+                .unwrap()
+                .len(),
+            MAX_OCCURRENCES
+        );
+    }
+
+    #[test]
+    fn test_aligned_delay() {
+        let timing = time::RepeatTiming::AlignedDelay {
+            delay: Delay::Seconds(60 * 60),
+            align: time::AlignUnit::Hour,
+        };
+        let mut proc = repeat_at(at(2024, 6, 3, 10, 17), "1h");
+        proc.timing = Repeat::Repeat { timing };
+
+        let (_, next) = proc.next_notification(&Calendar::default()).unwrap();
+        assert_eq!(next, at(2024, 6, 3, 11, 0));
+        // a late notification does not drift
+        for expected in [12, 13, 14] {
+            let (_, next) = proc.next_notification(&Calendar::default()).unwrap();
+            proc.timestamp = (next + TimeDelta::seconds(2))
+                .and_local_timezone(Local)
+                .unwrap();
+            let (_, next) = proc.next_notification(&Calendar::default()).unwrap();
+            assert_eq!(next, at(2024, 6, 3, expected, 0));
+        }
+
+        proc.timing = Repeat::Repeat {
+            timing: time::RepeatTiming::AlignedDelay {
+                delay: Delay::Seconds(30 * 60),
+                align: time::AlignUnit::Day,
+            },
+        };
+        proc.timestamp = at(2024, 6, 3, 10, 17).and_local_timezone(Local).unwrap();
+        let (_, next) = proc.next_notification(&Calendar::default()).unwrap();
+        assert_eq!(next, at(2024, 6, 4, 0, 0));
+    }
+
+    #[test]
+    fn test_backups() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(FILE_NAME);
+        let backup = |suffix: &str| PathBuf::from(format!("{}{suffix}", path.display()));
+
+        let save = |key: &str| {
+            let mut file = ProcrastinationFile::open_with(
+                &path,
+                FileOptions::new().read(true).append(true).create(true),
+            )
+            .unwrap();
+            file.set_backups(2);
+            file.data_mut()
+                .insert(key.to_string(), once_in(at(2024, 1, 1, 12, 0), 60));
+            file.save().unwrap();
+            std::fs::read_to_string(&path).unwrap()
+        };
+
+        let first = save("first");
+        // there was no previous content
+        assert!(!backup(".bak").exists());
+        let second = save("second");
+        assert_eq!(std::fs::read_to_string(backup(".bak")).unwrap(), first);
+        save("third");
+        assert_eq!(std::fs::read_to_string(backup(".bak")).unwrap(), second);
+        assert_eq!(std::fs::read_to_string(backup(".bak.2")).unwrap(), first);
+        save("fourth");
+        assert!(!backup(".bak.3").exists());
+    }
+
+    #[test]
+    fn test_with_lock() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(FILE_NAME);
+
+        let inserted = ProcrastinationFile::with_lock(&path, |data| {
+            data.insert("new".to_string(), once_in(at(2024, 1, 1, 12, 0), 60));
+            (data.iter().count(), true)
+        })
+        .unwrap();
+        assert_eq!(inserted, 1);
+        assert!(ProcrastinationFile::read(&path)
+            .unwrap()
+            .get("new")
+            .is_some());
+
+        // unchanged data is not saved
+        ProcrastinationFile::with_lock(&path, |data| {
+            data.remove("new");
+            ((), false)
+        })
+        .unwrap();
+        assert!(ProcrastinationFile::read(&path)
+            .unwrap()
+            .get("new")
+            .is_some());
+    }
+
+    #[test]
+    fn test_reader_writer_round_trip() {
+        let mut data = ProcrastinationFileData::empty();
+        data.defaults.timeout_ms = Some(5000);
+        let mut sticky = repeat_at(at(2024, 1, 1, 12, 0), "daily 9:00,17:30");
+        sticky.sticky = true;
+        sticky.priority = Priority::Critical;
+        data.insert("sticky".to_string(), sticky);
+        let mut once = once_in(at(2024, 1, 1, 12, 0), 3600);
+        once.start_after = Some(at(2024, 1, 2, 8, 0));
+        once.icon = Some("alarm".to_string());
+        data.insert("once".to_string(), once);
+
+        for format in [FileFormat::Ron, FileFormat::Json] {
+            let mut buffer = Vec::new();
+            data.to_writer(&mut buffer, format).unwrap();
+            let read = ProcrastinationFileData::from_reader(buffer.as_slice(), format).unwrap();
+
+            assert_eq!(read.defaults.timeout_ms, Some(5000), "{format:?}");
+            assert_eq!(read.iter().count(), 2);
+            for (key, procrastination) in data.iter() {
+                assert_eq!(
+                    ron::to_string(read.get(key).unwrap()).unwrap(),
+                    ron::to_string(procrastination).unwrap(),
+                    "{format:?}"
+                );
+            }
+        }
+
+        let json = ProcrastinationFileData::from_reader("{".as_bytes(), FileFormat::Json);
+        assert!(matches!(json, Err(Error::Json(_))));
+    }
+
+    #[test]
+    fn test_legacy_file_default_timeout() {
+        let legacy = r#"{
+            "water": (
+                title: "Drink water",
+                message: "",
+                timing: Repeat(timing: Delay(Seconds(3600))),
+                timestamp: "2024-01-01T12:00:00+01:00",
+            ),
+        }"#;
+        let mut data = ProcrastinationFileData::from_ron(legacy).unwrap();
+        assert_eq!(data.defaults.timeout_ms, None);
+        assert_eq!(data.get("water").unwrap().title, "Drink water");
+
+        data.defaults.timeout_ms = Some(5000);
+        let mut sticky = repeat_at(at(2024, 1, 1, 12, 0), "1h");
+        sticky.sticky = true;
+        data.insert("sticky".to_string(), sticky);
+
+        let saved = ron::to_string(&data).unwrap();
+        let mut data = ProcrastinationFileData::from_ron(&saved).unwrap();
+        assert_eq!(data.defaults.timeout_ms, Some(5000));
+
+        let mut sink = RecordingSink::default();
+        let report = data.notify_all_with(&NotifySettings::default(), &mut sink);
+        assert_eq!(
+            report.fired,
+            vec!["sticky".to_string(), "water".to_string()]
+        );
+        assert_eq!(sink.0[0].timeout, notify_rust::Timeout::Never);
+        assert_eq!(sink.0[1].timeout, notify_rust::Timeout::Milliseconds(5000));
+    }
+
+    #[test]
+    fn test_notify_all_continues_after_error() {
+        let now = Local::now().naive_local();
+        let mut data = ProcrastinationFileData::empty();
+        for key in ["a", "b", "c"] {
+            let mut proc = repeat_at(now - TimeDelta::hours(2), "1h");
+            proc.title = key.to_string();
+            data.insert(key.to_string(), proc);
+        }
+
+        let report = data.notify_all_with(&NotifySettings::default(), &mut FailingSink("b"));
+        assert_eq!(report.fired, vec!["a".to_string(), "c".to_string()]);
+        assert!(report.skipped.is_empty());
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(report.failed[0].0, "b");
+
+        assert_eq!(data.get("a").unwrap().fire_count, 1);
+        assert_eq!(data.get("b").unwrap().fire_count, 0);
+        assert_eq!(data.get("c").unwrap().fire_count, 1);
+        assert_eq!(
+            data.get("b")
+                .unwrap()
+                .should_notify(&Calendar::default())
+                .unwrap(),
+            NotificationType::Normal
+        );
+        assert_eq!(
+            data.get("a")
+                .unwrap()
+                .should_notify(&Calendar::default())
+                .unwrap(),
+            NotificationType::None
+        );
+    }
+
+    #[test]
+    fn test_created_at_label() {
+        let mut proc = repeat_at(at(2024, 1, 1, 12, 0), "1h");
+        assert!(proc
+            .colored(false, &Calendar::default())
+            .to_string()
+            .contains("created at: "));
+        assert!(!proc
+            .colored(false, &Calendar::default())
+            .to_string()
+            .contains("last notification: "));
+
+        proc.mark_notified();
+        assert_eq!(proc.fire_count, 1);
+        assert!(proc
+            .colored(false, &Calendar::default())
+            .to_string()
+            .contains("last notification: "));
+        assert!(!proc
+            .colored(false, &Calendar::default())
+            .to_string()
+            .contains("created at: "));
+    }
+
+    #[test]
+    fn test_summary_overflow() {
+        let title = "word ".repeat(40);
+        let title = title.trim_end();
+        assert_eq!(title.chars().count(), 199);
+        let title = format!("{title}!");
+        let mut proc = repeat_at(Local::now().naive_local() - TimeDelta::hours(2), "1h");
+        proc.title = title.clone();
+        proc.message = "message".to_string();
+
+        let defaults = FileDefaults {
+            summary_overflow: SummaryOverflow::Truncate { max: 50 },
+            ..Default::default()
+        };
+        let mut sink = RecordingSink::default();
+        proc.notify_with(&defaults, &NotifySettings::default(), &mut sink)
+            .unwrap();
+        let summary = &sink.0[0].summary;
+        assert!(summary.chars().count() <= 50, "{summary}");
+        assert!(summary.ends_with("word…"), "{summary}");
+        assert_eq!(sink.0[0].body, format!("{title}\n\nmessage"));
+
+        let (summary, rest) = SummaryOverflow::MoveToBody { max: 12 }.split("one two three four");
+        assert_eq!(summary, "one two…");
+        assert_eq!(rest.as_deref(), Some("…three four"));
+
+        let short = "short title";
+        assert_eq!(
+            SummaryOverflow::Truncate { max: 50 }.split(short),
+            (short.to_string(), None)
+        );
+        assert_eq!(
+            SummaryOverflow::Keep.split(&title),
+            (title.to_string(), None)
+        );
+    }
+
+    /// Fails while the notification server is not running
+    struct StoppedServerSink;
+
+    impl NotificationSink for StoppedServerSink {
+        fn show(&mut self, _: &Notification) -> Result<(), notify_rust::error::Error> {
+            Err("org.freedesktop.DBus.Error.ServiceUnknown".into())
+        }
+
+        fn server_running(&self) -> bool {
+            false
+        }
+    }
+
+    #[test]
+    fn test_transient_errors() {
+        let now = Local::now().naive_local();
+        let defaults = FileDefaults::default();
+        let settings = NotifySettings::default();
+
+        let mut proc = repeat_at(now - TimeDelta::hours(2), "1h");
+        let err = proc
+            .notify_with(&defaults, &settings, &mut StoppedServerSink)
+            .unwrap_err();
+        assert!(err.is_transient());
+
+        proc.title = "fails".to_string();
+        let err = proc
+            .notify_with(&defaults, &settings, &mut FailingSink("fails"))
+            .unwrap_err();
+        assert!(!err.is_transient());
+    }
+
+    #[test]
+    fn test_retry_delay() {
+        let retry = config::NotificationRetry {
+            attempts: 3,
+            backoff_ms: 100,
+        };
+        assert_eq!(retry.delay(1), Some(std::time::Duration::from_millis(100)));
+        assert_eq!(retry.delay(2), Some(std::time::Duration::from_millis(200)));
+        assert_eq!(retry.delay(3), None);
+    }
+
+    #[test]
+    fn test_calendar_delay() {
+        let mid_january = at(2024, 1, 15, 10, 0);
+        let approximate = Delay::Seconds(30 * 24 * 60 * 60 + 36 * 60 * 60);
+        let calendar = Delay::Calendar {
+            months: 1,
+            days: 1,
+            seconds: 12 * 60 * 60,
+        };
+        // january has 31 days, so 30 days fall short of a month
+        assert_eq!(
+            apply_delay(mid_january, approximate),
+            at(2024, 2, 15, 22, 0)
+        );
+        assert_eq!(apply_delay(mid_january, calendar), at(2024, 2, 16, 22, 0));
+
+        let month = Delay::Calendar {
+            months: 1,
+            days: 0,
+            seconds: 0,
+        };
+        assert_eq!(
+            apply_delay(mid_january, Delay::Days(30)),
+            at(2024, 2, 14, 0, 0)
+        );
+        assert_eq!(apply_delay(mid_january, month), at(2024, 2, 15, 0, 0));
+        assert_eq!(
+            apply_delay(at(2024, 1, 31, 10, 0), month),
+            at(2024, 2, 29, 0, 0)
+        );
+        assert!(month.same_duration(&Delay::Days(30)));
+        assert_eq!(month.to_string(), "1M");
+        assert_eq!(calendar.to_string(), "1M 1d 12h");
+    }
+
+    #[test]
+    fn test_month_delay() {
+        let months = |months| Delay::Calendar {
+            months,
+            days: 0,
+            seconds: 0,
+        };
+        let mid_january = at(2024, 1, 15, 10, 0);
+        assert_eq!(apply_delay(mid_january, months(1)), at(2024, 2, 15, 0, 0));
+        assert_eq!(
+            apply_delay(at(2024, 1, 31, 10, 0), months(1)),
+            at(2024, 2, 29, 0, 0)
+        );
+        assert_eq!(
+            apply_delay(at(2024, 2, 29, 10, 0), months(12)),
+            at(2025, 2, 28, 0, 0)
+        );
+        assert_eq!(months(1).normalize(), months(1));
+    }
+
+    #[test]
+    fn test_jitter_offset() {
+        let jitter = Delay::Seconds(15 * 60);
+        let a = jitter_offset("standup", jitter);
+        let b = jitter_offset("water", jitter);
+        assert_ne!(a, b);
+        assert_eq!(a, jitter_offset("standup", jitter));
+        assert_eq!(b, jitter_offset("water", jitter));
+        assert!((0..15 * 60).contains(&a));
+        assert!((0..15 * 60).contains(&b));
+        assert_eq!(jitter_offset("standup", Delay::Seconds(0)), 0);
+
+        let timestamp = at(2024, 1, 1, 12, 0);
+        let mut proc = repeat_at(timestamp, "cron 0 9 * * *");
+        proc.offset = a;
+        let (_, next) = proc.next_notification(&Calendar::default()).unwrap();
+        assert_eq!(next, at(2024, 1, 2, 9, 0) + TimeDelta::seconds(a));
+
+        // the offset does not add up when repeating
+        proc.timestamp = next.and_local_timezone(Local).unwrap();
+        let (_, next) = proc.next_notification(&Calendar::default()).unwrap();
+        assert_eq!(next, at(2024, 1, 3, 9, 0) + TimeDelta::seconds(a));
+    }
+
+    #[test]
+    fn test_merge() {
+        let now = Local::now().naive_local();
+        let file = |entries: &[(&str, &str)]| {
+            let mut data = ProcrastinationFileData::empty();
+            for (key, title) in entries {
+                let mut proc = repeat_at(now, "1h");
+                proc.title = title.to_string();
+                data.insert(key.to_string(), proc);
+            }
+            data
+        };
+        let title =
+            |data: &ProcrastinationFileData, key: &str| data.get(key).map(|p| p.title.clone());
+
+        let mut data = file(&[("a", "global a"), ("b", "global b"), ("b-2", "global b-2")]);
+        let conflicts = data.merge(
+            file(&[("b", "local b"), ("c", "local c")]),
+            OnConflict::Skip,
+        );
+        assert_eq!(conflicts, vec![("b".to_string(), "b".to_string())]);
+        assert_eq!(data.iter().count(), 4);
+        assert_eq!(title(&data, "b").unwrap(), "global b");
+        assert_eq!(title(&data, "c").unwrap(), "local c");
+
+        let mut data = file(&[("a", "global a"), ("b", "global b"), ("b-2", "global b-2")]);
+        let conflicts = data.merge(
+            file(&[("b", "local b"), ("c", "local c")]),
+            OnConflict::Overwrite,
+        );
+        assert_eq!(conflicts, vec![("b".to_string(), "b".to_string())]);
+        assert_eq!(data.iter().count(), 4);
+        assert_eq!(title(&data, "b").unwrap(), "local b");
+
+        let mut data = file(&[("a", "global a"), ("b", "global b"), ("b-2", "global b-2")]);
+        let conflicts = data.merge(
+            file(&[("b", "local b"), ("c", "local c")]),
+            OnConflict::Rename,
+        );
+        assert_eq!(conflicts, vec![("b".to_string(), "b-3".to_string())]);
+        assert_eq!(data.iter().count(), 5);
+        assert_eq!(title(&data, "b").unwrap(), "global b");
+        assert_eq!(title(&data, "b-2").unwrap(), "global b-2");
+        assert_eq!(title(&data, "b-3").unwrap(), "local b");
+    }
+
+    #[test]
+    fn test_start_after() {
+        let now = Local::now().naive_local().with_nanosecond(0).unwrap();
+        let start = now + TimeDelta::days(30);
+
+        let mut weekly = repeat_at(now - TimeDelta::days(8), "monday 9:00");
+        weekly.start_after = Some(start);
+        assert_eq!(
+            weekly.should_notify(&Calendar::default()).unwrap(),
+            NotificationType::None
+        );
+        let (_, next) = weekly.next_notification(&Calendar::default()).unwrap();
+        assert!(next >= start);
+        assert!(next < start + TimeDelta::days(7));
+        assert_eq!(next.weekday(), chrono::Weekday::Mon);
+        assert_eq!(next.time(), NaiveTime::from_hms_opt(9, 0, 0).unwrap());
+
+        let mut delay = repeat_at(now - TimeDelta::days(8), "1w");
+        delay.start_after = Some(start);
+        assert_eq!(
+            delay.should_notify(&Calendar::default()).unwrap(),
+            NotificationType::None
+        );
+        assert_eq!(
+            delay.next_notification(&Calendar::default()).unwrap().1,
+            start
+        );
+
+        let occurrences = delay
+            .occurrences_between(now, start + TimeDelta::days(14), &Calendar::default())
+            .unwrap();
+        // day based delays notify at midnight
+        let midnight: NaiveDateTime = start.date().into();
+        assert_eq!(
+            occurrences,
+            vec![
+                start,
+                midnight + TimeDelta::weeks(1),
+                midnight + TimeDelta::weeks(2)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_sleep_invalid_timing() {
+        let now = Local::now().naive_local();
+        let invalid = OnceTiming::Instant(RoughInstant::Month { month: 13 });
+        let err = Sleep::new(invalid, now, &Calendar::default()).unwrap_err();
+        assert_eq!(err.to_string(), "13 is not a valid month");
+    }
+
+    #[test]
+    fn test_sleep_stale_entry_to_monday() {
+        let now = Local::now().naive_local();
+        let mut proc = repeat_at(now - TimeDelta::days(30), "1y");
+
+        let monday = OnceTiming::Instant(RoughInstant::DayOfWeek { day: 0, time: None });
+        proc.sleep = Some(Sleep::new(monday, now, &Calendar::default()).unwrap());
+
+        let (typ, next) = proc.next_notification(&Calendar::default()).unwrap();
+        assert_eq!(typ, NotificationType::Sleep);
+        assert!(next > now);
+        assert!(next <= now + TimeDelta::days(7));
+        assert_eq!(next.weekday(), chrono::Weekday::Mon);
+        assert_eq!(
+            proc.should_notify(&Calendar::default()).unwrap(),
+            NotificationType::None
+        );
+    }
+
+    #[test]
+    fn test_notification_body() {
+        let mut proc = once_in(Local::now().naive_local(), 0);
+        assert_eq!(proc.notification_body(), None);
+
+        proc.message = "message".to_string();
+        assert_eq!(proc.notification_body(), Some("message"));
+
+        proc.no_body = true;
+        assert_eq!(proc.notification_body(), None);
+    }
+
+    #[test]
+    fn test_colored_display() {
+        let now = Local::now().naive_local();
+        let overdue = once_in(now, -60);
+
+        let plain = format!("{:#}", overdue.colored(false, &Calendar::default()));
+        assert!(!plain.contains('\x1b'));
+
+        let colored = format!("{:#}", overdue.colored(true, &Calendar::default()));
+        assert!(colored.starts_with(ANSI_RED));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_save_keeps_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(FILE_NAME);
+        std::fs::write(&path, "{}").unwrap();
+        std::fs::set_permissions(&path, Permissions::from_mode(0o600)).unwrap();
+
+        let mut file = ProcrastinationFile::open(&path).unwrap();
+        file.save().unwrap();
+        drop(file);
+
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+}
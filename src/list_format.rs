@@ -0,0 +1,382 @@
+//! Custom one-line formats for `procrastinate list --format`
+
+use std::{fmt::Write, str::FromStr};
+
+use chrono::{Local, NaiveDateTime};
+
+use crate::{
+    format_timestamp,
+    time::{format_relative, Calendar},
+    Priority, Procrastination, UpcomingTimestamp, ANSI_GREY, ANSI_RED, ANSI_RESET,
+};
+
+/// A value of a [Procrastination] that can be used as `{placeholder}` in a [ListFormat]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    Key,
+    Title,
+    Message,
+    Next,
+    Created,
+    Flags,
+}
+
+impl FromStr for Field {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "key" => Ok(Field::Key),
+            "title" => Ok(Field::Title),
+            "message" => Ok(Field::Message),
+            "next" => Ok(Field::Next),
+            "created" => Ok(Field::Created),
+            "flags" => Ok(Field::Flags),
+            _ => Err(format!(
+                "unknown placeholder '{{{s}}}', expected one of {{key}}, {{title}}, \
+                {{message}}, {{next}}, {{created}} or {{flags}}"
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    Literal(String),
+    Field(Field),
+}
+
+/// A format like `"{key}: {title} ({next})"`.
+///
+/// Use `{{` and `}}` for literal braces.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ListFormat {
+    parts: Vec<Part>,
+}
+
+impl FromStr for ListFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+        let mut chars = s.chars();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.as_str().starts_with('{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.as_str().starts_with('}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '{' => {
+                    let (name, rest) = chars
+                        .as_str()
+                        .split_once('}')
+                        .ok_or_else(|| "unclosed '{' in format".to_string())?;
+                    if !literal.is_empty() {
+                        parts.push(Part::Literal(std::mem::take(&mut literal)));
+                    }
+                    parts.push(Part::Field(name.parse()?));
+                    chars = rest.chars();
+                }
+                '}' => {
+                    return Err("unmatched '}' in format, use '}}' for a literal '}'".to_string())
+                }
+                c => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            parts.push(Part::Literal(literal));
+        }
+
+        Ok(Self { parts })
+    }
+}
+
+impl ListFormat {
+    /// Substitutes all placeholders with the values of `procrastination`.
+    pub fn render(
+        &self,
+        key: &str,
+        procrastination: &Procrastination,
+        us_date: bool,
+        calendar: &Calendar,
+    ) -> String {
+        let mut result = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Literal(literal) => result.push_str(literal),
+                Part::Field(Field::Key) => result.push_str(key),
+                Part::Field(Field::Title) => result.push_str(&procrastination.title),
+                Part::Field(Field::Message) => result.push_str(&procrastination.message),
+                Part::Field(Field::Next) => match procrastination.next_notification(calendar) {
+                    Ok((_, next)) => {
+                        let _ = write!(result, "{}", format_timestamp(next, us_date));
+                    }
+                    Err(e) => {
+                        log::error!("failed to get next notification time: {e:?}");
+                    }
+                },
+                Part::Field(Field::Created) => {
+                    let created = procrastination.timestamp.naive_local();
+                    let _ = write!(result, "{}", format_timestamp(created, us_date));
+                }
+                Part::Field(Field::Flags) => result.push_str(&procrastination.flags().join(", ")),
+            }
+        }
+        result
+    }
+}
+
+/// Formats each procrastination as `! key  next  title`, aligned in columns.
+///
+/// The line starts with the [urgency symbol](urgency_symbol), colored if `color` is set.
+/// Titles are truncated with an ellipsis so that no line is longer than `width`.
+/// If `relative` is set the next notification is shown relative to now, e.g. `in 2h`.
+pub fn compact(
+    entries: &[(&String, &Procrastination)],
+    us_date: bool,
+    relative: bool,
+    width: Option<usize>,
+    color: bool,
+    calendar: &Calendar,
+) -> Vec<String> {
+    let now = Local::now().naive_local();
+    let rows: Vec<_> = entries
+        .iter()
+        .map(|(key, procrastination)| {
+            let next = match procrastination.next_notification(calendar) {
+                Ok((_, next)) if relative => format_relative(next - now),
+                Ok((_, next)) => {
+                    UpcomingTimestamp(next, us_date, procrastination.notifies_by_date()).to_string()
+                }
+                Err(_) => "invalid".to_string(),
+            };
+            let title = procrastination.title.lines().next().unwrap_or_default();
+            (procrastination.priority, key.as_str(), next, title)
+        })
+        .collect();
+
+    let key_width = rows.iter().map(|(_, key, ..)| key.chars().count()).max();
+    let next_width = rows
+        .iter()
+        .map(|(_, _, next, _)| next.chars().count())
+        .max();
+    let (key_width, next_width) = (key_width.unwrap_or(0), next_width.unwrap_or(0));
+
+    rows.iter()
+        .map(|(priority, key, next, title)| {
+            let line = format!(" {key:key_width$}  {next:next_width$}  ");
+            // the symbol takes up one more character
+            let title_width = width.map(|width| width.saturating_sub(line.chars().count() + 1));
+            let symbol = match (color, urgency_color(*priority)) {
+                (true, Some(ansi)) => format!("{ansi}{}{ANSI_RESET}", urgency_symbol(*priority)),
+                _ => urgency_symbol(*priority).to_string(),
+            };
+            format!("{symbol}{line}{}", truncate(title, title_width))
+        })
+        .collect()
+}
+
+/// The symbol in front of a [compact] line: `!` for critical, `·` for low
+/// and a space for normal priority.
+pub fn urgency_symbol(priority: Priority) -> char {
+    match priority {
+        Priority::Low => '·',
+        Priority::Normal => ' ',
+        Priority::Critical => '!',
+    }
+}
+
+fn urgency_color(priority: Priority) -> Option<&'static str> {
+    match priority {
+        Priority::Low => Some(ANSI_GREY),
+        Priority::Normal => None,
+        Priority::Critical => Some(ANSI_RED),
+    }
+}
+
+/// Formats the soonest procrastination as a single line for status bars,
+/// e.g. `Title (in 2h)`. The line is empty if nothing is upcoming.
+///
+/// If `tooltip` is set a second line lists all procrastinations the same way,
+/// separated by `, `.
+pub fn bar(
+    entries: &[(&String, &Procrastination)],
+    now: NaiveDateTime,
+    tooltip: bool,
+    calendar: &Calendar,
+) -> String {
+    let mut upcoming: Vec<_> = entries
+        .iter()
+        .filter(|(_, procrastination)| procrastination.can_notify_in_future())
+        .filter_map(|(_, procrastination)| {
+            let (_, next) = procrastination.next_notification(calendar).ok()?;
+            let title = procrastination.title.lines().next().unwrap_or_default();
+            Some((next, title))
+        })
+        .collect();
+    upcoming.sort_by_key(|(next, _)| *next);
+    let item = |(next, title): &(NaiveDateTime, &str)| {
+        format!("{title} ({})", format_relative(*next - now))
+    };
+
+    let mut bar = upcoming.first().map(item).unwrap_or_default();
+    if tooltip {
+        bar.push('\n');
+        bar.push_str(&upcoming.iter().map(item).collect::<Vec<_>>().join(", "));
+    }
+    bar
+}
+
+/// Truncates `text` to at most `width` characters, ending in `…` if it was truncated.
+fn truncate(text: &str, width: Option<usize>) -> String {
+    match width {
+        Some(width) if text.chars().count() > width => {
+            let mut truncated: String = text.chars().take(width.saturating_sub(1)).collect();
+            if width > 0 {
+                truncated.push('…');
+            }
+            truncated
+        }
+        _ => text.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::{Local, NaiveDate};
+
+    use super::*;
+    use crate::time::Repeat;
+
+    fn procrastination() -> Procrastination {
+        let mut proc = Procrastination::new(
+            "Title".to_string(),
+            "message".to_string(),
+            Repeat::Repeat {
+                timing: "1h".parse().unwrap(),
+            },
+            true,
+        );
+        proc.timestamp = NaiveDate::from_ymd_opt(2020, 5, 17)
+            .unwrap()
+            .and_hms_opt(9, 30, 0)
+            .unwrap()
+            .and_local_timezone(Local)
+            .unwrap();
+        proc
+    }
+
+    fn render(format: &str) -> String {
+        format.parse::<ListFormat>().unwrap().render(
+            "key",
+            &procrastination(),
+            false,
+            &Calendar::default(),
+        )
+    }
+
+    #[test]
+    fn test_placeholders() {
+        assert_eq!(render("{key}: {title} - {message}"), "key: Title - message");
+        assert_eq!(render("{created}"), "17.05.2020 9:30");
+        assert_eq!(render("{next}"), "17.05.2020 10:30");
+        assert_eq!(render("[{flags}]"), "[repeating, sticky]");
+        assert_eq!(render("{{{key}}}"), "{key}");
+        assert_eq!(render("no placeholders"), "no placeholders");
+    }
+
+    #[test]
+    fn test_compact_alignment() {
+        let (short, long) = ("a".to_string(), "longer-key".to_string());
+        let mut other = procrastination();
+        other.title = "A very long title that does not fit".to_string();
+        let (first, second) = (procrastination(), other);
+        let entries = [(&short, &first), (&long, &second)];
+
+        let lines = compact(&entries, false, false, None, false, &Calendar::default());
+        assert_eq!(lines[0], "  a           now  Title");
+        assert_eq!(
+            lines[1],
+            "  longer-key  now  A very long title that does not fit"
+        );
+
+        let lines = compact(
+            &entries,
+            false,
+            false,
+            Some(29),
+            false,
+            &Calendar::default(),
+        );
+        assert_eq!(lines[0], "  a           now  Title");
+        assert_eq!(lines[1], "  longer-key  now  A very lo…");
+        assert_eq!(lines[1].chars().count(), 29);
+    }
+
+    #[test]
+    fn test_compact_urgency() {
+        let key = "k".to_string();
+        let line = |priority, color| {
+            let mut proc = procrastination();
+            proc.priority = priority;
+            compact(
+                &[(&key, &proc)],
+                false,
+                false,
+                None,
+                color,
+                &Calendar::default(),
+            )
+            .remove(0)
+        };
+
+        assert_eq!(line(Priority::Critical, false), "! k  now  Title");
+        assert_eq!(line(Priority::Normal, false), "  k  now  Title");
+        assert_eq!(line(Priority::Low, false), "· k  now  Title");
+        assert_eq!(
+            line(Priority::Critical, true),
+            format!("{ANSI_RED}!{ANSI_RESET} k  now  Title")
+        );
+        assert_eq!(line(Priority::Normal, true), "  k  now  Title");
+    }
+
+    #[test]
+    fn test_bar() {
+        let (a, b) = ("a".to_string(), "b".to_string());
+        let mut later = procrastination();
+        later.title = "Later\nsecond line".to_string();
+        later.timing = Repeat::Repeat {
+            timing: "3h".parse().unwrap(),
+        };
+        let (first, second) = (procrastination(), later);
+        let entries = [(&b, &second), (&a, &first)];
+        let now = NaiveDate::from_ymd_opt(2020, 5, 17)
+            .unwrap()
+            .and_hms_opt(8, 30, 0)
+            .unwrap();
+
+        assert_eq!(
+            bar(&entries, now, false, &Calendar::default()),
+            "Title (in 2h)"
+        );
+        assert_eq!(
+            bar(&entries, now, true, &Calendar::default()),
+            "Title (in 2h)\nTitle (in 2h), Later (in 4h)"
+        );
+        assert_eq!(bar(&[], now, false, &Calendar::default()), "");
+    }
+
+    #[test]
+    fn test_invalid_format() {
+        let err = "{key} {unknown}".parse::<ListFormat>().unwrap_err();
+        assert!(err.contains("unknown placeholder '{unknown}'"), "{err}");
+        assert!("{key".parse::<ListFormat>().is_err());
+        assert!("key}".parse::<ListFormat>().is_err());
+    }
+}
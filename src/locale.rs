@@ -0,0 +1,209 @@
+//! Localized names of weekdays and months for output
+
+use std::{fmt, str::FromStr};
+
+use crate::time::{DAYS_IN_WEEK, MONTHS};
+
+/// The language of weekday and month names in the output.
+///
+/// Input is always parsed in english.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Locale {
+    #[default]
+    English,
+    German,
+    French,
+    Spanish,
+}
+
+const LOCALES: [Locale; 4] = [
+    Locale::English,
+    Locale::German,
+    Locale::French,
+    Locale::Spanish,
+];
+
+const GERMAN_DAYS: [&str; 7] = [
+    "Montag",
+    "Dienstag",
+    "Mittwoch",
+    "Donnerstag",
+    "Freitag",
+    "Samstag",
+    "Sonntag",
+];
+const GERMAN_MONTHS: [&str; 12] = [
+    "Januar",
+    "Februar",
+    "März",
+    "April",
+    "Mai",
+    "Juni",
+    "Juli",
+    "August",
+    "September",
+    "Oktober",
+    "November",
+    "Dezember",
+];
+const FRENCH_DAYS: [&str; 7] = [
+    "lundi", "mardi", "mercredi", "jeudi", "vendredi", "samedi", "dimanche",
+];
+const FRENCH_MONTHS: [&str; 12] = [
+    "janvier",
+    "février",
+    "mars",
+    "avril",
+    "mai",
+    "juin",
+    "juillet",
+    "août",
+    "septembre",
+    "octobre",
+    "novembre",
+    "décembre",
+];
+const SPANISH_DAYS: [&str; 7] = [
+    "lunes",
+    "martes",
+    "miércoles",
+    "jueves",
+    "viernes",
+    "sábado",
+    "domingo",
+];
+const SPANISH_MONTHS: [&str; 12] = [
+    "enero",
+    "febrero",
+    "marzo",
+    "abril",
+    "mayo",
+    "junio",
+    "julio",
+    "agosto",
+    "septiembre",
+    "octubre",
+    "noviembre",
+    "diciembre",
+];
+
+impl Locale {
+    /// The locale of the environment, using `LC_ALL`, `LC_TIME` and `LANG` in that order.
+    ///
+    /// Defaults to english if none of them is set to a supported language.
+    pub fn from_env() -> Self {
+        ["LC_ALL", "LC_TIME", "LANG"]
+            .iter()
+            .filter_map(|var| std::env::var(var).ok())
+            .find(|value| !value.is_empty())
+            .and_then(|value| value.parse().ok())
+            .unwrap_or_default()
+    }
+
+    /// The name of the weekday `day` (Mon = 0), e.g. `monday`
+    pub fn weekday(self, day: u8) -> &'static str {
+        let days = match self {
+            Locale::English => &DAYS_IN_WEEK,
+            Locale::German => &GERMAN_DAYS,
+            Locale::French => &FRENCH_DAYS,
+            Locale::Spanish => &SPANISH_DAYS,
+        };
+        days.get(day as usize).copied().unwrap_or("unknown day")
+    }
+
+    /// The name of the `month` (january = 1), e.g. `january`
+    pub fn month(self, month: u8) -> &'static str {
+        let months = match self {
+            Locale::English => &MONTHS,
+            Locale::German => &GERMAN_MONTHS,
+            Locale::French => &FRENCH_MONTHS,
+            Locale::Spanish => &SPANISH_MONTHS,
+        };
+        (month as usize)
+            .checked_sub(1)
+            .and_then(|month| months.get(month))
+            .copied()
+            .unwrap_or("unknown month")
+    }
+
+    fn language_code(self) -> &'static str {
+        match self {
+            Locale::English => "en",
+            Locale::German => "de",
+            Locale::French => "fr",
+            Locale::Spanish => "es",
+        }
+    }
+}
+
+/// Parses a locale like `de`, `de_DE` or `de_DE.UTF-8`. `C` and `POSIX` are english.
+impl FromStr for Locale {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let language = s
+            .split(['_', '-', '.', '@'])
+            .next()
+            .unwrap_or_default()
+            .to_lowercase();
+        if language == "c" || language == "posix" {
+            return Ok(Locale::English);
+        }
+        LOCALES
+            .into_iter()
+            .find(|locale| locale.language_code() == language)
+            .ok_or_else(|| {
+                let supported: Vec<_> = LOCALES.iter().map(|l| l.language_code()).collect();
+                format!(
+                    "unsupported locale \"{s}\", supported are {}",
+                    supported.join(", ")
+                )
+            })
+    }
+}
+
+/// Values with weekday or month names in their description.
+///
+/// Their [Display](fmt::Display) implementation uses english names.
+pub trait LocalizedDisplay {
+    fn fmt_localized(&self, f: &mut fmt::Formatter<'_>, locale: Locale) -> fmt::Result;
+
+    /// Displays `self` with the names of `locale`
+    fn localized(&self, locale: Locale) -> Localized<'_, Self> {
+        Localized(self, locale)
+    }
+}
+
+/// Displays a value with the names of a locale, see [LocalizedDisplay::localized]
+pub struct Localized<'a, T: ?Sized>(&'a T, Locale);
+
+impl<T: LocalizedDisplay + ?Sized> fmt::Display for Localized<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt_localized(f, self.1)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_locale() {
+        assert_eq!("de_DE.UTF-8".parse(), Ok(Locale::German));
+        assert_eq!("fr".parse(), Ok(Locale::French));
+        assert_eq!("es-ES".parse(), Ok(Locale::Spanish));
+        assert_eq!("C".parse(), Ok(Locale::English));
+        assert_eq!("en_US.UTF-8".parse(), Ok(Locale::English));
+        assert!("xx_XX".parse::<Locale>().is_err());
+    }
+
+    #[test]
+    fn test_names() {
+        assert_eq!(Locale::English.weekday(6), "sunday");
+        assert_eq!(Locale::German.weekday(0), "Montag");
+        assert_eq!(Locale::French.weekday(2), "mercredi");
+        assert_eq!(Locale::German.month(3), "März");
+        assert_eq!(Locale::Spanish.month(12), "diciembre");
+        assert_eq!(Locale::English.month(13), "unknown month");
+    }
+}
@@ -1,9 +1,25 @@
+use std::{
+    error::Error as StdError,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use chrono::{Local, NaiveDateTime};
+use env_logger::Builder;
 use file_lock::{FileLock, FileOptions};
+use log::LevelFilter;
+use notify_rust::Notification;
 use procrastinate::{
-    procrastination_path, Error, ProcrastinationFile, ProcrastinationFileData, Sleep,
+    config::{config_path, Config},
+    crontab::parse_crontab,
+    expand_path, ics, in_group, json, list_format, procrastination_path,
+    time::{Calendar, Delay, Repeat, TimeError},
+    DesktopNotifications, Error, NotificationSink, OnConflict, Procrastination,
+    ProcrastinationFile, ProcrastinationFileData, Sleep, FILE_SCHEMA,
 };
+use regex::Regex;
 
-use crate::args::{Arguments, Cmd};
+use crate::args::{read_env_file, Arguments, Cmd, PausedFilter};
 
 pub mod args;
 
@@ -15,18 +31,298 @@ fn open_or_create(args: &Arguments) -> Result<ProcrastinationFile, Error> {
     if path.exists() {
         ProcrastinationFile::open(&path)
     } else {
-        let data = ProcrastinationFileData::empty();
-        let options = FileOptions::new().create_new(true).write(true);
-        let lock = FileLock::lock(&path, true, options)?;
-        Ok(ProcrastinationFile::new(data, lock))
+        create_or_open(&path)
+    }
+}
+
+/// The absolute path of the procrastination file, see [procrastination_path].
+fn resolved_path(local: bool, file: Option<&PathBuf>) -> std::io::Result<PathBuf> {
+    let path = procrastination_path(local, file)?;
+    match path.is_absolute() {
+        true => Ok(path),
+        false => Ok(std::env::current_dir()?.join(path)),
+    }
+}
+
+/// Creates a new empty procrastination file at `path`.
+///
+/// Opens the file instead, if another process created it in the meantime.
+fn create_or_open(path: &Path) -> Result<ProcrastinationFile, Error> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let data = ProcrastinationFileData::empty();
+    let options = FileOptions::new().create_new(true).write(true);
+    match FileLock::lock(path, true, options) {
+        Ok(lock) => Ok(ProcrastinationFile::new(data, lock, path)),
+        Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+            log::info!("procrastination file was created concurrently, opening it instead");
+            ProcrastinationFile::open(path)
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Removes all procrastinations with the given keys.
+///
+/// Missing keys are reported to `out` without stopping.
+fn done(
+    data: &mut ProcrastinationFileData,
+    keys: &[String],
+    mut out: impl Write,
+) -> std::io::Result<()> {
+    for key in keys {
+        if data.remove(key).is_none() {
+            writeln!(out, "No procrastination entry with key \"{key}\" exists")?;
+        }
+    }
+    Ok(())
+}
+
+/// Removes all procrastinations in `group` whose key matches `regex`.
+///
+/// The matching keys are only removed if `yes` is set, otherwise they are
+/// reported to `out` and an error is returned.
+fn done_matching(
+    data: &mut ProcrastinationFileData,
+    regex: &Regex,
+    group: Option<&str>,
+    yes: bool,
+    mut out: impl Write,
+) -> Result<(), Box<dyn StdError>> {
+    let mut keys: Vec<_> = data
+        .iter()
+        .map(|(key, _)| key)
+        .filter(|key| in_group(key, group) && regex.is_match(key))
+        .cloned()
+        .collect();
+    keys.sort();
+
+    if !yes {
+        for key in keys.iter() {
+            writeln!(out, "Would remove \"{key}\"")?;
+        }
+        return Err(format!(
+            "use --yes to remove the {} matching procrastinations",
+            keys.len()
+        )
+        .into());
+    }
+    for key in keys {
+        data.remove(&key);
+        writeln!(out, "Removed \"{key}\"")?;
+    }
+    Ok(())
+}
+
+/// Inserts `procrastination`, replacing any procrastination with the same key.
+///
+/// If both are repeating, the cadence continues from the last notification
+/// of the replaced procrastination unless `reset_timestamp` is set.
+fn replace(
+    data: &mut ProcrastinationFileData,
+    key: String,
+    mut procrastination: Procrastination,
+    reset_timestamp: bool,
+) {
+    if let Some(existing) = data.get(&key) {
+        let both_repeat = matches!(existing.timing, Repeat::Repeat { .. })
+            && matches!(procrastination.timing, Repeat::Repeat { .. });
+        if both_repeat && !reset_timestamp {
+            procrastination.timestamp = existing.timestamp;
+            procrastination.fire_count = existing.fire_count;
+        }
+    }
+    data.insert(key, procrastination);
+}
+
+/// Describes the schema version of the procrastination file at `path` with the given `content`.
+fn file_version(path: &Path, content: Option<&str>) -> String {
+    let schema = match content.map(ProcrastinationFileData::schema) {
+        None => "does not exist".to_string(),
+        Some(None) => "is empty".to_string(),
+        Some(Some(schema)) if schema < FILE_SCHEMA => {
+            format!("schema {schema}, will be upgraded to {FILE_SCHEMA} on the next change")
+        }
+        Some(Some(schema)) => format!("schema {schema}"),
+    };
+    format!("file {}: {schema}", path.display())
+}
+
+/// Writes the keys of `entries` to `out`, one per line.
+fn print_keys(entries: &[(&String, &Procrastination)], mut out: impl Write) -> std::io::Result<()> {
+    for (key, _) in entries {
+        writeln!(out, "{key}")?;
+    }
+    Ok(())
+}
+
+/// Reports the conflicting keys of [ProcrastinationFileData::merge].
+fn print_conflicts(conflicts: &[(String, String)], on_conflict: OnConflict) {
+    for (key, new_key) in conflicts {
+        match on_conflict {
+            OnConflict::Skip => println!("Skipped \"{key}\", it already exists"),
+            OnConflict::Overwrite => println!("Overwrote \"{key}\""),
+            OnConflict::Rename => println!("Renamed \"{key}\" to \"{new_key}\""),
+        }
+    }
+}
+
+/// Replaces all procrastinations of `file` with those of the procrastination file at `from`.
+///
+/// `file` is not changed if `from` can't be read, see [replace_data].
+fn replace_with_file(
+    file: &mut ProcrastinationFile,
+    from: &Path,
+    calendar: &Calendar,
+) -> Result<(), Box<dyn StdError>> {
+    let import = ProcrastinationFile::read(from)?;
+    replace_data(file, import, calendar)
+}
+
+/// Replaces all procrastinations of `file` with `import` and saves it.
+///
+/// `file` is not changed if any procrastination of `import` is invalid.
+fn replace_data(
+    file: &mut ProcrastinationFile,
+    import: ProcrastinationFileData,
+    calendar: &Calendar,
+) -> Result<(), Box<dyn StdError>> {
+    if let Some((key, err)) = import.validate(calendar).first() {
+        return Err(format!("not replacing the file, \"{key}\" is invalid: {err}").into());
+    }
+    *file.data_mut() = import;
+    file.save()?;
+    Ok(())
+}
+
+/// Wakes the procrastination `key` early, see [Procrastination::sleep].
+///
+/// Fails if it does not exist or is not sleeping.
+fn clear_sleep(data: &mut ProcrastinationFileData, key: &str) -> Result<(), String> {
+    let proc = data
+        .get_mut(key)
+        .ok_or_else(|| format!("No procrastination entry with key \"{key}\" exists"))?;
+    if proc.sleep.take().is_none() {
+        return Err(format!("\"{key}\" is not sleeping"));
+    }
+    Ok(())
+}
+
+/// Pauses or resumes the procrastination `key`, see [Procrastination::paused].
+///
+/// Fails if it does not exist or already is in that state.
+fn set_paused(data: &mut ProcrastinationFileData, key: &str, paused: bool) -> Result<(), String> {
+    let proc = data
+        .get_mut(key)
+        .ok_or_else(|| format!("No procrastination entry with key \"{key}\" exists"))?;
+    if proc.paused == paused {
+        return Err(match paused {
+            true => format!("\"{key}\" is already paused"),
+            false => format!("\"{key}\" is not paused"),
+        });
+    }
+    proc.paused = paused;
+    Ok(())
+}
+
+/// Saves `file` unless `cmd` only reads it, so that reading commands don't rotate backups.
+fn save_if_modified(file: &mut ProcrastinationFile, cmd: &Cmd) -> Result<(), Error> {
+    let read_only = matches!(
+        cmd,
+        Cmd::List { .. }
+            | Cmd::Agenda { .. }
+            | Cmd::Check
+            | Cmd::TestNotify
+            | Cmd::Version { .. }
+            | Cmd::PrintPath
+    );
+    if read_only {
+        return Ok(());
+    }
+    file.save()
+}
+
+/// Sends a single test notification using `sink`.
+fn test_notify(sink: &mut impl NotificationSink) -> Result<(), notify_rust::error::Error> {
+    let mut notification = Notification::new();
+    notification
+        .summary("procrastinate test notification")
+        .body("Notifications are working");
+    sink.show(&notification)
+}
+
+/// Reports all procrastinations with invalid timings to `out`.
+///
+/// Fails if any procrastination is invalid.
+fn check(
+    data: &ProcrastinationFileData,
+    calendar: &Calendar,
+    mut out: impl Write,
+) -> Result<(), Box<dyn StdError>> {
+    let invalid = data.validate(calendar);
+    for (key, err) in invalid.iter() {
+        writeln!(out, "{key}: {err}")?;
+    }
+    match invalid.len() {
+        0 => Ok(()),
+        1 => Err("1 procrastination is invalid".into()),
+        n => Err(format!("{n} procrastinations are invalid").into()),
+    }
+}
+
+/// The procrastinations to list, ordered by their next notification and key.
+fn list_entries<'a>(
+    data: &'a ProcrastinationFileData,
+    now: NaiveDateTime,
+    due_within: Option<Delay>,
+    group: Option<&str>,
+    paused: PausedFilter,
+    reverse: bool,
+    calendar: &Calendar,
+) -> Result<Vec<(&'a String, &'a Procrastination)>, TimeError> {
+    let mut entries = Vec::new();
+    for (key, proc) in data.iter_sorted_by_next(calendar)? {
+        if !in_group(key, group) || !paused.shows(proc.paused) {
+            continue;
+        }
+        if let Some(due_within) = due_within {
+            if !proc.is_due_within(now, due_within, calendar)? {
+                continue;
+            }
+        }
+        entries.push((key, proc));
+    }
+    if reverse {
+        entries.reverse();
+    }
+    Ok(entries)
+}
+
+fn init_logger(verbose: bool) {
+    let mut builder = Builder::new();
+    if verbose {
+        builder.filter_level(LevelFilter::Info);
+    } else {
+        builder.filter_level(LevelFilter::Error);
     }
+    builder.parse_default_env();
+    builder.init();
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     use clap::Parser;
-    #[allow(unused_mut)]
     let mut args = Arguments::parse();
+    if let Some(env_file) = args.env_file.as_ref() {
+        let env_file = read_env_file(&expand_path(env_file)?)?;
+        for ignored in args.apply_env_file(env_file) {
+            eprintln!("{ignored}");
+        }
+    }
+    init_logger(args.verbose);
     args.verify()?;
+    args.read_stdin_args(std::io::stdin())?;
 
     #[cfg(debug_assertions)]
     {
@@ -40,49 +336,688 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         println!("args: {args:?}");
     }
 
+    if let Cmd::PrintPath = args.cmd {
+        println!(
+            "{}",
+            resolved_path(args.local, args.file.as_ref())?.display()
+        );
+        return Ok(());
+    }
+
+    if let Cmd::TestNotify = args.cmd {
+        return match test_notify(&mut DesktopNotifications) {
+            Ok(()) => {
+                println!("Test notification sent");
+                Ok(())
+            }
+            Err(e) => Err(format!("Failed to send test notification: {e}").into()),
+        };
+    }
+
+    if let Cmd::Version { file } = args.cmd {
+        println!("procrastinate {}", env!("CARGO_PKG_VERSION"));
+        if file {
+            let path = procrastination_path(args.local, args.file.as_ref())?;
+            let content = match path.exists() {
+                true => Some(std::fs::read_to_string(&path)?),
+                false => None,
+            };
+            println!("{}", file_version(&path, content.as_deref()));
+        }
+        return Ok(());
+    }
+
+    let config = Config::load(&config_path())?;
+    let calendar = args.calendar(&config);
+
+    if args.notification_args().is_some_and(|args| args.explain) {
+        println!(
+            "{}",
+            args.procrastination(&config)?.explain(false, &calendar)?
+        );
+        return Ok(());
+    }
+
     let mut procrastination_file = open_or_create(&args)?;
+    procrastination_file.set_backups(match args.backup {
+        true => config.backups.max(1),
+        false => config.backups,
+    });
 
-    match args.cmd {
-        Cmd::Once { ref key, .. } | Cmd::Repeat { ref key, .. } => {
+    let cmd = args.cmd.clone();
+    match cmd.clone() {
+        Cmd::Once { ref key, .. } => {
             procrastination_file
                 .data_mut()
-                .insert(key.clone(), args.procrastination());
+                .insert(args.key(key), args.procrastination(&config)?);
         }
-        Cmd::Done { ref key } => {
-            procrastination_file.data_mut().remove(key);
+        Cmd::Repeat {
+            ref key,
+            reset_timestamp,
+            ..
+        } => {
+            replace(
+                procrastination_file.data_mut(),
+                args.key(key),
+                args.procrastination(&config)?,
+                reset_timestamp,
+            );
+        }
+        Cmd::Done {
+            regex: Some(ref regex),
+            yes,
+            ..
+        } => {
+            done_matching(
+                procrastination_file.data_mut(),
+                regex,
+                args.group.as_deref(),
+                yes,
+                std::io::stdout(),
+            )?;
+        }
+        Cmd::Done {
+            ref keys,
+            regex: None,
+            ..
+        } => {
+            let keys: Vec<_> = keys.iter().map(|key| args.key(key)).collect();
+            done(procrastination_file.data_mut(), &keys, std::io::stdout())?;
+        }
+        Cmd::Check => {
+            check(procrastination_file.data(), &calendar, std::io::stdout())?;
+        }
+        Cmd::TestNotify => unreachable!("test-notify does not use the procrastination file"),
+        Cmd::Version { .. } => unreachable!("version does not use the procrastination file"),
+        Cmd::PrintPath => unreachable!("print-path does not open the procrastination file"),
+        Cmd::Merge {
+            ref from,
+            on_conflict,
+            replace_file,
+        } => {
+            let from = expand_path(from)?;
+            let path = procrastination_path(args.local, args.file.as_ref())?;
+            // opening the same file twice would block on the file lock
+            if from.canonicalize()? == path.canonicalize()? {
+                return Err("can't merge a file into itself".into());
+            }
+            if replace_file {
+                return replace_with_file(&mut procrastination_file, &from, &calendar);
+            }
+            let other = ProcrastinationFile::open(&from)?.into_data();
+            let conflicts = procrastination_file.data_mut().merge(other, on_conflict);
+            print_conflicts(&conflicts, on_conflict);
+        }
+        Cmd::Import {
+            ref crontab,
+            on_conflict,
+            replace_file,
+        } => {
+            let content = std::fs::read_to_string(expand_path(crontab)?)?;
+            let import = parse_crontab(&content);
+            for warning in import.warnings.iter() {
+                eprintln!("{warning}");
+            }
+            let mut data = ProcrastinationFileData::empty();
+            for (key, procrastination) in import.entries {
+                data.insert(args.key(&key), procrastination);
+            }
+            if replace_file {
+                return replace_data(&mut procrastination_file, data, &calendar);
+            }
+            let conflicts = procrastination_file.data_mut().merge(data, on_conflict);
+            print_conflicts(&conflicts, on_conflict);
         }
         Cmd::List {
             debug,
             ron,
             us_date,
+            due_within,
+            include_paused,
+            paused_only,
+            color,
+            reverse,
+            format,
+            compact,
+            width,
+            json,
+            flatten_timing,
+            relative,
+            key_only,
+            bar,
+            tooltip,
+            agenda_ics,
+            days,
         } => {
-            for proc in procrastination_file.data().iter() {
-                if ron {
-                    if debug {
-                        eprintln!("ron option is overwritting the debug print option");
-                    }
-                    println!("{}", procrastination_file.ron().expect("Failed to serialize procrastination file into ron format. This should never happen"));
-                } else if debug {
-                    println!("{}: {:#?}", proc.0, proc.1);
-                } else {
-                    if us_date {
-                        println!("{}: {:-#}", proc.0, proc.1);
+            let color = color.enabled();
+            let now = Local::now().naive_local();
+            let entries = list_entries(
+                procrastination_file.data(),
+                now,
+                due_within,
+                args.group.as_deref(),
+                PausedFilter::new(include_paused, paused_only),
+                reverse,
+                &calendar,
+            )?;
+            if agenda_ics {
+                let mut agenda = procrastination_file.data().agenda(now, days, &calendar)?;
+                for day in agenda.iter_mut() {
+                    day.entries
+                        .retain(|(_, key, _)| in_group(key, args.group.as_deref()));
+                }
+                print!("{}", ics::agenda(&agenda, now));
+            } else if bar {
+                println!("{}", list_format::bar(&entries, now, tooltip, &calendar));
+            } else if key_only {
+                print_keys(&entries, std::io::stdout())?;
+            } else if json {
+                println!("{}", json::list(&entries, flatten_timing, &calendar));
+            } else if compact {
+                for line in
+                    list_format::compact(&entries, us_date, relative, width, color, &calendar)
+                {
+                    println!("{line}");
+                }
+            } else {
+                for proc in entries {
+                    if let Some(format) = format.as_ref() {
+                        println!("{}", format.render(proc.0, proc.1, us_date, &calendar));
+                    } else if ron {
+                        if debug {
+                            eprintln!("ron option is overwritting the debug print option");
+                        }
+                        println!("{}", procrastination_file.ron().expect("Failed to serialize procrastination file into ron format. This should never happen"));
+                    } else if debug {
+                        println!("{}: {:#?}", proc.0, proc.1);
+                    } else if us_date {
+                        println!(
+                            "{}: {:-#}",
+                            proc.0,
+                            proc.1.colored(color, &calendar).relative(relative)
+                        );
                     } else {
-                        println!("{}: {:#}", proc.0, proc.1);
+                        println!(
+                            "{}: {:#}",
+                            proc.0,
+                            proc.1.colored(color, &calendar).relative(relative)
+                        );
                     }
                 }
             }
         }
-        Cmd::Sleep { ref key, timing } => {
-            if let Some(proc) = procrastination_file.data_mut().get_mut(key) {
-                proc.sleep = Some(Sleep { timing });
+        Cmd::Agenda { days, us_date } => {
+            let now = Local::now().naive_local();
+            for day in procrastination_file.data().agenda(now, days, &calendar)? {
+                if day.entries.is_empty() {
+                    continue;
+                }
+                if us_date {
+                    println!("{day:-}");
+                } else {
+                    println!("{day}");
+                }
+            }
+        }
+        Cmd::Sleep {
+            ref key,
+            clear: true,
+            ..
+        } => {
+            clear_sleep(procrastination_file.data_mut(), &args.key(key))?;
+        }
+        Cmd::Sleep {
+            ref key,
+            timing: Some(ref timing),
+            ..
+        } => {
+            let key = args.key(key);
+            if let Some(proc) = procrastination_file.data_mut().get_mut(&key) {
+                let sleep = Sleep::new(
+                    timing.timing(args.delay_precision())?,
+                    Local::now().naive_local(),
+                    &calendar,
+                )
+                .map_err(|e| format!("invalid sleep timing: {e}"))?;
+                proc.sleep = Some(sleep);
             } else {
                 println!("No procrastination entry with key \"{key}\" exists");
             }
         }
+        Cmd::Sleep { timing: None, .. } => unreachable!("timing is required without --clear"),
+        Cmd::Pause { ref key } => {
+            set_paused(procrastination_file.data_mut(), &args.key(key), true)?;
+        }
+        Cmd::Resume { ref key } => {
+            set_paused(procrastination_file.data_mut(), &args.key(key), false)?;
+        }
     };
 
-    procrastination_file.save()?;
+    save_if_modified(&mut procrastination_file, &cmd)?;
 
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use procrastinate::{
+        time::{OnceTiming, Repeat, RoughInstant},
+        FileFormat, RecordingSink,
+    };
+
+    use super::*;
+
+    fn data(entries: &[(&str, &str)]) -> ProcrastinationFileData {
+        let mut data = ProcrastinationFileData::empty();
+        let timestamp = Local::now();
+        for (key, timing) in entries {
+            let timing = Repeat::Once {
+                timing: timing.parse().unwrap(),
+            };
+            let mut proc = Procrastination::new(key.to_string(), String::new(), timing, false);
+            proc.timestamp = timestamp;
+            data.insert(key.to_string(), proc);
+        }
+        data
+    }
+
+    #[test]
+    fn test_test_notify() {
+        let mut sink = RecordingSink::default();
+        test_notify(&mut sink).unwrap();
+        assert_eq!(sink.0.len(), 1);
+        assert_eq!(sink.0[0].summary, "procrastinate test notification");
+    }
+
+    #[test]
+    fn test_create_or_open_existing_file() {
+        let dir = tempfile::tempdir().unwrap();
+
+        // another process created the file after the existence check
+        let path = dir.path().join("created.ron");
+        let mut other = create_or_open(&path).unwrap();
+        other.data_mut().insert(
+            "key".to_string(),
+            data(&[("key", "5m")]).remove("key").unwrap(),
+        );
+        other.save().unwrap();
+        drop(other);
+
+        let file = create_or_open(&path).unwrap();
+        assert!(file.data().get("key").is_some());
+        drop(file);
+
+        // the other process did not write the file yet
+        let path = dir.path().join("empty.ron");
+        std::fs::File::create(&path).unwrap();
+        let file = create_or_open(&path).unwrap();
+        assert_eq!(file.data().iter().count(), 0);
+        drop(file);
+
+        // first run with a data dir that does not exist yet
+        let path = dir.path().join("missing").join("procrastination.ron");
+        let file = create_or_open(&path).unwrap();
+        assert_eq!(file.data().iter().count(), 0);
+        drop(file);
+    }
+
+    #[test]
+    fn test_replace_file() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let path = dir.path().join("target.ron");
+        let mut file = create_or_open(&path).unwrap();
+        *file.data_mut() = data(&[("original", "5m")]);
+        file.save().unwrap();
+        let original = std::fs::read_to_string(&path).unwrap();
+
+        let broken = dir.path().join("broken.ron");
+        std::fs::write(&broken, "(procrastinations: {\"cut off").unwrap();
+        assert!(replace_with_file(&mut file, &broken, &Calendar::default()).is_err());
+        assert!(file.data().get("original").is_some());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), original);
+
+        let backup = dir.path().join("backup.ron");
+        let mut backup_file = create_or_open(&backup).unwrap();
+        *backup_file.data_mut() = data(&[("restored", "1h")]);
+        backup_file.save().unwrap();
+        drop(backup_file);
+
+        replace_with_file(&mut file, &backup, &Calendar::default()).unwrap();
+        drop(file);
+        let replaced = ProcrastinationFile::read(&path).unwrap();
+        assert!(replaced.get("original").is_none());
+        assert!(replaced.get("restored").is_some());
+    }
+
+    #[test]
+    fn test_list_keeps_backups() {
+        use clap::Parser;
+        let cmd = |args: &[&str]| {
+            Arguments::try_parse_from(std::iter::once("procrastinate").chain(args.iter().copied()))
+                .unwrap()
+                .cmd
+        };
+        let dir = tempfile::tempdir().unwrap();
+
+        let path = dir.path().join("procrastination.ron");
+        let bak = dir.path().join("procrastination.ron.bak");
+        let mut file = create_or_open(&path).unwrap();
+        file.set_backups(1);
+        *file.data_mut() = data(&[("key", "5m")]);
+        file.save().unwrap();
+        std::fs::write(&bak, "history").unwrap();
+
+        for args in [&["list"][..], &["check"], &["agenda"]] {
+            save_if_modified(&mut file, &cmd(args)).unwrap();
+            assert_eq!(
+                std::fs::read_to_string(&bak).unwrap(),
+                "history",
+                "{args:?}"
+            );
+        }
+        save_if_modified(&mut file, &cmd(&["done", "key"])).unwrap();
+        assert_ne!(std::fs::read_to_string(&bak).unwrap(), "history");
+    }
+
+    #[test]
+    fn test_clear_sleep() {
+        let mut data = data(&[("key", "1h")]);
+        let original = data
+            .get("key")
+            .unwrap()
+            .next_notification(&Calendar::default())
+            .unwrap();
+
+        let soon: OnceTiming = "10m".parse().unwrap();
+        let sleep = Sleep::new(soon, Local::now().naive_local(), &Calendar::default()).unwrap();
+        data.get_mut("key").unwrap().sleep = Some(sleep);
+        assert_ne!(
+            data.get("key")
+                .unwrap()
+                .next_notification(&Calendar::default())
+                .unwrap(),
+            original
+        );
+
+        clear_sleep(&mut data, "key").unwrap();
+        assert_eq!(
+            data.get("key")
+                .unwrap()
+                .next_notification(&Calendar::default())
+                .unwrap(),
+            original
+        );
+
+        assert!(clear_sleep(&mut data, "key").is_err());
+        assert!(clear_sleep(&mut data, "missing").is_err());
+    }
+
+    #[test]
+    fn test_done_multiple_keys() {
+        let mut data = data(&[("a", "5m"), ("b", "5m"), ("c", "5m")]);
+
+        let keys = ["a".to_string(), "missing".to_string(), "c".to_string()];
+        let mut out = Vec::new();
+        done(&mut data, &keys, &mut out).unwrap();
+
+        assert!(data.get("a").is_none());
+        assert!(data.get("b").is_some());
+        assert!(data.get("c").is_none());
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "No procrastination entry with key \"missing\" exists\n"
+        );
+    }
+
+    #[test]
+    fn test_check_invalid() {
+        let mut data = data(&[("valid", "5m")]);
+        let mut out = Vec::new();
+        assert!(check(&data, &Calendar::default(), &mut out).is_ok());
+        assert!(out.is_empty());
+
+        let timing = Repeat::Once {
+            timing: OnceTiming::Instant(RoughInstant::Month { month: 13 }),
+        };
+        let invalid = Procrastination::new("invalid".to_string(), String::new(), timing, false);
+        data.insert("invalid".to_string(), invalid);
+
+        let err = check(&data, &Calendar::default(), &mut out).unwrap_err();
+        assert_eq!(err.to_string(), "1 procrastination is invalid");
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "invalid: 13 is not a valid month\n"
+        );
+    }
+
+    #[test]
+    fn test_list_entries_order() {
+        let data = data(&[("b", "5m"), ("c", "1m"), ("a", "5m")]);
+        let now = Local::now().naive_local();
+        let keys = |reverse| {
+            list_entries(
+                &data,
+                now,
+                None,
+                None,
+                PausedFilter::Hide,
+                reverse,
+                &Calendar::default(),
+            )
+            .unwrap()
+            .into_iter()
+            .map(|(key, _)| key.as_str())
+            .collect::<Vec<_>>()
+        };
+
+        assert_eq!(keys(false), ["c", "a", "b"]);
+        assert_eq!(keys(true), ["b", "a", "c"]);
+    }
+
+    #[test]
+    fn test_done_matching() {
+        let mut data = data(&[("temp-a", "5m"), ("temp-b", "5m"), ("keep-temp", "5m")]);
+        let regex = Regex::new("^temp-").unwrap();
+
+        let mut out = Vec::new();
+        assert!(done_matching(&mut data, &regex, None, false, &mut out).is_err());
+        assert_eq!(data.iter().count(), 3);
+
+        let mut out = Vec::new();
+        done_matching(&mut data, &regex, None, true, &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "Removed \"temp-a\"\nRemoved \"temp-b\"\n"
+        );
+        let keys: Vec<_> = data.iter().map(|(key, _)| key.as_str()).collect();
+        assert_eq!(keys, ["keep-temp"]);
+    }
+
+    #[test]
+    fn test_replace_reset_timestamp() {
+        let now = Local::now();
+        let last = now - chrono::TimeDelta::minutes(30);
+        let repeat = |timing: &str| {
+            let timing = Repeat::Repeat {
+                timing: timing.parse().unwrap(),
+            };
+            Procrastination::new("title".to_string(), String::new(), timing, false)
+        };
+        let mut data = ProcrastinationFileData::empty();
+        let mut existing = repeat("1h");
+        existing.timestamp = last;
+        data.insert("key".to_string(), existing);
+
+        replace(&mut data, "key".to_string(), repeat("2h"), false);
+        let (_, next) = data
+            .get("key")
+            .unwrap()
+            .next_notification(&Calendar::default())
+            .unwrap();
+        assert_eq!(next, (last + chrono::TimeDelta::hours(2)).naive_local());
+
+        let new = repeat("2h");
+        let created = new.timestamp;
+        replace(&mut data, "key".to_string(), new, true);
+        let (_, next) = data
+            .get("key")
+            .unwrap()
+            .next_notification(&Calendar::default())
+            .unwrap();
+        assert_eq!(next, (created + chrono::TimeDelta::hours(2)).naive_local());
+        assert!(next > (now + chrono::TimeDelta::minutes(110)).naive_local());
+    }
+
+    #[test]
+    fn test_resolved_path() {
+        let current_dir = std::env::current_dir().unwrap();
+        assert_eq!(
+            resolved_path(true, None).unwrap(),
+            current_dir.join(procrastinate::FILE_NAME)
+        );
+
+        let file = PathBuf::from("todo/work.ron");
+        assert_eq!(
+            resolved_path(false, Some(&file)).unwrap(),
+            current_dir.join("todo/work.ron")
+        );
+        let file = PathBuf::from("/tmp/work.ron");
+        assert_eq!(resolved_path(false, Some(&file)).unwrap(), file);
+    }
+
+    #[test]
+    fn test_file_version() {
+        let path = Path::new("procrastination.ron");
+        let legacy = r#"{"k": (title: "t", message: "", timing: Repeat(timing: Delay(Seconds(60))), timestamp: "2024-01-01T12:00:00+01:00")}"#;
+        let mut current = Vec::new();
+        ProcrastinationFileData::from_ron(legacy)
+            .unwrap()
+            .to_writer(&mut current, FileFormat::Ron)
+            .unwrap();
+        let current = String::from_utf8(current).unwrap();
+
+        assert_eq!(
+            file_version(path, Some(legacy)),
+            "file procrastination.ron: schema 1, will be upgraded to 2 on the next change"
+        );
+        assert_eq!(
+            file_version(path, Some(&current)),
+            "file procrastination.ron: schema 2"
+        );
+        assert_eq!(
+            file_version(path, Some("")),
+            "file procrastination.ron: is empty"
+        );
+        assert_eq!(
+            file_version(path, None),
+            "file procrastination.ron: does not exist"
+        );
+    }
+
+    #[test]
+    fn test_print_keys() {
+        let data = data(&[("b", "5m"), ("c", "1m"), ("a", "5m")]);
+        let now = Local::now().naive_local();
+        let entries = list_entries(
+            &data,
+            now,
+            None,
+            None,
+            PausedFilter::Hide,
+            false,
+            &Calendar::default(),
+        )
+        .unwrap();
+
+        let mut out = Vec::new();
+        print_keys(&entries, &mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "c\na\nb\n");
+    }
+
+    #[test]
+    fn test_list_entries_group() {
+        let data = data(&[
+            ("work/standup", "5m"),
+            ("home/dishes", "1m"),
+            ("standup", "1m"),
+        ]);
+        let now = Local::now().naive_local();
+        let keys: Vec<_> = list_entries(
+            &data,
+            now,
+            None,
+            Some("work"),
+            PausedFilter::Hide,
+            false,
+            &Calendar::default(),
+        )
+        .unwrap()
+        .into_iter()
+        .map(|(key, _)| key.as_str())
+        .collect();
+
+        assert_eq!(keys, ["work/standup"]);
+    }
+
+    #[test]
+    fn test_list_entries_paused() {
+        let mut data = data(&[("active", "5m"), ("paused", "1m")]);
+        set_paused(&mut data, "paused", true).unwrap();
+        let now = Local::now().naive_local();
+        let keys = |paused| {
+            list_entries(&data, now, None, None, paused, false, &Calendar::default())
+                .unwrap()
+                .into_iter()
+                .map(|(key, _)| key.as_str())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(keys(PausedFilter::Hide), ["active"]);
+        assert_eq!(keys(PausedFilter::Include), ["paused", "active"]);
+        assert_eq!(keys(PausedFilter::Only), ["paused"]);
+    }
+
+    #[test]
+    fn test_set_paused() {
+        let mut data = data(&[("key", "5m")]);
+        assert_eq!(
+            set_paused(&mut data, "key", false).unwrap_err(),
+            "\"key\" is not paused"
+        );
+        set_paused(&mut data, "key", true).unwrap();
+        assert!(data.get("key").unwrap().paused);
+        assert_eq!(
+            set_paused(&mut data, "key", true).unwrap_err(),
+            "\"key\" is already paused"
+        );
+        set_paused(&mut data, "key", false).unwrap();
+        assert!(!data.get("key").unwrap().paused);
+        assert!(set_paused(&mut data, "missing", true).is_err());
+    }
+
+    #[test]
+    fn test_paused_filter_args() {
+        use clap::Parser;
+        let filter = |args: &[&str]| {
+            let args =
+                Arguments::try_parse_from(["procrastinate", "list"].iter().chain(args)).ok()?;
+            match args.cmd {
+                Cmd::List {
+                    include_paused,
+                    paused_only,
+                    ..
+                } => Some(PausedFilter::new(include_paused, paused_only)),
+                _ => unreachable!(),
+            }
+        };
+
+        assert_eq!(filter(&[]).unwrap(), PausedFilter::Hide);
+        assert_eq!(
+            filter(&["--include-paused"]).unwrap(),
+            PausedFilter::Include
+        );
+        assert_eq!(filter(&["--paused-only"]).unwrap(), PausedFilter::Only);
+        assert!(filter(&["--include-paused", "--paused-only"]).is_none());
+    }
+}
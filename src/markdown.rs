@@ -0,0 +1,103 @@
+/// Converts simple markdown into the html subset supported by notification servers.
+///
+/// Supported are `**bold**`, `__bold__`, `*italic*`, `_italic_` and `[links](url)`.
+/// Everything else is escaped and kept as is.
+pub fn markdown_to_html(markdown: &str) -> String {
+    let mut html = String::with_capacity(markdown.len());
+    render(markdown, &mut html);
+    html
+}
+
+fn render(markdown: &str, html: &mut String) {
+    let mut rest = markdown;
+    let mut prev: Option<char> = None;
+
+    while let Some(c) = rest.chars().next() {
+        // `_` within words, e.g. snake_case, is not emphasis
+        let can_open = c != '_' || !prev.is_some_and(|p| p.is_alphanumeric());
+
+        if let Some(remaining) = can_open
+            .then(|| emphasis(rest, html))
+            .flatten()
+            .or_else(|| link(rest, html))
+        {
+            prev = markdown[..markdown.len() - remaining.len()].chars().last();
+            rest = remaining;
+            continue;
+        }
+
+        escape(c, html);
+        prev = Some(c);
+        rest = &rest[c.len_utf8()..];
+    }
+}
+
+/// Renders bold or italic text at the start of `input`. Returns the remaining input.
+fn emphasis<'a>(input: &'a str, html: &mut String) -> Option<&'a str> {
+    for (delim, tag) in [("**", "b"), ("__", "b"), ("*", "i"), ("_", "i")] {
+        let Some(inner) = input.strip_prefix(delim) else {
+            continue;
+        };
+        let end = inner.find(delim)?;
+        if end == 0 {
+            return None;
+        }
+        html.push_str(&format!("<{tag}>"));
+        render(&inner[..end], html);
+        html.push_str(&format!("</{tag}>"));
+        return Some(&inner[end + delim.len()..]);
+    }
+    None
+}
+
+/// Renders a link at the start of `input`. Returns the remaining input.
+fn link<'a>(input: &'a str, html: &mut String) -> Option<&'a str> {
+    let inner = input.strip_prefix('[')?;
+    let (text, rest) = inner.split_once("](")?;
+    let (url, rest) = rest.split_once(')')?;
+
+    html.push_str("<a href=\"");
+    url.chars().for_each(|c| escape(c, html));
+    html.push_str("\">");
+    render(text, html);
+    html.push_str("</a>");
+    Some(rest)
+}
+
+fn escape(c: char, html: &mut String) {
+    match c {
+        '&' => html.push_str("&amp;"),
+        '<' => html.push_str("&lt;"),
+        '>' => html.push_str("&gt;"),
+        '"' => html.push_str("&quot;"),
+        c => html.push(c),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_bold_italic() {
+        assert_eq!(markdown_to_html("**bold**"), "<b>bold</b>");
+        assert_eq!(markdown_to_html("__bold__"), "<b>bold</b>");
+        assert_eq!(markdown_to_html("*italic*"), "<i>italic</i>");
+        assert_eq!(markdown_to_html("_italic_"), "<i>italic</i>");
+        assert_eq!(
+            markdown_to_html("a **bold _and italic_** text"),
+            "a <b>bold <i>and italic</i></b> text"
+        );
+        assert_eq!(markdown_to_html("2 * 3 = 6"), "2 * 3 = 6");
+        assert_eq!(markdown_to_html("snake_case_name"), "snake_case_name");
+    }
+
+    #[test]
+    fn test_link_and_escape() {
+        assert_eq!(
+            markdown_to_html("see [the docs](https://example.com?a=1&b=2)"),
+            "see <a href=\"https://example.com?a=1&amp;b=2\">the docs</a>"
+        );
+        assert_eq!(markdown_to_html("a < b & c"), "a &lt; b &amp; c");
+    }
+}
@@ -1,15 +1,22 @@
 use std::str::FromStr;
 
-use chrono::{Datelike, Days, Local, NaiveDate, NaiveDateTime, NaiveTime, Weekday};
+use chrono::{
+    DateTime, Datelike, Days, Local, Months, NaiveDate, NaiveDateTime, NaiveTime, TimeDelta,
+    Timelike, Weekday,
+};
 use nom::{branch::alt, IResult};
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
-use crate::nom_ext::consume_all;
+use crate::{
+    locale::{Locale, LocalizedDisplay},
+    nom_ext::consume_all,
+};
 
-use self::parsing::{parse_duration, parse_rough_instant};
+use self::parsing::{parse_duration_with, parse_exact_instant, parse_rough_instant};
 
 pub mod parsing;
+pub mod sun;
 
 const SECONDS_IN_HOUR: u64 = 60 * 60;
 const SECONDS_IN_DAY: u64 = SECONDS_IN_HOUR * 24;
@@ -58,12 +65,380 @@ pub enum OnceTiming {
 pub enum RepeatTiming {
     Exact(RepeatExact),
     Delay(Delay),
+    /// A cron expression, see [cron_schedule]
+    Cron(String),
+    /// A [Delay] aligned to a time of day
+    AnchoredDelay {
+        delay: Delay,
+        at: NaiveTime,
+    },
+    /// A [Delay] that always notifies on a boundary of `align`, e.g. on the hour
+    AlignedDelay {
+        delay: Delay,
+        align: AlignUnit,
+    },
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+/// A delay of either seconds, whole days, weeks or calendar months.
+///
+/// Delays of seconds notify after their duration, all other delays notify at midnight.
+/// Weeks keep the unit they were entered with. Months and years are [Delay::Calendar]
+/// months, unless they were parsed with [DelayPrecision::Approximate].
+/// Delays are only equal if they notify at the same time, use [Delay::same_duration]
+/// to compare their duration, e.g. `1d` and `86400s`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Delay {
     Seconds(i64),
     Days(i64),
+    Weeks(i64),
+    /// Calendar months followed by days and seconds, see [DelayPrecision::Calendar].
+    ///
+    /// `1M` after the 15th notifies on the 15th of the next month. Years are 12 months.
+    /// Notifies at midnight if `seconds` is 0, like [Delay::Days].
+    Calendar {
+        months: u32,
+        days: i64,
+        seconds: i64,
+    },
+}
+
+impl Delay {
+    /// The duration of this delay.
+    ///
+    /// Months are approximated with 30 days and years with 365 days.
+    pub fn to_duration(&self) -> TimeDelta {
+        match *self {
+            Delay::Seconds(secs) => TimeDelta::seconds(secs),
+            Delay::Days(_) | Delay::Weeks(_) => {
+                TimeDelta::days(self.whole_days().unwrap_or_default())
+            }
+            Delay::Calendar {
+                months,
+                days,
+                seconds,
+            } => TimeDelta::days(i64::from(months) * 30 + days) + TimeDelta::seconds(seconds),
+        }
+    }
+
+    /// The canonical representation of this delay.
+    ///
+    /// Days stay days and seconds stay seconds, as they notify at different times of day.
+    /// A delay of 0 days is the same as 0 seconds.
+    /// Calendar delays without months are days or seconds.
+    pub fn normalize(self) -> Delay {
+        match self {
+            Delay::Days(0) | Delay::Weeks(0) => Delay::Seconds(0),
+            Delay::Calendar {
+                months: 0,
+                days,
+                seconds: 0,
+            } => Delay::Days(days).normalize(),
+            Delay::Calendar {
+                months: 0,
+                days,
+                seconds,
+            } => Delay::Seconds(days * SECONDS_IN_DAY as i64 + seconds),
+            delay => delay,
+        }
+    }
+
+    /// Whether both delays last equally long, see [Delay::to_duration]
+    pub fn same_duration(&self, other: &Delay) -> bool {
+        self.to_duration() == other.to_duration()
+    }
+
+    /// Compares the duration of both delays, see [Delay::to_duration]
+    pub fn cmp_duration(&self, other: &Delay) -> std::cmp::Ordering {
+        self.to_duration().cmp(&other.to_duration())
+    }
+
+    /// The days of a delay in days or weeks, `None` for seconds and months
+    pub fn whole_days(self) -> Option<i64> {
+        match self {
+            Delay::Days(days) => Some(days),
+            Delay::Weeks(weeks) => Some(weeks * 7),
+            Delay::Seconds(_) | Delay::Calendar { .. } => None,
+        }
+    }
+
+    /// Whether this delay notifies at midnight instead of at the time of day it started
+    pub fn notifies_at_midnight(self) -> bool {
+        match self {
+            Delay::Seconds(_) => false,
+            Delay::Days(_) | Delay::Weeks(_) => true,
+            Delay::Calendar { seconds, .. } => seconds == 0,
+        }
+    }
+}
+
+/// Formats the delay the same way it is parsed, e.g. `1h 30min`
+impl std::fmt::Display for Delay {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            Delay::Days(days) => write!(f, "{days}d"),
+            Delay::Weeks(weeks) => write!(f, "{weeks}w"),
+            Delay::Calendar {
+                months,
+                days,
+                seconds,
+            } => {
+                let (years, months) = (months / 12, months % 12);
+                if years != 0 {
+                    write!(f, "{years}y")?;
+                }
+                match (years, months) {
+                    (0, months) => write!(f, "{months}M")?,
+                    (_, 0) => {}
+                    (_, months) => write!(f, " {months}M")?,
+                }
+                if days != 0 {
+                    write!(f, " {days}d")?;
+                }
+                if seconds != 0 {
+                    write!(f, " {}", Delay::Seconds(seconds))?;
+                }
+                Ok(())
+            }
+            Delay::Seconds(secs) => {
+                let units = [
+                    (SECONDS_IN_DAY as i64, "d"),
+                    (SECONDS_IN_HOUR as i64, "h"),
+                    (60, "min"),
+                    (1, "s"),
+                ];
+                let mut rest = secs;
+                let mut parts = Vec::new();
+                for (unit_secs, unit) in units {
+                    if rest >= unit_secs {
+                        parts.push(format!("{}{unit}", rest / unit_secs));
+                        rest %= unit_secs;
+                    }
+                }
+                if parts.is_empty() {
+                    parts.push(format!("{secs}s"));
+                }
+                f.write_str(&parts.join(" "))
+            }
+        }
+    }
+}
+
+/// Formats `delta` coarsely, e.g. `in 2h`, `in 5 weeks` or `3d ago`.
+///
+/// The largest unit that fits at least twice is used, so that e.g. 90 minutes
+/// are shown as `in 90min` instead of `in 1h`.
+pub fn format_relative(delta: TimeDelta) -> String {
+    let secs = delta.num_seconds().unsigned_abs();
+    if secs == 0 {
+        return "now".to_string();
+    }
+    let units = [
+        (SECONDS_IN_YEAR, " years"),
+        (SECONDS_IN_MONTH, " months"),
+        (SECONDS_IN_WEEK, " weeks"),
+        (SECONDS_IN_DAY, "d"),
+        (SECONDS_IN_HOUR, "h"),
+        (60, "min"),
+    ];
+    let (n, unit) = units
+        .into_iter()
+        .map(|(unit_secs, unit)| (secs / unit_secs, unit))
+        .find(|(n, _)| *n >= 2)
+        .unwrap_or((secs, "s"));
+    if delta < TimeDelta::zero() {
+        format!("{n}{unit} ago")
+    } else {
+        format!("in {n}{unit}")
+    }
+}
+
+/// Formats a time of day as `h:mm`, with seconds only if they are not 0
+fn format_time_of_day(time: &NaiveTime) -> impl std::fmt::Display {
+    match time.second() {
+        0 => time.format("%-k:%M"),
+        _ => time.format("%-k:%M:%S"),
+    }
+}
+
+/// A human readable description, e.g. `daily at 9:00`
+impl std::fmt::Display for RepeatTiming {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.fmt_localized(f, Locale::English)
+    }
+}
+
+impl LocalizedDisplay for RepeatTiming {
+    fn fmt_localized(&self, f: &mut std::fmt::Formatter<'_>, locale: Locale) -> std::fmt::Result {
+        match self {
+            RepeatTiming::Exact(exact) => write!(f, "{}", exact.localized(locale)),
+            RepeatTiming::Delay(delay) => write!(f, "every {delay}"),
+            RepeatTiming::Cron(expr) => write!(f, "on the cron schedule \"{expr}\""),
+            RepeatTiming::AnchoredDelay { delay, at } => {
+                write!(f, "every {delay} aligned to {}", format_time_of_day(at))
+            }
+            RepeatTiming::AlignedDelay { delay, align } => {
+                write!(f, "every {delay} on the {align}")
+            }
+        }
+    }
+}
+
+/// A human readable description, e.g. `in 5min` or `on monday at 9:00`
+impl std::fmt::Display for OnceTiming {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.fmt_localized(f, Locale::English)
+    }
+}
+
+impl LocalizedDisplay for OnceTiming {
+    fn fmt_localized(&self, f: &mut std::fmt::Formatter<'_>, locale: Locale) -> std::fmt::Result {
+        match self {
+            OnceTiming::Instant(instant) => write!(f, "{}", instant.localized(locale)),
+            OnceTiming::Delay(delay) => write!(f, "in {delay}"),
+        }
+    }
+}
+
+/// A human readable description, e.g. `on monday at 9:00`
+impl std::fmt::Display for RoughInstant {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.fmt_localized(f, Locale::English)
+    }
+}
+
+impl LocalizedDisplay for RoughInstant {
+    fn fmt_localized(&self, f: &mut std::fmt::Formatter<'_>, locale: Locale) -> std::fmt::Result {
+        let time = match self {
+            RoughInstant::DayOfMonth { day, time } => {
+                write!(f, "on day {day} of the month")?;
+                *time
+            }
+            RoughInstant::DayOfWeek { day, time } => {
+                write!(f, "on {}", locale.weekday(*day))?;
+                *time
+            }
+            RoughInstant::Date { date } => {
+                write!(f, "on {}", date.format("%Y-%m-%d"))?;
+                Some(date.time()).filter(|time| *time != NaiveTime::MIN)
+            }
+            RoughInstant::Month { month } => {
+                return write!(f, "in {}", locale.month(*month));
+            }
+            RoughInstant::Sunrise { offset } => return write_sun_offset(f, *offset, "sunrise"),
+            RoughInstant::Sunset { offset } => return write_sun_offset(f, *offset, "sunset"),
+        };
+        match time {
+            Some(time) => write!(f, " at {}", format_time_of_day(&time)),
+            None => Ok(()),
+        }
+    }
+}
+
+/// e.g. `30min before sunset`
+fn write_sun_offset(f: &mut std::fmt::Formatter<'_>, offset: i64, event: &str) -> std::fmt::Result {
+    match offset {
+        0 => write!(f, "at {event}"),
+        ..=-1 => write!(f, "{} before {event}", Delay::Seconds(-offset)),
+        1.. => write!(f, "{} after {event}", Delay::Seconds(offset)),
+    }
+}
+
+/// Converts a parse error into an owned error message.
+///
+/// If the parser succeeded but did not consume the entire input the message
+/// contains the unexpected rest of the input.
+fn timing_error(error: nom::Err<nom::error::Error<&str>>) -> nom::Err<String> {
+    let message = |err: nom::error::Error<&str>| match err.code {
+        nom::error::ErrorKind::Eof => format!("unexpected input: '{}'", err.input),
+        _ => err.to_string(),
+    };
+    match error {
+        nom::Err::Incomplete(err) => nom::Err::Incomplete(err),
+        nom::Err::Error(err) => nom::Err::Error(message(err)),
+        nom::Err::Failure(err) => nom::Err::Failure(message(err)),
+    }
+}
+
+/// The error for an empty timing
+pub const MISSING_TIMING: &str = "a timing is required, e.g. 'tomorrow 9:00'";
+
+/// Keywords of timings that are suggested for typos
+const KEYWORDS: [&str; 6] = ["daily", "monthly", "today", "tomorrow", "cron", "dom"];
+
+/// Adds a "did you mean" suggestion to `error` if the first word of `input` is a typo of a keyword.
+fn with_suggestion(input: &str, error: nom::Err<String>) -> nom::Err<String> {
+    let Some(suggestion) = suggest_keyword(input) else {
+        return error;
+    };
+    error.map(|message| format!("{message} (did you mean '{suggestion}'?)"))
+}
+
+fn suggest_keyword(input: &str) -> Option<&'static str> {
+    let word = input.split_whitespace().next()?.to_ascii_lowercase();
+    let keywords = KEYWORDS.iter().chain(&DAYS_IN_WEEK).chain(&MONTHS);
+    if word.len() < 3 || keywords.clone().any(|keyword| *keyword == word) {
+        return None;
+    }
+
+    keywords
+        .map(|keyword| (levenshtein(&word, keyword), *keyword))
+        .filter(|(distance, keyword)| *distance <= 2 && *distance < keyword.len() / 2)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, keyword)| keyword)
+}
+
+/// The number of single character edits to change `a` into `b`.
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    for (i, a) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b) in b.iter().enumerate() {
+            let substitution = prev[j] + usize::from(a != *b);
+            current.push(substitution.min(prev[j + 1] + 1).min(current[j] + 1));
+        }
+        prev = current;
+    }
+    prev[b.len()]
+}
+
+impl FromStr for Delay {
+    type Err = nom::Err<String>;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Delay::from_str_with(s, DelayPrecision::default())
+    }
+}
+
+impl Delay {
+    /// Parses a delay like [FromStr], adding months and years with `precision`
+    pub fn from_str_with(s: &str, precision: DelayPrecision) -> Result<Self, nom::Err<String>> {
+        match consume_all(parse_duration_with(precision))(s) {
+            Ok((_, delay)) => Ok(delay),
+            Err(error) => Err(timing_error(error)),
+        }
+    }
+}
+
+/// Parses a time of day in `h:m[:s]` format
+pub fn parse_time_of_day(s: &str) -> Result<NaiveTime, nom::Err<String>> {
+    match consume_all(parsing::parse_time)(s) {
+        Ok((_, time)) => Ok(time),
+        Err(error) => Err(timing_error(error)),
+    }
+}
+
+/// Parses an rfc3339 timestamp as local time, e.g. `2025-03-01T09:00:00+01:00`.
+///
+/// The offset and seconds are optional, a timestamp without offset is already in local time.
+pub fn parse_local_datetime(s: &str) -> Result<NaiveDateTime, String> {
+    if let Ok(datetime) = DateTime::parse_from_rfc3339(s) {
+        return Ok(datetime.with_timezone(&Local).naive_local());
+    }
+    ["%Y-%m-%dT%H:%M:%S", "%Y-%m-%dT%H:%M"]
+        .iter()
+        .find_map(|fmt| NaiveDateTime::parse_from_str(s, fmt).ok())
+        .ok_or_else(|| format!("invalid timestamp \"{s}\", expected e.g. 2025-03-01T09:00"))
 }
 
 fn parse_once_instant(input: &str) -> IResult<&str, OnceTiming> {
@@ -71,21 +446,50 @@ fn parse_once_instant(input: &str) -> IResult<&str, OnceTiming> {
     Ok((input, OnceTiming::Instant(instant)))
 }
 
-fn parse_once_delay(input: &str) -> IResult<&str, OnceTiming> {
-    let (input, delay) = parse_duration(input)?;
-    Ok((input, OnceTiming::Delay(delay)))
+fn parse_once_delay(precision: DelayPrecision) -> impl Fn(&str) -> IResult<&str, OnceTiming> {
+    move |input| {
+        let (input, delay) = parse_duration_with(precision)(input)?;
+        Ok((input, OnceTiming::Delay(delay)))
+    }
 }
 
 impl FromStr for OnceTiming {
     type Err = nom::Err<String>;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match consume_all(alt((parse_once_instant, parse_once_delay)))(s) {
+        OnceTiming::from_str_with(s, DelayPrecision::default())
+    }
+}
+
+impl OnceTiming {
+    /// Parses a timing like [FromStr], adding months and years of delays with `precision`
+    pub fn from_str_with(s: &str, precision: DelayPrecision) -> Result<Self, nom::Err<String>> {
+        if s.trim().is_empty() {
+            return Err(nom::Err::Failure(MISSING_TIMING.to_string()));
+        }
+        match consume_all(alt((parse_once_instant, parse_once_delay(precision))))(s) {
+            Ok((_, once)) => Ok(once),
+            Err(error) => Err(with_suggestion(s, timing_error(error))),
+        }
+    }
+
+    /// Parses a timing like [OnceTiming::from_str_with], but rejects instants that are
+    /// not fully specified.
+    ///
+    /// Delays are always accepted, instants need a date with year and a time,
+    /// e.g. `2025-12-14 09:00`.
+    pub fn from_str_strict(s: &str, precision: DelayPrecision) -> Result<Self, nom::Err<String>> {
+        let exact_instant = |input| {
+            let (input, instant) = parse_exact_instant(input)?;
+            Ok((input, OnceTiming::Instant(instant)))
+        };
+        match consume_all(alt((exact_instant, parse_once_delay(precision))))(s) {
             Ok((_, once)) => Ok(once),
-            Err(error) => match error {
-                nom::Err::Incomplete(err) => Err(nom::Err::Incomplete(err)),
-                nom::Err::Error(err) => Err(nom::Err::Error(err.to_string())),
-                nom::Err::Failure(err) => Err(nom::Err::Failure(err.to_string())),
+            Err(error) => match s.parse::<OnceTiming>() {
+                Ok(_) => Err(nom::Err::Error(format!(
+                    "ambiguous timing '{s}', expected a full date and time, e.g. 2025-12-14 09:00"
+                ))),
+                Err(_) => Err(with_suggestion(s, timing_error(error))),
             },
         }
     }
@@ -96,22 +500,57 @@ fn parse_repeat_exact(input: &str) -> IResult<&str, RepeatTiming> {
     Ok((input, RepeatTiming::Exact(exact)))
 }
 
-fn parse_repeat_delay(input: &str) -> IResult<&str, RepeatTiming> {
-    let (input, delay) = parse_duration(input)?;
-    Ok((input, RepeatTiming::Delay(delay)))
+fn parse_repeat_delay(precision: DelayPrecision) -> impl Fn(&str) -> IResult<&str, RepeatTiming> {
+    move |input| {
+        let (input, delay) = parse_duration_with(precision)(input)?;
+        Ok((input, RepeatTiming::Delay(delay)))
+    }
+}
+
+fn parse_repeat_cron(input: &str) -> IResult<&str, RepeatTiming> {
+    let (input, expr) = parsing::parse_cron(input)?;
+    Ok((input, RepeatTiming::Cron(expr)))
 }
 
 impl FromStr for RepeatTiming {
     type Err = nom::Err<String>;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match consume_all(alt((parse_repeat_exact, parse_repeat_delay)))(s) {
-            Ok((_, repeat)) => Ok(repeat),
-            Err(error) => match error {
-                nom::Err::Incomplete(err) => Err(nom::Err::Incomplete(err)),
-                nom::Err::Error(err) => Err(nom::Err::Error(err.to_string())),
-                nom::Err::Failure(err) => Err(nom::Err::Failure(err.to_string())),
+        RepeatTiming::from_str_with(s, DelayPrecision::default())
+    }
+}
+
+impl Repeat {
+    /// Fixes a [RepeatExact::Daily] without a time to the time of day of `created`,
+    /// so that late notifications don't move it.
+    pub fn with_creation_time(self, created: NaiveDateTime) -> Self {
+        match self {
+            Repeat::Repeat {
+                timing: RepeatTiming::Exact(RepeatExact::Daily { time: None }),
+            } => Repeat::Repeat {
+                timing: RepeatTiming::Exact(RepeatExact::Daily {
+                    time: Some(daily_time(None, created)),
+                }),
             },
+            timing => timing,
+        }
+    }
+}
+
+impl RepeatTiming {
+    /// Parses a timing like [FromStr], adding months and years of delays with `precision`
+    pub fn from_str_with(s: &str, precision: DelayPrecision) -> Result<Self, nom::Err<String>> {
+        if s.trim().is_empty() {
+            return Err(nom::Err::Failure(MISSING_TIMING.to_string()));
+        }
+        match consume_all(alt((
+            parse_repeat_cron,
+            parse_repeat_exact,
+            parse_repeat_delay(precision),
+        )))(s)
+        {
+            Ok((_, repeat)) => Ok(repeat),
+            Err(error) => Err(with_suggestion(s, timing_error(error))),
         }
     }
 }
@@ -131,8 +570,17 @@ pub enum RoughInstant {
         date: NaiveDateTime,
     },
     Month {
+        /// 1 for january to 12 for december
         month: u8,
     },
+    /// `offset` seconds after sunrise at the configured [sun::Location]
+    Sunrise {
+        offset: i64,
+    },
+    /// `offset` seconds after sunset at the configured [sun::Location]
+    Sunset {
+        offset: i64,
+    },
 }
 
 #[derive(Debug, Error)]
@@ -141,35 +589,247 @@ pub enum TimeError {
     InvalidDay(u8),
     #[error("{0} is not a valid month")]
     InvalidMonth(u8),
+    #[error("\"{0}\" is not a valid cron expression")]
+    InvalidCron(String),
+    #[error("sunrise and sunset need a location, e.g. `location: Some((latitude: 52.5, longitude: 13.4))` in the config file")]
+    NoLocation,
+    #[error("the sun does not rise or set on {0}")]
+    NoSun(NaiveDate),
+}
+
+const CRON_DAYS_OF_WEEK: [&str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+/// Creates a [cron::Schedule] from a cron expression.
+///
+/// Standard 5 field expressions (`min hour day-of-month month day-of-week`) are
+/// supported with `0` and `7` meaning sunday. Expressions with 6 or 7 fields
+/// are passed to [cron::Schedule] unchanged.
+pub fn cron_schedule(expr: &str) -> Result<cron::Schedule, TimeError> {
+    let fields: Vec<&str> = expr.split_whitespace().collect();
+    let expr = match fields.as_slice() {
+        [min, hour, dom, month, dow] => {
+            format!("0 {min} {hour} {dom} {month} {}", cron_day_of_week(dow))
+        }
+        _ => fields.join(" "),
+    };
+    cron::Schedule::from_str(&expr).map_err(|_| TimeError::InvalidCron(expr))
+}
+
+/// Translates numeric days of week from standard cron (sunday = 0)
+/// to names, because [cron::Schedule] counts from sunday = 1.
+fn cron_day_of_week(field: &str) -> String {
+    field
+        .split(',')
+        .map(|item| {
+            let (range, step) = match item.split_once('/') {
+                Some((range, step)) => (range, Some(step)),
+                None => (item, None),
+            };
+            let range = range
+                .split('-')
+                .map(|day| match day.parse::<usize>() {
+                    Ok(day) if day <= 7 => CRON_DAYS_OF_WEEK[day % 7],
+                    _ => day,
+                })
+                .collect::<Vec<_>>()
+                .join("-");
+            match step {
+                Some(step) => format!("{range}/{step}"),
+                None => range,
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// The boundary an [RepeatTiming::AlignedDelay] notifies on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+pub enum AlignUnit {
+    /// at minute 0 of an hour
+    Hour,
+    /// at midnight
+    Day,
+}
+
+impl AlignUnit {
+    /// The latest boundary at or before `timestamp`
+    pub fn floor(self, timestamp: NaiveDateTime) -> NaiveDateTime {
+        match self {
+            AlignUnit::Hour => timestamp
+                .date()
+                .and_hms_opt(timestamp.hour(), 0, 0)
+                .expect("minute 0 of an hour is always valid"),
+            AlignUnit::Day => timestamp.date().and_time(NaiveTime::MIN),
+        }
+    }
+
+    /// The boundary following `boundary`
+    fn next(self, boundary: NaiveDateTime) -> NaiveDateTime {
+        match self {
+            AlignUnit::Hour => boundary + TimeDelta::hours(1),
+            AlignUnit::Day => boundary + TimeDelta::days(1),
+        }
+    }
+}
+
+impl std::fmt::Display for AlignUnit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AlignUnit::Hour => write!(f, "hour"),
+            AlignUnit::Day => write!(f, "day"),
+        }
+    }
 }
 
-fn monday_same_week(date: &NaiveDate) -> NaiveDate {
-    let days_since_mon = date.weekday().days_since(Weekday::Mon);
-    *date - Days::new(days_since_mon.into())
+/// Rounds `next` down to a boundary of `align`, or up if that is not after `last`.
+///
+/// Rounding down keeps late notifications from drifting to the next boundary.
+pub fn align_after(next: NaiveDateTime, last: NaiveDateTime, align: AlignUnit) -> NaiveDateTime {
+    let aligned = align.floor(next);
+    if aligned > last {
+        aligned
+    } else {
+        align.next(align.floor(last))
+    }
+}
+
+/// The first day of a week, used to resolve weekdays like "sunday" within the current week
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+pub enum WeekStart {
+    #[default]
+    Monday,
+    Sunday,
+}
+
+/// The settings dates are computed and named with, usually taken from the config
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Calendar {
+    /// The first day of the week, which decides e.g. if "sunday" is in the past or future
+    pub week_start: WeekStart,
+    /// The language of weekday and month names in the output
+    pub locale: Locale,
+    /// Where sunrise and sunset timings are computed for
+    pub location: Option<sun::Location>,
+}
+
+/// How months and years of delays are added, e.g. in `1M` or `1M 36h`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+pub enum DelayPrecision {
+    /// months are 30 days and years are 365 days
+    Approximate,
+    /// months and years are calendar months, e.g. from the 15th to the 15th of the next month
+    #[default]
+    Calendar,
+}
+
+/// The date of the weekday `day` (Mon = 0) in the same week as `date`
+fn day_in_week(date: &NaiveDate, day: u8, week_start: WeekStart) -> NaiveDate {
+    let first_day = match week_start {
+        WeekStart::Monday => Weekday::Mon,
+        WeekStart::Sunday => Weekday::Sun,
+    };
+    let week_start_date = *date - Days::new(date.weekday().days_since(first_day).into());
+    let weekday = Weekday::try_from(day).unwrap_or(Weekday::Mon);
+    week_start_date + Days::new(weekday.days_since(first_day).into())
+}
+
+/// The first `day` of a month at `time` strictly after `after`.
+///
+/// Months without `day`, e.g. february for day 31, are skipped.
+fn day_of_month_after(
+    after: NaiveDateTime,
+    day: u8,
+    time: NaiveTime,
+) -> Result<NaiveDateTime, TimeError> {
+    let mut month = after.date().with_day(1).unwrap();
+    // every valid day exists at least once within a year
+    for _ in 0..=12 {
+        if let Some(date) = month.with_day(day as u32) {
+            let next = NaiveDateTime::new(date, time);
+            if next > after {
+                return Ok(next);
+            }
+        }
+        month = month
+            .checked_add_months(Months::new(1))
+            .ok_or(TimeError::InvalidDay(day))?;
+    }
+    Err(TimeError::InvalidDay(day))
+}
+
+/// `day` of the month of `now` at `time`, which might be in the past.
+///
+/// If the month of `now` has no `day`, the next month with it is used instead.
+fn day_of_month(now: NaiveDateTime, day: u8, time: NaiveTime) -> Result<NaiveDateTime, TimeError> {
+    match now.date().with_day(day as u32) {
+        Some(date) => Ok(NaiveDateTime::new(date, time)),
+        None => day_of_month_after(now, day, time),
+    }
 }
 
 impl RoughInstant {
-    pub fn notification_date(&self) -> Result<NaiveDateTime, TimeError> {
+    pub fn notification_date(&self, calendar: &Calendar) -> Result<NaiveDateTime, TimeError> {
         let now = Local::now().naive_local();
         let midnight = NaiveTime::from_hms_opt(0, 0, 0).unwrap();
         match self {
-            RoughInstant::DayOfMonth { day, time } => Ok(NaiveDateTime::new(
-                NaiveDate::from_ymd_opt(now.year(), now.month(), *day as u32)
-                    .ok_or(TimeError::InvalidDay(*day))?,
-                time.unwrap_or(midnight),
-            )),
+            RoughInstant::DayOfMonth { day, time } => {
+                day_of_month(now, *day, time.unwrap_or(midnight))
+            }
             RoughInstant::DayOfWeek { day, time } => {
-                let today = now.date();
-                let week_start = monday_same_week(&today);
-                let day = week_start + Days::new((*day).into());
-                Ok(NaiveDateTime::new(day, time.clone().unwrap_or(midnight)))
+                let day = day_in_week(&now.date(), *day, calendar.week_start);
+                Ok(NaiveDateTime::new(day, time.unwrap_or(midnight)))
             }
-            RoughInstant::Date { date } => Ok(date.clone()),
+            RoughInstant::Date { date } => Ok(*date),
             RoughInstant::Month { month } => Ok(NaiveDateTime::new(
                 NaiveDate::from_ymd_opt(now.year(), *month as u32, 1)
                     .ok_or(TimeError::InvalidMonth(*month))?,
                 midnight,
             )),
+            RoughInstant::Sunrise { .. } | RoughInstant::Sunset { .. } => {
+                self.sun_time(now.date(), calendar)
+            }
+        }
+    }
+
+    /// The local time of a [RoughInstant::Sunrise] or [RoughInstant::Sunset] on `date`
+    fn sun_time(&self, date: NaiveDate, calendar: &Calendar) -> Result<NaiveDateTime, TimeError> {
+        let location = calendar.location.ok_or(TimeError::NoLocation)?;
+        let (time, offset) = match self {
+            RoughInstant::Sunrise { offset } => (sun::sunrise(date, location), offset),
+            RoughInstant::Sunset { offset } => (sun::sunset(date, location), offset),
+            _ => unreachable!("only sunrise and sunset depend on the sun"),
+        };
+        let time = time.ok_or(TimeError::NoSun(date))? + TimeDelta::seconds(*offset);
+        Ok(time.with_timezone(&Local).naive_local())
+    }
+
+    /// The first notification date after `now`.
+    ///
+    /// Unlike [Self::notification_date] this never returns a date in the past,
+    /// except for [RoughInstant::Date].
+    pub fn next_after(
+        &self,
+        now: NaiveDateTime,
+        calendar: &Calendar,
+    ) -> Result<NaiveDateTime, TimeError> {
+        if let RoughInstant::DayOfMonth { day, time } = self {
+            let midnight = NaiveTime::from_hms_opt(0, 0, 0).unwrap();
+            return day_of_month_after(now, *day, time.unwrap_or(midnight));
+        }
+        let date = self.notification_date(calendar)?;
+        if date > now {
+            return Ok(date);
+        }
+        match self {
+            RoughInstant::DayOfMonth { .. } => unreachable!("returned above"),
+            RoughInstant::DayOfWeek { .. } => Ok(date + Days::new(7)),
+            RoughInstant::Date { date } => Ok(*date),
+            RoughInstant::Month { month } => date
+                .with_year(date.year() + 1)
+                .ok_or(TimeError::InvalidMonth(*month)),
+            RoughInstant::Sunrise { .. } | RoughInstant::Sunset { .. } => {
+                self.sun_time(now.date() + Days::new(1), calendar)
+            }
         }
     }
 }
@@ -187,32 +847,614 @@ pub enum RepeatExact {
         day: u8,
         time: Option<NaiveTime>,
     },
-    Daily {
-        time: Option<NaiveTime>,
-    },
+    /// Once each day at `time`.
+    ///
+    /// `daily` without a time is fixed to the time of day of the creation, see
+    /// [Repeat::with_creation_time]. Files written before that may still contain
+    /// `None`, which repeats at the time of day of the last notification, see [daily_time].
+    Daily { time: Option<NaiveTime> },
+    /// Multiple times each day, sorted
+    DailyTimes { times: Vec<NaiveTime> },
+    /// Once each day at a time between `start` and `end`, see [window_time]
+    Window { start: NaiveTime, end: NaiveTime },
+}
+
+/// A human readable description, e.g. `every monday at 9:00`
+impl std::fmt::Display for RepeatExact {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.fmt_localized(f, Locale::English)
+    }
+}
+
+impl LocalizedDisplay for RepeatExact {
+    fn fmt_localized(&self, f: &mut std::fmt::Formatter<'_>, locale: Locale) -> std::fmt::Result {
+        let time = match self {
+            RepeatExact::DayOfMonth { day, time } => {
+                write!(f, "monthly on day {day}")?;
+                time
+            }
+            RepeatExact::DayOfWeek { day, time } => {
+                write!(f, "every {}", locale.weekday(*day))?;
+                time
+            }
+            RepeatExact::Daily { time } => {
+                f.write_str("daily")?;
+                time
+            }
+            RepeatExact::DailyTimes { times } => {
+                let times: Vec<_> = times
+                    .iter()
+                    .map(|time| format_time_of_day(time).to_string())
+                    .collect();
+                return write!(f, "daily at {}", times.join(", "));
+            }
+            RepeatExact::Window { start, end } => {
+                return write!(
+                    f,
+                    "daily between {} and {}",
+                    format_time_of_day(start),
+                    format_time_of_day(end)
+                );
+            }
+        };
+        match time {
+            Some(time) => write!(f, " at {}", format_time_of_day(time)),
+            None => Ok(()),
+        }
+    }
 }
 
 impl RepeatExact {
-    pub fn notification_date(&self) -> Result<NaiveDateTime, TimeError> {
+    pub fn notification_date(
+        &self,
+        last_timestamp: NaiveDateTime,
+        calendar: &Calendar,
+    ) -> Result<NaiveDateTime, TimeError> {
         let now = Local::now().naive_local();
         let midnight = NaiveTime::from_hms_opt(0, 0, 0).unwrap();
         match self {
-            RepeatExact::DayOfMonth { day, time } => Ok(NaiveDateTime::new(
-                NaiveDate::from_ymd_opt(now.year(), now.month(), *day as u32)
-                    .ok_or(TimeError::InvalidDay(*day))?,
-                time.unwrap_or(midnight),
-            )),
+            RepeatExact::DayOfMonth { day, time } => {
+                day_of_month(now, *day, time.unwrap_or(midnight))
+            }
             RepeatExact::DayOfWeek { day, time } => {
-                let today = now.date();
-                let week_start = monday_same_week(&today);
-                let day = week_start + Days::new((*day).into());
-                Ok(NaiveDateTime::new(day, time.clone().unwrap_or(midnight)))
+                let day = day_in_week(&now.date(), *day, calendar.week_start);
+                Ok(NaiveDateTime::new(day, time.unwrap_or(midnight)))
             }
 
             RepeatExact::Daily { time } => {
                 let today = now.date();
-                Ok(NaiveDateTime::new(today, time.unwrap_or(midnight)))
+                Ok(NaiveDateTime::new(today, daily_time(*time, last_timestamp)))
+            }
+            RepeatExact::DailyTimes { .. } => self.next_after(last_timestamp, calendar),
+            RepeatExact::Window { start, end } => {
+                let today = now.date();
+                Ok(NaiveDateTime::new(today, window_time(*start, *end, today)))
             }
         }
     }
+
+    /// The first notification date strictly after `after`.
+    pub fn next_after(
+        &self,
+        after: NaiveDateTime,
+        calendar: &Calendar,
+    ) -> Result<NaiveDateTime, TimeError> {
+        let midnight = NaiveTime::from_hms_opt(0, 0, 0).unwrap();
+        match self {
+            RepeatExact::DayOfMonth { day, time } => {
+                day_of_month_after(after, *day, time.unwrap_or(midnight))
+            }
+            RepeatExact::DayOfWeek { day, time } => {
+                let date = day_in_week(&after.date(), *day, calendar.week_start);
+                let next = NaiveDateTime::new(date, time.unwrap_or(midnight));
+                if next > after {
+                    Ok(next)
+                } else {
+                    Ok(next + Days::new(7))
+                }
+            }
+            RepeatExact::Daily { time } => {
+                let next = NaiveDateTime::new(after.date(), daily_time(*time, after));
+                if next > after {
+                    Ok(next)
+                } else {
+                    Ok(next + Days::new(1))
+                }
+            }
+            RepeatExact::DailyTimes { times } => {
+                let today = after.date();
+                let next = times
+                    .iter()
+                    .map(|time| NaiveDateTime::new(today, *time))
+                    .find(|next| *next > after);
+                match (next, times.first()) {
+                    (Some(next), _) => Ok(next),
+                    (None, Some(first)) => Ok(NaiveDateTime::new(today + Days::new(1), *first)),
+                    (None, None) => Ok(NaiveDateTime::new(today + Days::new(1), midnight)),
+                }
+            }
+            RepeatExact::Window { start, end } => {
+                let today = after.date();
+                let next = NaiveDateTime::new(today, window_time(*start, *end, today));
+                if next > after {
+                    Ok(next)
+                } else {
+                    let tomorrow = today + Days::new(1);
+                    Ok(NaiveDateTime::new(
+                        tomorrow,
+                        window_time(*start, *end, tomorrow),
+                    ))
+                }
+            }
+        }
+    }
+}
+
+/// The time of a [RepeatExact::Daily] notification.
+///
+/// Without an explicit `time`, which only older files contain, the notification
+/// repeats at the time of day of `last_timestamp`.
+/// Seconds are ignored so that late notifications don't shift the time.
+fn daily_time(time: Option<NaiveTime>, last_timestamp: NaiveDateTime) -> NaiveTime {
+    time.unwrap_or_else(|| {
+        let last = last_timestamp.time();
+        NaiveTime::from_hms_opt(last.hour(), last.minute(), 0).expect("valid time")
+    })
+}
+
+/// A time between `start` (inclusive) and `end` (exclusive) that looks random,
+/// but is the same for every call with the same `date`.
+pub fn window_time(start: NaiveTime, end: NaiveTime, date: NaiveDate) -> NaiveTime {
+    let window = (end - start).num_seconds();
+    if window <= 0 {
+        return start;
+    }
+    // FNV-1a, the std hashers are not guaranteed to be stable between releases
+    let hash = date
+        .num_days_from_ce()
+        .to_le_bytes()
+        .iter()
+        .fold(0xcbf29ce484222325u64, |hash, b| {
+            (hash ^ *b as u64).wrapping_mul(0x100000001b3)
+        });
+    start + TimeDelta::seconds((hash % window as u64) as i64)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_daily_times_across_day_boundary() {
+        let timing = RepeatExact::DailyTimes {
+            times: vec![
+                NaiveTime::from_hms_opt(9, 0, 0).unwrap(),
+                NaiveTime::from_hms_opt(17, 0, 0).unwrap(),
+            ],
+        };
+        let at = |d, h, m| {
+            NaiveDate::from_ymd_opt(2024, 10, d)
+                .unwrap()
+                .and_hms_opt(h, m, 0)
+                .unwrap()
+        };
+
+        assert_eq!(
+            timing
+                .notification_date(at(7, 8, 0), &Calendar::default())
+                .unwrap(),
+            at(7, 9, 0)
+        );
+        assert_eq!(
+            timing
+                .notification_date(at(7, 9, 0), &Calendar::default())
+                .unwrap(),
+            at(7, 17, 0)
+        );
+        assert_eq!(
+            timing
+                .notification_date(at(7, 12, 0), &Calendar::default())
+                .unwrap(),
+            at(7, 17, 0)
+        );
+        assert_eq!(
+            timing
+                .notification_date(at(7, 17, 0), &Calendar::default())
+                .unwrap(),
+            at(8, 9, 0)
+        );
+        assert_eq!(
+            timing
+                .notification_date(at(7, 23, 59), &Calendar::default())
+                .unwrap(),
+            at(8, 9, 0)
+        );
+    }
+
+    #[test]
+    fn test_daily_without_time() {
+        let timing = RepeatExact::Daily { time: None };
+        let at = |d, h, m, s| {
+            NaiveDate::from_ymd_opt(2024, 10, d)
+                .unwrap()
+                .and_hms_opt(h, m, s)
+                .unwrap()
+        };
+
+        assert_eq!(
+            timing
+                .next_after(at(7, 14, 30, 0), &Calendar::default())
+                .unwrap(),
+            at(8, 14, 30, 0)
+        );
+        // a late notification does not move the time
+        assert_eq!(
+            timing
+                .next_after(at(8, 14, 30, 42), &Calendar::default())
+                .unwrap(),
+            at(9, 14, 30, 0)
+        );
+        let timing = RepeatExact::Daily {
+            time: NaiveTime::from_hms_opt(9, 0, 0),
+        };
+        assert_eq!(
+            timing
+                .next_after(at(7, 14, 30, 0), &Calendar::default())
+                .unwrap(),
+            at(8, 9, 0, 0)
+        );
+    }
+
+    #[test]
+    fn test_window_time() {
+        let start = NaiveTime::from_hms_opt(9, 0, 0).unwrap();
+        let end = NaiveTime::from_hms_opt(11, 0, 0).unwrap();
+        let timing = RepeatExact::Window { start, end };
+
+        let mut times = Vec::new();
+        for day in 1..=31 {
+            let date = NaiveDate::from_ymd_opt(2024, 10, day).unwrap();
+            let time = window_time(start, end, date);
+            assert!(start <= time && time < end, "{date}: {time}");
+            assert_eq!(window_time(start, end, date), time);
+
+            let next = timing
+                .next_after(date.and_hms_opt(0, 0, 0).unwrap(), &Calendar::default())
+                .unwrap();
+            assert_eq!(next, NaiveDateTime::new(date, time));
+            times.push(time);
+        }
+        times.dedup();
+        assert!(times.len() > 1, "the time should change between days");
+
+        let date = NaiveDate::from_ymd_opt(2024, 10, 7).unwrap();
+        let after = NaiveDateTime::new(date, end);
+        let tomorrow = date + Days::new(1);
+        assert_eq!(
+            timing.next_after(after, &Calendar::default()).unwrap(),
+            NaiveDateTime::new(tomorrow, window_time(start, end, tomorrow))
+        );
+        assert_eq!(timing.to_string(), "daily between 9:00 and 11:00");
+    }
+
+    #[test]
+    fn test_day_of_month_31() {
+        let at = |y, m, d, h| {
+            NaiveDate::from_ymd_opt(y, m, d)
+                .unwrap()
+                .and_hms_opt(h, 0, 0)
+                .unwrap()
+        };
+        let dom = RoughInstant::DayOfMonth {
+            day: 31,
+            time: None,
+        };
+        let monthly = RepeatExact::DayOfMonth {
+            day: 31,
+            time: None,
+        };
+
+        // february has no 31st
+        for now in [at(2024, 1, 31, 0), at(2024, 1, 31, 12)] {
+            assert_eq!(
+                dom.next_after(now, &Calendar::default()).unwrap(),
+                at(2024, 3, 31, 0),
+                "{now}"
+            );
+            assert_eq!(
+                monthly.next_after(now, &Calendar::default()).unwrap(),
+                at(2024, 3, 31, 0),
+                "{now}"
+            );
+        }
+        assert_eq!(
+            dom.next_after(at(2024, 1, 30, 12), &Calendar::default())
+                .unwrap(),
+            at(2024, 1, 31, 0)
+        );
+
+        // across the end of the year
+        assert_eq!(
+            dom.next_after(at(2024, 12, 20, 12), &Calendar::default())
+                .unwrap(),
+            at(2024, 12, 31, 0)
+        );
+        for now in [at(2024, 12, 31, 12), at(2024, 12, 31, 0)] {
+            assert_eq!(
+                dom.next_after(now, &Calendar::default()).unwrap(),
+                at(2025, 1, 31, 0),
+                "{now}"
+            );
+            assert_eq!(
+                monthly.next_after(now, &Calendar::default()).unwrap(),
+                at(2025, 1, 31, 0),
+                "{now}"
+            );
+        }
+
+        // months without the day use the next month with it
+        assert_eq!(
+            day_of_month(at(2024, 11, 20, 12), 31, NaiveTime::MIN).unwrap(),
+            at(2024, 12, 31, 0)
+        );
+        assert_eq!(
+            day_of_month(at(2024, 12, 20, 12), 31, NaiveTime::MIN).unwrap(),
+            at(2024, 12, 31, 0)
+        );
+        assert!(matches!(
+            day_of_month_after(at(2024, 1, 1, 0), 32, NaiveTime::MIN),
+            Err(TimeError::InvalidDay(32))
+        ));
+    }
+
+    #[test]
+    fn test_day_in_week_start() {
+        // a wednesday
+        let date = NaiveDate::from_ymd_opt(2024, 3, 6).unwrap();
+        let sunday = 6;
+        assert_eq!(
+            day_in_week(&date, sunday, WeekStart::Monday),
+            NaiveDate::from_ymd_opt(2024, 3, 10).unwrap()
+        );
+        assert_eq!(
+            day_in_week(&date, sunday, WeekStart::Sunday),
+            NaiveDate::from_ymd_opt(2024, 3, 3).unwrap()
+        );
+
+        let monday = 0;
+        assert_eq!(
+            day_in_week(&date, monday, WeekStart::Monday),
+            NaiveDate::from_ymd_opt(2024, 3, 4).unwrap()
+        );
+        assert_eq!(
+            day_in_week(&date, monday, WeekStart::Sunday),
+            NaiveDate::from_ymd_opt(2024, 3, 4).unwrap()
+        );
+
+        // a sunday
+        let date = NaiveDate::from_ymd_opt(2024, 3, 10).unwrap();
+        assert_eq!(day_in_week(&date, sunday, WeekStart::Monday), date);
+        assert_eq!(day_in_week(&date, sunday, WeekStart::Sunday), date);
+        assert_eq!(
+            day_in_week(&date, monday, WeekStart::Sunday),
+            NaiveDate::from_ymd_opt(2024, 3, 11).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_parse_local_datetime() {
+        let expected = NaiveDate::from_ymd_opt(2025, 3, 1)
+            .unwrap()
+            .and_hms_opt(9, 0, 0)
+            .unwrap();
+        assert_eq!(parse_local_datetime("2025-03-01T09:00").unwrap(), expected);
+        assert_eq!(
+            parse_local_datetime("2025-03-01T09:00:00").unwrap(),
+            expected
+        );
+
+        let utc = parse_local_datetime("2025-03-01T09:00:00Z").unwrap();
+        assert_eq!(utc, expected.and_utc().with_timezone(&Local).naive_local());
+
+        assert!(parse_local_datetime("tomorrow").is_err());
+        assert!(parse_local_datetime("2025-03-01").is_err());
+    }
+
+    #[test]
+    fn test_once_timing_strict() {
+        let expected = NaiveDate::from_ymd_opt(2025, 12, 14)
+            .unwrap()
+            .and_hms_opt(9, 0, 0)
+            .unwrap();
+        assert_eq!(
+            OnceTiming::from_str_strict("2025-12-14 09:00", DelayPrecision::Approximate).unwrap(),
+            OnceTiming::Instant(RoughInstant::Date { date: expected })
+        );
+        assert_eq!(
+            OnceTiming::from_str_strict("1h 30min", DelayPrecision::Approximate).unwrap(),
+            "1h 30min".parse().unwrap()
+        );
+
+        for ambiguous in [
+            "14-12",
+            "2025-12-14",
+            "14-12 9:00",
+            "monday",
+            "tomorrow 9:00",
+        ] {
+            assert!(ambiguous.parse::<OnceTiming>().is_ok());
+            let err =
+                OnceTiming::from_str_strict(ambiguous, DelayPrecision::Approximate).unwrap_err();
+            assert!(err.to_string().contains("ambiguous"), "{ambiguous}: {err}");
+        }
+        assert!(OnceTiming::from_str_strict("someday", DelayPrecision::Approximate).is_err());
+    }
+
+    #[test]
+    fn test_format_relative() {
+        assert_eq!(format_relative(TimeDelta::zero()), "now");
+        assert_eq!(format_relative(TimeDelta::seconds(42)), "in 42s");
+        assert_eq!(format_relative(TimeDelta::seconds(90)), "in 90s");
+        assert_eq!(format_relative(TimeDelta::minutes(90)), "in 90min");
+        assert_eq!(format_relative(TimeDelta::hours(2)), "in 2h");
+        assert_eq!(format_relative(TimeDelta::days(3)), "in 3d");
+        assert_eq!(format_relative(TimeDelta::days(10)), "in 10d");
+        assert_eq!(format_relative(TimeDelta::weeks(5)), "in 5 weeks");
+        assert_eq!(format_relative(TimeDelta::days(65)), "in 2 months");
+        assert_eq!(format_relative(TimeDelta::days(800)), "in 2 years");
+        assert_eq!(format_relative(TimeDelta::hours(-3)), "3h ago");
+    }
+
+    #[test]
+    fn test_unexpected_input_error() {
+        let err = OnceTiming::from_str("monday 12:00 xyz").unwrap_err();
+        assert_eq!(err, nom::Err::Error("unexpected input: ' xyz'".to_string()));
+
+        let err = RepeatTiming::from_str("daily rest").unwrap_err();
+        assert!(err.to_string().contains("' rest'"), "{err}");
+
+        let err = Delay::from_str("5d 3w").unwrap_err();
+        assert!(err.to_string().contains("'3w'"), "{err}");
+    }
+
+    #[test]
+    fn test_delay_equality() {
+        assert!(Delay::Days(1).same_duration(&Delay::Seconds(86400)));
+        assert!(Delay::Days(7).same_duration(&Delay::from_str("1w").unwrap()));
+        assert!(Delay::from_str("24h")
+            .unwrap()
+            .same_duration(&Delay::from_str("1d").unwrap()));
+        assert!(!Delay::Days(1).same_duration(&Delay::Seconds(86399)));
+        assert_eq!(
+            Delay::Seconds(3600).cmp_duration(&Delay::Days(1)),
+            std::cmp::Ordering::Less
+        );
+        assert_eq!(
+            Delay::Days(2).cmp_duration(&Delay::Seconds(86401)),
+            std::cmp::Ordering::Greater
+        );
+
+        // days notify at midnight, seconds at the time of day they were created
+        assert_ne!(Delay::Days(1), Delay::Seconds(86400));
+        assert_ne!(
+            Delay::Days(30),
+            Delay::Calendar {
+                months: 1,
+                days: 0,
+                seconds: 0
+            }
+        );
+        assert_eq!(Delay::Days(2).to_duration(), TimeDelta::hours(48));
+
+        assert!(matches!(Delay::from_str("1d").unwrap(), Delay::Days(1)));
+        assert!(matches!(
+            Delay::from_str("1d 1h").unwrap(),
+            Delay::Seconds(90000)
+        ));
+        assert!(matches!(Delay::from_str("0d").unwrap(), Delay::Seconds(0)));
+        assert!(matches!(Delay::Days(3).normalize(), Delay::Days(3)));
+    }
+
+    #[test]
+    fn test_delay_units() {
+        let month = Delay::from_str("1M").unwrap();
+        let calendar_month = Delay::Calendar {
+            months: 1,
+            days: 0,
+            seconds: 0,
+        };
+        assert_eq!(month, calendar_month);
+        assert_eq!(month.to_string(), "1M");
+        assert_eq!(month.to_duration(), TimeDelta::days(30));
+        let ron = ron::to_string(&month).unwrap();
+        assert_eq!(ron::from_str::<Delay>(&ron).unwrap(), calendar_month);
+
+        assert_eq!(Delay::from_str("2w").unwrap().to_string(), "2w");
+        assert_eq!(Delay::from_str("1y").unwrap().to_string(), "1y");
+        assert_eq!(Delay::from_str("1y 2M").unwrap().to_string(), "1y 2M");
+        assert_eq!(
+            Delay::from_str_with("1M", DelayPrecision::Approximate)
+                .unwrap()
+                .to_string(),
+            "30d"
+        );
+        assert!(matches!(Delay::from_str("0M").unwrap(), Delay::Seconds(0)));
+
+        // files written before the units were kept
+        assert!(matches!(
+            ron::from_str::<Delay>("Days(30)").unwrap(),
+            Delay::Days(30)
+        ));
+        assert!(matches!(
+            ron::from_str::<Delay>("Seconds(60)").unwrap(),
+            Delay::Seconds(60)
+        ));
+    }
+
+    #[test]
+    fn test_display() {
+        let display = |timing: &str| RepeatTiming::from_str(timing).unwrap().to_string();
+        assert_eq!(display("daily 9:00"), "daily at 9:00");
+        assert_eq!(display("daily 9:00,17:30"), "daily at 9:00, 17:30");
+        assert_eq!(display("monday 9:00:30"), "every monday at 9:00:30");
+        assert_eq!(display("1h 30min"), "every 1h 30min");
+        assert_eq!(display("3d"), "every 3d");
+        assert_eq!(Delay::Seconds(0).to_string(), "0s");
+
+        let monday = RepeatTiming::from_str("monday 9:00").unwrap();
+        assert_eq!(
+            monday.localized(Locale::German).to_string(),
+            "every Montag at 9:00"
+        );
+        let friday = OnceTiming::from_str("friday").unwrap();
+        assert_eq!(friday.localized(Locale::French).to_string(), "on vendredi");
+    }
+
+    #[test]
+    fn test_suggestions() {
+        let err = RepeatTiming::from_str("dayly 9:00").unwrap_err();
+        assert!(err.to_string().contains("did you mean 'daily'?"), "{err}");
+
+        let err = RepeatTiming::from_str("mounthly 3").unwrap_err();
+        assert!(err.to_string().contains("did you mean 'monthly'?"), "{err}");
+
+        let err = OnceTiming::from_str("mondy 12:00").unwrap_err();
+        assert!(err.to_string().contains("did you mean 'monday'?"), "{err}");
+
+        let err = OnceTiming::from_str("monday 12:00 xyz").unwrap_err();
+        assert!(!err.to_string().contains("did you mean"), "{err}");
+        let err = OnceTiming::from_str("xyz").unwrap_err();
+        assert!(!err.to_string().contains("did you mean"), "{err}");
+    }
+
+    #[test]
+    fn test_sun_location() {
+        let sunset = RoughInstant::Sunset { offset: -30 * 60 };
+        let now = NaiveDate::from_ymd_opt(2024, 6, 21)
+            .unwrap()
+            .and_hms_opt(0, 0, 0)
+            .unwrap();
+        assert!(matches!(
+            sunset.next_after(now, &Calendar::default()),
+            Err(TimeError::NoLocation)
+        ));
+
+        let calendar = Calendar {
+            location: Some(sun::Location {
+                latitude: 52.52,
+                longitude: 13.405,
+            }),
+            ..Default::default()
+        };
+        assert!(sunset.next_after(now, &calendar).unwrap() > now);
+    }
+
+    #[test]
+    fn test_levenshtein() {
+        assert_eq!(levenshtein("daily", "daily"), 0);
+        assert_eq!(levenshtein("dayly", "daily"), 1);
+        assert_eq!(levenshtein("mondy", "monday"), 1);
+        assert_eq!(levenshtein("", "abc"), 3);
+        assert_eq!(levenshtein("kitten", "sitting"), 3);
+    }
 }
@@ -3,15 +3,15 @@ use nom::{
     branch::alt,
     bytes::complete::{tag, take_while_m_n},
     character::complete::{self, digit1},
-    combinator::{fail, map_parser, map_res, opt},
-    sequence::preceded,
+    combinator::{fail, map_parser, map_res, opt, rest},
+    sequence::{pair, preceded},
     IResult,
 };
 use std::{ops::Add, str::FromStr, time::Duration};
 
 use super::{
-    Delay, RepeatExact, RoughInstant, SECONDS_IN_DAY, SECONDS_IN_HOUR, SECONDS_IN_MONTH,
-    SECONDS_IN_WEEK, SECONDS_IN_YEAR,
+    Delay, DelayPrecision, RepeatExact, RoughInstant, SECONDS_IN_DAY, SECONDS_IN_HOUR,
+    SECONDS_IN_MONTH, SECONDS_IN_WEEK, SECONDS_IN_YEAR,
 };
 
 /// Parse multiple ascii digits into I
@@ -85,21 +85,72 @@ where
     }
 }
 
+/// Parses a delay, adding months and years with the default [DelayPrecision]
 pub fn parse_duration(input: &str) -> IResult<&str, Delay> {
-    let mut seconds = false;
-    let mut result = None;
+    parse_duration_with(DelayPrecision::default())(input)
+}
 
-    let (input, duration) = opt(parse_year)(input)?;
-    let (input, _) = opt(complete::char(' '))(input)?;
-    result = reduce(result, duration, Duration::add);
+/// Parses a delay, adding months and years with the given `precision`
+pub fn parse_duration_with(precision: DelayPrecision) -> impl Fn(&str) -> IResult<&str, Delay> {
+    move |input| {
+        let (input, years) = opt(parse_year)(input)?;
+        let (input, _) = opt(complete::char(' '))(input)?;
+        let (input, months) = opt(parse_months)(input)?;
+        let (input, _) = opt(complete::char(' '))(input)?;
+
+        let (input, delay) = match (precision, years, months) {
+            (DelayPrecision::Approximate, years, months) | (_, years @ None, months @ None) => {
+                parse_duration_from(input, reduce(years, months, Duration::add))?
+            }
+            (DelayPrecision::Calendar, _, _) => {
+                let months = years.map_or(0, |y| y.as_secs() / SECONDS_IN_YEAR * 12)
+                    + months.map_or(0, |m| m.as_secs() / SECONDS_IN_MONTH);
+                let months = months.try_into().expect("months value must fit within u32");
+                let (input, delay) = opt(|input| parse_duration_from(input, None))(input)?;
+                let (days, seconds) = match delay {
+                    None => (0, 0),
+                    Some(Delay::Seconds(secs)) => {
+                        (secs / SECONDS_IN_DAY as i64, secs % SECONDS_IN_DAY as i64)
+                    }
+                    Some(Delay::Calendar { .. }) => unreachable!("parsed without months"),
+                    Some(delay) => (delay.whole_days().expect("delay is in whole days"), 0),
+                };
+                (
+                    input,
+                    Delay::Calendar {
+                        months,
+                        days,
+                        seconds,
+                    }
+                    .normalize(),
+                )
+            }
+        };
+        Ok((input, delay))
+    }
+}
 
-    let (input, duration) = opt(parse_months)(input)?;
-    let (input, _) = opt(complete::char(' '))(input)?;
-    result = reduce(result, duration, Duration::add);
+/// returns `true` if `input` starts with a further part of a delay, e.g. `3d` after `1w `
+fn starts_delay(input: &str) -> bool {
+    input.starts_with(|c: char| c.is_ascii_digit())
+}
 
-    let (input, duration) = opt(parse_weeks)(input)?;
+/// Parses the weeks and smaller intervals of a delay, adding them to the approximate `months`
+fn parse_duration_from(input: &str, months: Option<Duration>) -> IResult<&str, Delay> {
+    let mut seconds = false;
+    let mut result = months;
+
+    let (input, weeks) = opt(parse_weeks)(input)?;
     let (input, _) = opt(complete::char(' '))(input)?;
-    result = reduce(result, duration, Duration::add);
+    result = reduce(result, weeks, Duration::add);
+    if let (None, Some(weeks)) = (months, weeks) {
+        if !starts_delay(input) {
+            return Ok((
+                input,
+                Delay::Weeks((weeks.as_secs() / SECONDS_IN_WEEK) as i64).normalize(),
+            ));
+        }
+    }
 
     let (input, duration) = opt(parse_days)(input)?;
     let (input, _) = opt(complete::char(' '))(input)?;
@@ -135,7 +186,8 @@ pub fn parse_duration(input: &str) -> IResult<&str, Delay> {
                 (duration.as_secs() / SECONDS_IN_DAY)
                     .try_into()
                     .expect("days value must fit within i64"),
-            ),
+            )
+            .normalize(),
         )),
         (None, _) => fail(input),
     }
@@ -150,9 +202,15 @@ pub fn parse_rough_instant(input: &str) -> IResult<&str, RoughInstant> {
         parse_tomorrow,
         parse_date,
         parse_month,
+        parse_sun,
     ))(input)
 }
 
+/// Parses an instant with date and time, e.g. `2025-12-14 09:00`.
+pub fn parse_exact_instant(input: &str) -> IResult<&str, RoughInstant> {
+    rough_instant::parse_exact_date(input)
+}
+
 mod rough_instant {
     use crate::{
         nom_ext::alt_many,
@@ -163,12 +221,12 @@ mod rough_instant {
         branch::alt,
         bytes::complete::{tag, tag_no_case},
         character::complete,
-        combinator::{fail, opt},
+        combinator::{fail, map, opt},
         sequence::{pair, preceded, tuple},
         IResult,
     };
 
-    use super::{parse_digits, parse_time};
+    use super::{parse_digits, parse_duration, parse_time};
 
     pub fn parse_day_of_month(input: &str) -> IResult<&str, RoughInstant> {
         let (input, _) = pair(tag("dom"), complete::char(' '))(input)?;
@@ -186,10 +244,9 @@ mod rough_instant {
 
     pub fn parse_day_of_week(input: &str) -> IResult<&str, RoughInstant> {
         use nom::Parser;
-        let (input, day) = alt_many(
-            DAYS_IN_WEEK.map(|tag| tag_no_case::<&str, &str, nom::error::Error<&str>>(tag)),
-        )
-        .parse(input)?;
+        let (input, day) =
+            alt_many(DAYS_IN_WEEK.map(tag_no_case::<&str, &str, nom::error::Error<&str>>))
+                .parse(input)?;
 
         let Some(day) = DAYS_IN_WEEK
             .iter()
@@ -235,14 +292,15 @@ mod rough_instant {
     pub fn parse_month(input: &str) -> IResult<&str, RoughInstant> {
         use nom::Parser;
         let (input, month) =
-            alt_many(MONTHS.map(|tag| tag_no_case::<&str, &str, nom::error::Error<&str>>(tag)))
+            alt_many(MONTHS.map(tag_no_case::<&str, &str, nom::error::Error<&str>>))
                 .parse(input)?;
 
+        // months are 1 indexed, like in [chrono::Datelike::month]
         let Some(month) = MONTHS
             .iter()
             .enumerate()
             .find(|(_, it)| **it == month.to_ascii_lowercase())
-            .map(|(i, _)| i as u8)
+            .map(|(i, _)| i as u8 + 1)
         else {
             fail::<_, RoughInstant, _>(input)?;
             unreachable!();
@@ -252,7 +310,10 @@ mod rough_instant {
     }
 
     pub fn parse_date(input: &str) -> IResult<&str, RoughInstant> {
-        let (input, date) = opt(alt((parse_ymd, parse_day_month)))(input)?;
+        let (input, date) = opt(alt((
+            map(parse_ymd, |date| (date, false)),
+            map(parse_day_month, |date| (date, true)),
+        )))(input)?;
 
         let (input, time) = opt(preceded(complete::char(' '), parse_time))(input)?;
 
@@ -260,14 +321,73 @@ mod rough_instant {
             fail::<_, RoughInstant, _>(input)?;
         }
 
-        let date = date.unwrap_or_else(|| Local::now().date_naive());
+        let (date, year_less) = date.unwrap_or_else(|| (Local::now().date_naive(), false));
         let time = time.unwrap_or_else(|| NaiveTime::from_hms_opt(0, 0, 0).unwrap());
 
-        let datetime = NaiveDateTime::new(date, time);
+        let mut datetime = NaiveDateTime::new(date, time);
+        if year_less {
+            match roll_to_future(datetime, Local::now().naive_local()) {
+                Some(future) => datetime = future,
+                None => return fail(input),
+            }
+        }
 
         Ok((input, RoughInstant::Date { date: datetime }))
     }
 
+    /// Parses a date with year and a time, without filling in any missing parts
+    pub fn parse_exact_date(input: &str) -> IResult<&str, RoughInstant> {
+        let (input, (date, _, time)) = tuple((parse_ymd, complete::char(' '), parse_time))(input)?;
+        Ok((
+            input,
+            RoughInstant::Date {
+                date: NaiveDateTime::new(date, time),
+            },
+        ))
+    }
+
+    /// Parses `sunrise` or `sunset`, optionally followed by an offset like `-30m` or `+1h`
+    pub fn parse_sun(input: &str) -> IResult<&str, RoughInstant> {
+        let (input, sunrise) =
+            alt((map(tag("sunrise"), |_| true), map(tag("sunset"), |_| false)))(input)?;
+
+        let (input, offset) = opt(preceded(
+            complete::char(' '),
+            pair(
+                alt((complete::char('+'), complete::char('-'))),
+                parse_duration,
+            ),
+        ))(input)?;
+        let offset = match offset {
+            Some((sign, delay)) => {
+                let secs = delay.to_duration().num_seconds();
+                if sign == '-' {
+                    -secs
+                } else {
+                    secs
+                }
+            }
+            None => 0,
+        };
+
+        if sunrise {
+            Ok((input, RoughInstant::Sunrise { offset }))
+        } else {
+            Ok((input, RoughInstant::Sunset { offset }))
+        }
+    }
+
+    /// Moves a date without an explicit year to the next year, if it is before `now`.
+    ///
+    /// Returns `None` if the date does not exist next year, e.g. february 29th.
+    fn roll_to_future(datetime: NaiveDateTime, now: NaiveDateTime) -> Option<NaiveDateTime> {
+        if datetime < now {
+            datetime.with_year(datetime.year() + 1)
+        } else {
+            Some(datetime)
+        }
+    }
+
     fn parse_ymd(input: &str) -> IResult<&str, NaiveDate> {
         let dash = complete::char::<&str, nom::error::Error<&str>>('-');
 
@@ -308,6 +428,37 @@ mod rough_instant {
             Local::now().year()
         }
 
+        /// The year a date without year refers to
+        fn upcoming_year(month: u32, day: u32, time: NaiveTime) -> i32 {
+            let date = NaiveDate::from_ymd_opt(year(), month, day).unwrap();
+            if NaiveDateTime::new(date, time) < Local::now().naive_local() {
+                year() + 1
+            } else {
+                year()
+            }
+        }
+
+        #[test]
+        fn test_parse_sun() {
+            assert_eq!(
+                parse_sun("sunset"),
+                Ok(("", RoughInstant::Sunset { offset: 0 }))
+            );
+            assert_eq!(
+                parse_sun("sunset -30m"),
+                Ok(("", RoughInstant::Sunset { offset: -30 * 60 }))
+            );
+            assert_eq!(
+                parse_sun("sunrise +1h 15m"),
+                Ok(("", RoughInstant::Sunrise { offset: 75 * 60 }))
+            );
+            assert_eq!(
+                RoughInstant::Sunset { offset: -30 * 60 }.to_string(),
+                "30min before sunset"
+            );
+            assert!(parse_sun("sundown").is_err());
+        }
+
         #[test]
         fn test_parse_day_of_month() {
             for i in 1..=31 {
@@ -410,14 +561,14 @@ mod rough_instant {
             for (i, month) in MONTHS.iter().enumerate() {
                 assert_eq!(
                     parse_month(month),
-                    Ok(("", RoughInstant::Month { month: i as u8 }))
+                    Ok(("", RoughInstant::Month { month: i as u8 + 1 }))
                 );
                 let mut cap_month = String::with_capacity(month.len());
                 cap_month.push_str(&month.chars().next().unwrap().to_uppercase().to_string());
                 cap_month.push_str(&month[1..]);
                 assert_eq!(
                     parse_month(&cap_month),
-                    Ok(("", RoughInstant::Month { month: i as u8 }))
+                    Ok(("", RoughInstant::Month { month: i as u8 + 1 }))
                 );
             }
         }
@@ -430,7 +581,8 @@ mod rough_instant {
                     "",
                     RoughInstant::Date {
                         date: NaiveDateTime::new(
-                            NaiveDate::from_ymd_opt(year(), 12, 14).unwrap(),
+                            NaiveDate::from_ymd_opt(upcoming_year(12, 14, NaiveTime::MIN), 12, 14)
+                                .unwrap(),
                             NaiveTime::from_hms_opt(0, 0, 0).unwrap()
                         )
                     }
@@ -454,7 +606,12 @@ mod rough_instant {
                     "",
                     RoughInstant::Date {
                         date: NaiveDateTime::new(
-                            NaiveDate::from_ymd_opt(year(), 12, 14).unwrap(),
+                            NaiveDate::from_ymd_opt(
+                                upcoming_year(12, 14, NaiveTime::from_hms_opt(9, 13, 0).unwrap()),
+                                12,
+                                14
+                            )
+                            .unwrap(),
                             NaiveTime::from_hms_opt(9, 13, 0).unwrap()
                         )
                     }
@@ -482,6 +639,40 @@ mod rough_instant {
             );
         }
 
+        #[test]
+        fn test_year_less_date_rolls_over() {
+            let today = Local::now().date_naive();
+            let parse = |date: NaiveDate| {
+                let input = format!("{}-{}", date.day(), date.month());
+                match parse_date(&input) {
+                    Ok(("", RoughInstant::Date { date })) => date,
+                    other => panic!("failed to parse {input}: {other:?}"),
+                }
+            };
+
+            let yesterday = today.pred_opt().unwrap();
+            let expected = yesterday.with_year(yesterday.year() + 1).unwrap();
+            assert_eq!(parse(yesterday), NaiveDateTime::from(expected));
+
+            let tomorrow = today.succ_opt().unwrap();
+            assert_eq!(parse(tomorrow), NaiveDateTime::from(tomorrow));
+        }
+
+        #[test]
+        fn test_roll_to_future() {
+            let at = |y, m, d| {
+                NaiveDate::from_ymd_opt(y, m, d)
+                    .unwrap()
+                    .and_hms_opt(9, 0, 0)
+                    .unwrap()
+            };
+            let now = at(2024, 3, 2);
+            assert_eq!(roll_to_future(at(2024, 3, 1), now), Some(at(2025, 3, 1)));
+            assert_eq!(roll_to_future(at(2024, 3, 3), now), Some(at(2024, 3, 3)));
+            assert_eq!(roll_to_future(now, now), Some(now));
+            assert_eq!(roll_to_future(at(2024, 2, 29), now), None);
+        }
+
         #[test]
         fn test_parse_ymd() {
             assert_eq!(
@@ -492,6 +683,22 @@ mod rough_instant {
     }
 }
 
+/// parse a cron expression.
+///
+/// Valid: `cron <expression>`, the expression may be quoted.
+/// This consumes the rest of the input.
+pub fn parse_cron(input: &str) -> IResult<&str, String> {
+    let (input, _) = pair(tag("cron"), complete::char(' '))(input)?;
+    let (input, expr) = rest(input)?;
+
+    let expr = expr.trim().trim_matches('"').trim();
+    if super::cron_schedule(expr).is_err() {
+        return fail(input);
+    }
+
+    Ok((input, expr.to_string()))
+}
+
 pub fn parse_repeat_exact(input: &str) -> IResult<&str, RepeatExact> {
     use repeat_exact::*;
     alt((parse_day_of_month, parse_day_of_week, parse_daily))(input)
@@ -502,6 +709,7 @@ mod repeat_exact {
         bytes::complete::{tag, tag_no_case},
         character::complete,
         combinator::{fail, opt},
+        multi::many0,
         sequence::{pair, preceded},
         IResult,
     };
@@ -513,15 +721,35 @@ mod repeat_exact {
 
     use super::{parse_digits, parse_time};
 
-    /// parse [RepeatExact::Daily]
+    /// parse [RepeatExact::Daily], [RepeatExact::DailyTimes] or [RepeatExact::Window]
     ///
-    /// Valid: `daily[ <time-of-day>]`
+    /// Valid: `daily[ <time-of-day>[,<time-of-day>...]]` or `daily <time-of-day>-<time-of-day>`
     pub fn parse_daily(input: &str) -> IResult<&str, RepeatExact> {
         let (input, _) = tag("daily")(input)?;
 
         let (input, time) = opt(preceded(complete::char(' '), parse_time))(input)?;
 
-        Ok((input, RepeatExact::Daily { time }))
+        let Some(time) = time else {
+            return Ok((input, RepeatExact::Daily { time: None }));
+        };
+
+        let (input, end) = opt(preceded(complete::char('-'), parse_time))(input)?;
+        if let Some(end) = end {
+            if end <= time {
+                return fail(input);
+            }
+            return Ok((input, RepeatExact::Window { start: time, end }));
+        }
+
+        let (input, mut times) = many0(preceded(complete::char(','), parse_time))(input)?;
+        if times.is_empty() {
+            return Ok((input, RepeatExact::Daily { time: Some(time) }));
+        }
+
+        times.push(time);
+        times.sort();
+        times.dedup();
+        Ok((input, RepeatExact::DailyTimes { times }))
     }
 
     /// parse [RepeatExact::DayOfMonth].
@@ -547,10 +775,9 @@ mod repeat_exact {
     /// Valid: `<day-of-week>[ <time-of-day>]`
     pub fn parse_day_of_week(input: &str) -> IResult<&str, RepeatExact> {
         use nom::Parser;
-        let (input, day) = alt_many(
-            DAYS_IN_WEEK.map(|tag| tag_no_case::<&str, &str, nom::error::Error<&str>>(tag)),
-        )
-        .parse(input)?;
+        let (input, day) =
+            alt_many(DAYS_IN_WEEK.map(tag_no_case::<&str, &str, nom::error::Error<&str>>))
+                .parse(input)?;
 
         let Some(day) = DAYS_IN_WEEK
             .iter()
@@ -596,6 +823,41 @@ mod repeat_exact {
                 )),
                 "daily 14:59"
             );
+            assert_eq!(
+                parse_daily("daily 17:00,9:00"),
+                Ok((
+                    "",
+                    RepeatExact::DailyTimes {
+                        times: vec![
+                            NaiveTime::from_hms_opt(9, 0, 0).unwrap(),
+                            NaiveTime::from_hms_opt(17, 0, 0).unwrap()
+                        ]
+                    }
+                )),
+                "daily 17:00,9:00"
+            );
+            assert_eq!(
+                parse_daily("daily 9:00-11:30"),
+                Ok((
+                    "",
+                    RepeatExact::Window {
+                        start: NaiveTime::from_hms_opt(9, 0, 0).unwrap(),
+                        end: NaiveTime::from_hms_opt(11, 30, 0).unwrap()
+                    }
+                )),
+                "daily 9:00-11:30"
+            );
+            assert!(parse_daily("daily 11:00-9:00").is_err());
+            assert_eq!(
+                parse_daily("daily 9:00,9:00"),
+                Ok((
+                    "",
+                    RepeatExact::DailyTimes {
+                        times: vec![NaiveTime::from_hms_opt(9, 0, 0).unwrap()]
+                    }
+                )),
+                "daily 9:00,9:00"
+            );
         }
 
         #[test]
@@ -791,9 +1053,14 @@ mod test {
             Ok(("", Delay::Seconds(12 * SECONDS_IN_HOUR as i64)))
         );
         assert_eq!(parse_duration("12d"), Ok(("", Delay::Days(12))));
-        assert_eq!(parse_duration("12w"), Ok(("", Delay::Days(12 * 7))));
-        assert_eq!(parse_duration("12M"), Ok(("", Delay::Days(12 * 30))));
-        assert_eq!(parse_duration("12y"), Ok(("", Delay::Days(12 * 365))));
+        assert_eq!(parse_duration("12w"), Ok(("", Delay::Weeks(12))));
+        let months = |months| Delay::Calendar {
+            months,
+            days: 0,
+            seconds: 0,
+        };
+        assert_eq!(parse_duration("12M"), Ok(("", months(12))));
+        assert_eq!(parse_duration("12y"), Ok(("", months(144))));
 
         assert_eq!(
             parse_duration("3d 5s"),
@@ -804,6 +1071,62 @@ mod test {
         assert!(consume_all(parse_duration)("5d 3w").is_err());
     }
 
+    #[test]
+    fn test_parse_duration_unit() {
+        let approximate = parse_duration_with(DelayPrecision::Approximate);
+        assert!(matches!(approximate("2w"), Ok(("", Delay::Weeks(2)))));
+        assert!(matches!(approximate("1M"), Ok(("", Delay::Days(30)))));
+        assert!(matches!(approximate("3y"), Ok(("", Delay::Days(1095)))));
+        assert!(matches!(approximate("1w 2d"), Ok(("", Delay::Days(9)))));
+        assert!(matches!(approximate("1M 2d"), Ok(("", Delay::Days(32)))));
+        assert!(matches!(approximate("1y 1M"), Ok(("", Delay::Days(395)))));
+        assert!(matches!(
+            approximate("1M 1h"),
+            Ok(("", Delay::Seconds(2595600)))
+        ));
+    }
+
+    #[test]
+    fn test_parse_duration_calendar() {
+        let calendar = parse_duration_with(DelayPrecision::Calendar);
+        let months = |input| match calendar(input) {
+            Ok((
+                "",
+                Delay::Calendar {
+                    months,
+                    days,
+                    seconds,
+                },
+            )) => (months, days, seconds),
+            other => panic!("expected a calendar delay, got {other:?}"),
+        };
+        assert_eq!(months("1M"), (1, 0, 0));
+        assert_eq!(months("1y 2M"), (14, 0, 0));
+        assert_eq!(months("1M 36h"), (1, 1, 12 * SECONDS_IN_HOUR as i64));
+        assert_eq!(months("1M 1w 2d"), (1, 9, 0));
+        assert_eq!(
+            calendar("36h"),
+            Ok(("", Delay::Seconds(36 * SECONDS_IN_HOUR as i64)))
+        );
+        assert!(matches!(calendar("2w"), Ok(("", Delay::Weeks(2)))));
+        assert!(calendar("5").is_err());
+    }
+
+    #[test]
+    fn test_parse_cron() {
+        assert_eq!(
+            parse_cron("cron 0 9 * * 1-5"),
+            Ok(("", "0 9 * * 1-5".to_string()))
+        );
+        assert_eq!(
+            parse_cron("cron \"*/15 * * * *\""),
+            Ok(("", "*/15 * * * *".to_string()))
+        );
+        assert!(parse_cron("cron").is_err());
+        assert!(parse_cron("cron 0 9 * *").is_err());
+        assert!(parse_cron("cron 61 9 * * *").is_err());
+    }
+
     #[test]
     fn test_parse_duration_multiday_hours() {
         assert_eq!(
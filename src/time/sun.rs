@@ -0,0 +1,105 @@
+//! Sunrise and sunset times, used by [RoughInstant::Sunrise](super::RoughInstant::Sunrise)
+//! and [RoughInstant::Sunset](super::RoughInstant::Sunset).
+//!
+//! The times are computed with the sunrise equation and are accurate to about a minute.
+
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// A position on earth in degrees, north and east are positive
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Location {
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+/// Julian date of 2000-01-01 12:00 UTC
+const J2000: f64 = 2451545.0;
+/// Julian date of the unix epoch
+const JULIAN_UNIX_EPOCH: f64 = 2440587.5;
+/// Obliquity of the ecliptic in degrees
+const EARTH_TILT: f64 = 23.4397;
+/// Altitude of the sun's center at sunrise, accounting for refraction and the sun's radius
+const SUNRISE_ALTITUDE: f64 = -0.833;
+
+/// The time the sun rises at `location` on `date`.
+///
+/// Returns `None` if the sun does not rise or set on that day, e.g. during polar night.
+pub fn sunrise(date: NaiveDate, location: Location) -> Option<DateTime<Utc>> {
+    let (transit, half_day) = solar_day(date, location)?;
+    julian_to_utc(transit - half_day)
+}
+
+/// The time the sun sets at `location` on `date`, see [sunrise]
+pub fn sunset(date: NaiveDate, location: Location) -> Option<DateTime<Utc>> {
+    let (transit, half_day) = solar_day(date, location)?;
+    julian_to_utc(transit + half_day)
+}
+
+/// Julian date of solar noon and half the length of the day in days
+fn solar_day(date: NaiveDate, location: Location) -> Option<(f64, f64)> {
+    let noon = date.and_time(NaiveTime::from_hms_opt(12, 0, 0)?).and_utc();
+    let days = (noon.timestamp() as f64 / 86400.0 + JULIAN_UNIX_EPOCH - J2000).round();
+
+    let mean_noon = days - location.longitude / 360.0;
+    let anomaly = (357.5291 + 0.98560028 * mean_noon)
+        .rem_euclid(360.0)
+        .to_radians();
+    let center =
+        1.9148 * anomaly.sin() + 0.02 * (2.0 * anomaly).sin() + 0.0003 * (3.0 * anomaly).sin();
+    let ecliptic_longitude = (anomaly.to_degrees() + center + 180.0 + 102.9372)
+        .rem_euclid(360.0)
+        .to_radians();
+    let transit =
+        J2000 + mean_noon + 0.0053 * anomaly.sin() - 0.0069 * (2.0 * ecliptic_longitude).sin();
+
+    let declination = (ecliptic_longitude.sin() * EARTH_TILT.to_radians().sin()).asin();
+    let latitude = location.latitude.to_radians();
+    let cos_hour_angle = (SUNRISE_ALTITUDE.to_radians().sin() - latitude.sin() * declination.sin())
+        / (latitude.cos() * declination.cos());
+    if !(-1.0..=1.0).contains(&cos_hour_angle) {
+        return None;
+    }
+    Some((transit, cos_hour_angle.acos().to_degrees() / 360.0))
+}
+
+fn julian_to_utc(julian: f64) -> Option<DateTime<Utc>> {
+    let secs = ((julian - JULIAN_UNIX_EPOCH) * 86400.0).round() as i64;
+    DateTime::from_timestamp(secs, 0)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn assert_close(actual: Option<DateTime<Utc>>, expected: &str) {
+        let expected: DateTime<Utc> = expected.parse().unwrap();
+        let actual = actual.expect("the sun should rise and set");
+        assert!(
+            (actual - expected).num_seconds().abs() <= 120,
+            "{actual} is not close to {expected}"
+        );
+    }
+
+    #[test]
+    fn test_sunrise_sunset() {
+        let berlin = Location {
+            latitude: 52.52,
+            longitude: 13.405,
+        };
+        let midsummer = NaiveDate::from_ymd_opt(2024, 6, 21).unwrap();
+        assert_close(sunrise(midsummer, berlin), "2024-06-21T02:43:00Z");
+        assert_close(sunset(midsummer, berlin), "2024-06-21T19:33:00Z");
+
+        let winter = NaiveDate::from_ymd_opt(2024, 12, 21).unwrap();
+        assert_close(sunrise(winter, berlin), "2024-12-21T07:15:00Z");
+        assert_close(sunset(winter, berlin), "2024-12-21T14:54:00Z");
+
+        let tromso = Location {
+            latitude: 69.65,
+            longitude: 18.96,
+        };
+        assert_eq!(sunrise(midsummer, tromso), None);
+        assert_eq!(sunset(winter, tromso), None);
+    }
+}